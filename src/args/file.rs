@@ -0,0 +1,681 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    fs, io,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use chrono::{NaiveDate, Weekday};
+use flexi_logger::Level;
+use lettre::message::Mailbox;
+use serde::{de, Deserialize, Deserializer};
+
+use super::{validate, NotifierKind, StoreKind, TimeOfDay};
+use crate::{
+    message::DateLocale,
+    notification::{PushoverKey, SOUNDS},
+    strings::Language,
+    LogFormat, LogRotation, WebhookHeader,
+};
+
+/// Configuration loaded from a TOML file. Every value is optional, since values can also be given
+/// as command line arguments, which take precedence over the file.
+///
+/// # Examples
+///
+/// ```toml
+/// events_file = "events.json"
+/// events_backups = 1
+/// prune_past = true
+/// keep_days = 7
+/// store = "json"
+/// lock = true
+/// fetch_interval = 120
+/// interval_jitter = 10
+/// align_schedule = false
+/// notifier = "pushover"
+/// heartbeat = 24
+/// error_alert_after = 5
+/// max_new_per_cycle = 50
+/// id_change_threshold = 90
+/// digest_at = "18:00"
+/// notify_require_all = false
+/// dry_run = false
+/// strict = false
+/// allow_empty = false
+/// emit_ndjson = false
+/// notify_on_first_run = false
+/// http_listen = "127.0.0.1:8080"
+/// metrics_listen = "127.0.0.1:9090"
+///
+/// [log]
+/// level = "info"
+/// directory = "logs"
+/// format = "text"
+/// rotate = "size:10"
+/// keep = 7
+/// file = true
+///
+/// [pushover]
+/// api_key = "qwertyuiopasdfghjklzxcvbnm0123"
+/// group_key = "qwertyuiopasdfghjklzxcvbnm0123"
+/// priority = 1
+/// sound = "cosmic"
+/// device = "phone"
+///
+/// [[pushover.routes]]
+/// group_key = "asdfghjklzxcvbnmqwertyuiop0123"
+/// include = ["junior"]
+///
+/// [telegram]
+/// token = "123456:ABC-DEF1234ghIkl-zyx57W2v1u123ew11"
+/// chat_id = "-1001234567890"
+///
+/// [discord]
+/// webhook = "https://discord.com/api/webhooks/123456789012345678/abcDEF-123_xyz"
+///
+/// [webhook]
+/// url = "https://example.com/ktkbot"
+/// headers = ["Authorization: Bearer abc123"]
+///
+/// [email]
+/// host = "smtp.example.com"
+/// port = 587
+/// username = "ktkbot"
+/// password = "hunter2"
+/// from = "ktkbot <ktkbot@example.com>"
+/// to = "me@example.com"
+///
+/// [fetch]
+/// attempts = 3
+/// request_timeout = 30
+/// base_url = "https://ktk-tennis.halbooking.dk"
+/// page_delay = 250
+/// proxy = "http://proxy.example.com:3128"
+/// warm_up_url = "/newlook/default.asp"
+///
+/// [export]
+/// ical_out = "events.ics"
+/// csv_out = "events.csv"
+/// rss_out = "events.xml"
+/// markdown_out = "events.md"
+///
+/// [filter]
+/// include = ["Tennisskole"]
+/// exclude = ["Padel"]
+/// from = "2021-05-01"
+/// until = "2021-05-31"
+/// weekdays = ["sat", "sun"]
+/// min_advance = 12
+/// level = ["Øvet"]
+/// level_unknown_ok = false
+///
+/// [quiet_hours]
+/// from = "22:00"
+/// until = "07:00"
+///
+/// [selectors]
+/// event = 'tr[class="infinite-item"]'
+/// main_info = 'td[class="liste_wide min992"]'
+/// class_info = 'td[class="liste_wide min992 holdinfo"]'
+/// location = 'td[class="liste_wide min992 lokale"]'
+///
+/// [message]
+/// template = "<b>{title}</b>: {date}[ ({location})][ – {availability}]"
+/// title = "KTK"
+/// date_format = "%a %e %b %Y"
+/// date_locale = "en"
+/// lang = "da"
+///
+/// [[clubs]]
+/// base_url = "https://ktk-padel.halbooking.dk"
+/// events_file = "padel_events.json"
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PartialConfig {
+    pub log: PartialLogConfig,
+    pub pushover: PartialPushoverConfig,
+    pub telegram: PartialTelegramConfig,
+    pub discord: PartialDiscordConfig,
+    pub webhook: PartialWebhookConfig,
+    pub email: PartialEmailConfig,
+    pub fetch: PartialFetchConfig,
+    pub export: PartialExportConfig,
+    pub filter: PartialFilterConfig,
+    pub quiet_hours: PartialQuietHoursConfig,
+    pub selectors: PartialSelectorsConfig,
+    pub message: PartialMessageConfig,
+    /// Further clubs to fetch events from besides the one at `fetch.base_url`.
+    pub clubs: Option<Vec<PartialClubConfig>>,
+    pub events_file: Option<PathBuf>,
+    pub events_backups: Option<usize>,
+    pub prune_past: Option<bool>,
+    pub keep_days: Option<u64>,
+    #[serde(deserialize_with = "deserialize_from_str")]
+    pub store: Option<StoreKind>,
+    pub lock: Option<bool>,
+    pub fetch_interval: Option<u64>,
+    pub interval_jitter: Option<u8>,
+    pub align_schedule: Option<bool>,
+    pub notifier: Option<NotifierKind>,
+    pub heartbeat: Option<u64>,
+    pub error_alert_after: Option<u32>,
+    pub max_new_per_cycle: Option<usize>,
+    pub id_change_threshold: Option<u8>,
+    #[serde(deserialize_with = "deserialize_from_str")]
+    pub digest_at: Option<TimeOfDay>,
+    pub notify_require_all: Option<bool>,
+    pub dry_run: Option<bool>,
+    pub strict: Option<bool>,
+    pub allow_empty: Option<bool>,
+    pub emit_ndjson: Option<bool>,
+    pub notify_on_first_run: Option<bool>,
+    pub http_listen: Option<SocketAddr>,
+    pub metrics_listen: Option<SocketAddr>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PartialLogConfig {
+    #[serde(deserialize_with = "deserialize_from_str")]
+    pub level: Option<Level>,
+    pub directory: Option<PathBuf>,
+    pub format: Option<LogFormat>,
+    #[serde(deserialize_with = "deserialize_from_str")]
+    pub rotate: Option<LogRotation>,
+    pub keep: Option<usize>,
+    pub file: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PartialPushoverConfig {
+    pub api_key: Option<PushoverKey>,
+    pub group_key: Option<PushoverKey>,
+    pub priority: Option<i8>,
+    pub sound: Option<String>,
+    pub device: Option<String>,
+    pub routes: Option<Vec<PartialPushoverRoute>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PartialPushoverRoute {
+    pub group_key: PushoverKey,
+    #[serde(default)]
+    pub include: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PartialTelegramConfig {
+    pub token: Option<String>,
+    pub chat_id: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PartialDiscordConfig {
+    pub webhook: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PartialWebhookConfig {
+    pub url: Option<String>,
+    #[serde(deserialize_with = "deserialize_all_from_str")]
+    pub headers: Option<Vec<WebhookHeader>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PartialEmailConfig {
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    #[serde(deserialize_with = "deserialize_from_str")]
+    pub from: Option<Mailbox>,
+    #[serde(deserialize_with = "deserialize_from_str")]
+    pub to: Option<Mailbox>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PartialFetchConfig {
+    pub attempts: Option<u32>,
+    pub request_timeout: Option<u64>,
+    pub base_url: Option<String>,
+    pub page_delay: Option<u64>,
+    pub proxy: Option<String>,
+    pub warm_up_url: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PartialExportConfig {
+    pub ical_out: Option<PathBuf>,
+    pub csv_out: Option<PathBuf>,
+    pub rss_out: Option<PathBuf>,
+    pub markdown_out: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PartialFilterConfig {
+    pub include: Option<Vec<String>>,
+    pub exclude: Option<Vec<String>>,
+    #[serde(deserialize_with = "deserialize_from_str")]
+    pub from: Option<NaiveDate>,
+    #[serde(deserialize_with = "deserialize_from_str")]
+    pub until: Option<NaiveDate>,
+    pub weekdays: Option<Vec<Weekday>>,
+    pub min_advance: Option<u64>,
+    pub level: Option<Vec<String>>,
+    pub level_unknown_ok: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PartialQuietHoursConfig {
+    #[serde(deserialize_with = "deserialize_from_str")]
+    pub from: Option<TimeOfDay>,
+    #[serde(deserialize_with = "deserialize_from_str")]
+    pub until: Option<TimeOfDay>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PartialSelectorsConfig {
+    pub event: Option<String>,
+    pub main_info: Option<String>,
+    pub class_info: Option<String>,
+    pub location: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PartialClubConfig {
+    pub base_url: String,
+    pub events_file: PathBuf,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PartialMessageConfig {
+    pub template: Option<String>,
+    pub title: Option<String>,
+    pub date_format: Option<String>,
+    pub date_locale: Option<DateLocale>,
+    pub lang: Option<Language>,
+}
+
+/// Loads and validates a TOML configuration file.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read, is not valid TOML, contains unknown keys, or
+/// contains invalid values.
+pub fn load_config_file(path: impl AsRef<Path>) -> Result<PartialConfig, ConfigError> {
+    let contents = fs::read_to_string(path).map_err(ConfigError::Read)?;
+    let config: PartialConfig = toml::from_str(&contents).map_err(ConfigError::Parse)?;
+
+    if let Some(base_url) = &config.fetch.base_url {
+        validate::url(base_url.clone()).map_err(|message| ConfigError::Invalid {
+            field: "fetch.base_url",
+            message,
+        })?;
+    }
+
+    if let Some(attempts) = config.fetch.attempts {
+        validate::attempts(attempts.to_string()).map_err(|message| ConfigError::Invalid {
+            field: "fetch.attempts",
+            message,
+        })?;
+    }
+
+    if let Some(proxy) = &config.fetch.proxy {
+        validate::url(proxy.clone()).map_err(|message| ConfigError::Invalid {
+            field: "fetch.proxy",
+            message,
+        })?;
+    }
+
+    for club in config.clubs.iter().flatten() {
+        validate::url(club.base_url.clone()).map_err(|message| ConfigError::Invalid {
+            field: "clubs.base_url",
+            message,
+        })?;
+    }
+
+    let selectors = [
+        ("selectors.event", &config.selectors.event),
+        ("selectors.main_info", &config.selectors.main_info),
+        ("selectors.class_info", &config.selectors.class_info),
+        ("selectors.location", &config.selectors.location),
+    ];
+    for &(field, selector) in selectors.iter() {
+        if let Some(selector) = selector {
+            validate::css_selector(selector.to_string())
+                .map_err(|message| ConfigError::Invalid { field, message })?;
+        }
+    }
+
+    if let Some(template) = &config.message.template {
+        validate::message_template(template.clone()).map_err(|message| ConfigError::Invalid {
+            field: "message.template",
+            message,
+        })?;
+    }
+
+    if let Some(date_format) = &config.message.date_format {
+        validate::date_format(date_format.clone()).map_err(|message| ConfigError::Invalid {
+            field: "message.date_format",
+            message,
+        })?;
+    }
+
+    if let Some(sound) = &config.pushover.sound {
+        if !SOUNDS.contains(&sound.as_str()) {
+            return Err(ConfigError::Invalid {
+                field: "pushover.sound",
+                message: format!("Unknown sound - must be one of {}", SOUNDS.join(", ")),
+            });
+        }
+    }
+
+    if let Some(interval_jitter) = config.interval_jitter {
+        validate::percent(interval_jitter.to_string()).map_err(|message| ConfigError::Invalid {
+            field: "interval_jitter",
+            message,
+        })?;
+    }
+
+    if let Some(priority) = config.pushover.priority {
+        validate::pushover_priority(priority.to_string()).map_err(|message| {
+            ConfigError::Invalid {
+                field: "pushover.priority",
+                message,
+            }
+        })?;
+    }
+
+    if let Some(device) = &config.pushover.device {
+        validate::pushover_devices(device.clone()).map_err(|message| ConfigError::Invalid {
+            field: "pushover.device",
+            message,
+        })?;
+    }
+
+    if let Some(webhook) = &config.discord.webhook {
+        validate::discord_webhook(webhook.clone()).map_err(|message| ConfigError::Invalid {
+            field: "discord.webhook",
+            message,
+        })?;
+    }
+
+    if let Some(url) = &config.webhook.url {
+        validate::url(url.clone()).map_err(|message| ConfigError::Invalid {
+            field: "webhook.url",
+            message,
+        })?;
+    }
+
+    Ok(config)
+}
+
+/// Deserializes an optional string into any type implementing [`FromStr`], failing with the
+/// [`FromStr::Err`] message if the string is invalid.
+fn deserialize_from_str<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    let value: Option<String> = Option::deserialize(deserializer)?;
+    value
+        .map(|value| T::from_str(&value).map_err(de::Error::custom))
+        .transpose()
+}
+
+/// Like [`deserialize_from_str`], but for a list of values.
+fn deserialize_all_from_str<'de, D, T>(deserializer: D) -> Result<Option<Vec<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    let values: Option<Vec<String>> = Option::deserialize(deserializer)?;
+    values
+        .map(|values| {
+            values
+                .iter()
+                .map(|value| T::from_str(value).map_err(de::Error::custom))
+                .collect()
+        })
+        .transpose()
+}
+
+/// An error representing missing or invalid configuration.
+#[derive(Debug)]
+pub enum ConfigError {
+    Read(io::Error),
+    Parse(toml::de::Error),
+    Invalid {
+        field: &'static str,
+        message: String,
+    },
+    Missing {
+        field: &'static str,
+        argument: &'static str,
+    },
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Read(error) => write!(f, "Failed to read config file: {}", error),
+            Self::Parse(error) => write!(f, "Failed to parse config file: {}", error),
+            Self::Invalid { field, message } => {
+                write!(f, "Invalid `{}` in config file: {}", field, message)
+            }
+            Self::Missing { field, argument } => write!(
+                f,
+                "Missing {}. Pass it as {} on the command line or set `{}` in the config file \
+                 (the command line takes precedence).",
+                argument, argument, field
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use tempfile::NamedTempFile;
+
+    use super::*;
+
+    fn config_file(contents: &str) -> NamedTempFile {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(contents.as_bytes()).unwrap();
+        file
+    }
+
+    #[test]
+    fn load_config_file_parses_all_sections() {
+        let file = config_file(
+            r#"
+            events_file = "my_events.json"
+            fetch_interval = 60
+
+            [log]
+            level = "debug"
+            rotate = "size:10"
+
+            [pushover]
+            api_key = "qwertyuiopasdfghjklzxcvbnm0123"
+
+            [fetch]
+            base_url = "https://other-club.halbooking.dk"
+            "#,
+        );
+
+        let config = load_config_file(file.path()).unwrap();
+
+        assert_eq!(config.events_file, Some(PathBuf::from("my_events.json")));
+        assert_eq!(config.fetch_interval, Some(60));
+        assert_eq!(config.log.level, Some(Level::Debug));
+        assert_eq!(config.log.directory, None);
+        assert_eq!(config.log.rotate, Some(LogRotation::Size(10)));
+        assert_eq!(
+            config.pushover.api_key.unwrap().get(),
+            "qwertyuiopasdfghjklzxcvbnm0123"
+        );
+        assert!(config.pushover.group_key.is_none());
+        assert_eq!(
+            config.fetch.base_url.as_deref(),
+            Some("https://other-club.halbooking.dk")
+        );
+    }
+
+    #[test]
+    fn load_config_file_parses_clubs() {
+        let file = config_file(
+            r#"
+            [[clubs]]
+            base_url = "https://ktk-padel.halbooking.dk"
+            events_file = "padel_events.json"
+
+            [[clubs]]
+            base_url = "ktk-squash.halbooking.dk"
+            events_file = "squash_events.json"
+            "#,
+        );
+
+        assert!(matches!(
+            load_config_file(file.path()),
+            Err(ConfigError::Invalid {
+                field: "clubs.base_url",
+                ..
+            })
+        ));
+
+        let file = config_file(
+            "[[clubs]]\nbase_url = \"https://ktk-padel.halbooking.dk\"\n\
+             events_file = \"padel_events.json\"\n",
+        );
+        let clubs = load_config_file(file.path()).unwrap().clubs.unwrap();
+
+        assert_eq!(clubs.len(), 1);
+        assert_eq!(clubs[0].base_url, "https://ktk-padel.halbooking.dk");
+        assert_eq!(clubs[0].events_file, PathBuf::from("padel_events.json"));
+    }
+
+    #[test]
+    fn load_config_file_validates_pushover_keys() {
+        let file = config_file("[pushover]\napi_key = \"tooshort\"\n");
+
+        assert!(matches!(
+            load_config_file(file.path()),
+            Err(ConfigError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn load_config_file_validates_pushover_priority() {
+        let file = config_file("[pushover]\npriority = 3\n");
+
+        assert!(matches!(
+            load_config_file(file.path()),
+            Err(ConfigError::Invalid {
+                field: "pushover.priority",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn load_config_file_parses_webhook_headers() {
+        let file = config_file(
+            "[webhook]\nurl = \"https://example.com/ktkbot\"\nheaders = [\"X-Token: secret\"]\n",
+        );
+        assert_eq!(
+            load_config_file(file.path())
+                .unwrap()
+                .webhook
+                .headers
+                .unwrap()
+                .len(),
+            1
+        );
+
+        let file = config_file("[webhook]\nheaders = [\"X-Token\"]\n");
+        assert!(matches!(
+            load_config_file(file.path()),
+            Err(ConfigError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn load_config_file_validates_base_url() {
+        let file = config_file("[fetch]\nbase_url = \"ktk-tennis.halbooking.dk\"\n");
+
+        assert!(matches!(
+            load_config_file(file.path()),
+            Err(ConfigError::Invalid { .. })
+        ));
+    }
+
+    #[test]
+    fn load_config_file_validates_fetch_attempts() {
+        let file = config_file("[fetch]\nattempts = 5000\n");
+
+        assert!(matches!(
+            load_config_file(file.path()),
+            Err(ConfigError::Invalid {
+                field: "fetch.attempts",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn load_config_file_validates_selectors() {
+        let file = config_file("[selectors]\nevent = \"tr[class=\"\n");
+
+        assert!(matches!(
+            load_config_file(file.path()),
+            Err(ConfigError::Invalid {
+                field: "selectors.event",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn load_config_file_validates_message_template() {
+        let file = config_file("[message]\ntemplate = \"{title} at {time}\"\n");
+
+        assert!(matches!(
+            load_config_file(file.path()),
+            Err(ConfigError::Invalid {
+                field: "message.template",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn load_config_file_rejects_unknown_keys() {
+        let file = config_file("[log]\nlevle = \"debug\"\n");
+
+        assert!(matches!(
+            load_config_file(file.path()),
+            Err(ConfigError::Parse(_))
+        ));
+    }
+}
@@ -1,66 +1,767 @@
+mod file;
 mod validate;
 
-use std::{fmt, num::ParseIntError, str::FromStr, time::Duration};
+use std::{collections::HashSet, fmt, path::PathBuf, str::FromStr, time::Duration};
 
-use clap::{crate_authors, crate_name, crate_version, App, Arg, ArgMatches};
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveTime};
+use clap::{crate_authors, crate_name, crate_version, App, Arg, ArgMatches, ErrorKind, SubCommand};
+use flexi_logger::Level;
+use reqwest::header::{HeaderName, HeaderValue};
+use serde::Deserialize;
 
-use crate::{Config, LogConfig, PushoverConfig};
+use crate::{
+    club,
+    event::{
+        self,
+        fetch::DEFAULT_BASE_URL,
+        parse::{
+            Selectors, DEFAULT_CLASS_INFO_SELECTOR, DEFAULT_EVENT_SELECTOR,
+            DEFAULT_LOCATION_SELECTOR, DEFAULT_MAIN_INFO_SELECTOR,
+        },
+    },
+    log,
+    message::{self, MessageFormat},
+    notification::{PushoverKey, SOUNDS},
+    retry::RetryPolicy,
+    ClubConfig, Config, DiscordConfig, EmailConfig, ExportConfig, FetchConfig, FilterConfig,
+    LogConfig, NotifierConfig, PushoverConfig, PushoverRoute, QuietHours, StoreConfig,
+    TelegramConfig, WebhookConfig, WebhookHeader,
+};
+
+pub use self::file::{load_config_file, ConfigError, PartialConfig};
+use self::file::{
+    PartialClubConfig, PartialFilterConfig, PartialPushoverRoute, PartialQuietHoursConfig,
+};
 
 macro_rules! all_args {
     () => {{
         &[
+            Argument::ConfigFile.into(),
             Argument::LogLevel.into(),
+            Argument::Verbose.into(),
+            Argument::Quiet.into(),
             Argument::LogDirectory.into(),
+            Argument::LogFormat.into(),
+            Argument::LogRotate.into(),
+            Argument::LogKeep.into(),
+            Argument::NoLogFile.into(),
+            Argument::Notifier.into(),
             Argument::PushoverApiKey.into(),
             Argument::PushoverGroupKey.into(),
+            Argument::PushoverRoute.into(),
+            Argument::PushoverPriority.into(),
+            Argument::PushoverSound.into(),
+            Argument::PushoverDevice.into(),
+            Argument::TelegramToken.into(),
+            Argument::TelegramChatId.into(),
+            Argument::DiscordWebhook.into(),
+            Argument::WebhookUrl.into(),
+            Argument::WebhookHeader.into(),
+            Argument::SmtpHost.into(),
+            Argument::SmtpPort.into(),
+            Argument::SmtpUsername.into(),
+            Argument::SmtpPassword.into(),
+            Argument::EmailFrom.into(),
+            Argument::EmailTo.into(),
             Argument::EventsFile.into(),
+            Argument::EventsBackups.into(),
+            Argument::PrunePast.into(),
+            Argument::KeepDays.into(),
+            Argument::Store.into(),
+            Argument::NoLock.into(),
             Argument::FetchInterval.into(),
+            Argument::IntervalJitter.into(),
+            Argument::AlignSchedule.into(),
+            Argument::FetchAttempts.into(),
+            Argument::RequestTimeout.into(),
+            Argument::PageDelay.into(),
+            Argument::BaseUrl.into(),
+            Argument::Proxy.into(),
+            Argument::WarmUpUrl.into(),
+            Argument::EventSelector.into(),
+            Argument::MainInfoSelector.into(),
+            Argument::ClassInfoSelector.into(),
+            Argument::LocationSelector.into(),
+            Argument::IcalOut.into(),
+            Argument::CsvOut.into(),
+            Argument::RssOut.into(),
+            Argument::MarkdownOut.into(),
+            Argument::Include.into(),
+            Argument::Exclude.into(),
+            Argument::Weekdays.into(),
+            Argument::MinAdvance.into(),
+            Argument::Level.into(),
+            Argument::LevelUnknownOk.into(),
+            Argument::From.into(),
+            Argument::Until.into(),
+            Argument::Heartbeat.into(),
+            Argument::ErrorAlertAfter.into(),
+            Argument::MaxNewPerCycle.into(),
+            Argument::IdChangeThreshold.into(),
+            Argument::QuietFrom.into(),
+            Argument::QuietUntil.into(),
+            Argument::DigestAt.into(),
+            Argument::MessageTemplate.into(),
+            Argument::NotificationTitle.into(),
+            Argument::DateFormat.into(),
+            Argument::DateLocale.into(),
+            Argument::Lang.into(),
+            Argument::HttpListen.into(),
+            Argument::MetricsListen.into(),
+            Argument::NotifyRequireAll.into(),
+            Argument::DryRun.into(),
+            Argument::Once.into(),
+            Argument::FromFile.into(),
+            Argument::TestNotification.into(),
+            Argument::ValidateConfig.into(),
+            Argument::Strict.into(),
+            Argument::AllowEmpty.into(),
+            Argument::EmitNdjson.into(),
+            Argument::NotifyOnFirstRun.into(),
         ]
     }};
 }
 
-pub fn parse_config() -> Config {
-    let matches = App::new(crate_name!())
+const STATS: &str = "stats";
+
+fn app() -> App<'static, 'static> {
+    App::new(crate_name!())
         .version(crate_version!())
         .author(crate_authors!(",\n"))
-        .about("Sends KTK event push notifications.")
+        .about("Sends KTK event notifications.")
         .args(all_args!())
-        .get_matches();
+        .subcommand(
+            SubCommand::with_name(STATS)
+                .about(
+                    "Prints a summary of the stored events, e.g. their number by weekday and \
+                     level, without fetching anything.",
+                )
+                .arg(
+                    Arg::with_name(Argument::EventsFile.name())
+                        .short("e")
+                        .long("events-file")
+                        .value_name("FILE")
+                        .help("Sets the file to read events from.")
+                        .takes_value(true)
+                        .default_value("events.json"),
+                ),
+        )
+}
+
+/// What to do, as given on the command line.
+pub enum Command {
+    /// Run the bot with the given configuration.
+    Run(Box<Config>),
+    /// Print a summary of the events stored in the given file.
+    Stats(PathBuf),
+}
+
+pub fn parse_command() -> Command {
+    let matches = app().get_matches();
+    match matches.subcommand_matches(STATS) {
+        Some(stats) => Command::Stats(PathBuf::from(
+            stats.value_of_unchecked(Argument::EventsFile),
+        )),
+        None => Command::Run(Box::new(parse_config(&matches))),
+    }
+}
+
+fn parse_config(matches: &ArgMatches) -> Config {
+    let config_file = matches
+        .value_of_optional(Argument::ConfigFile)
+        .map(PathBuf::from);
+    let file = match &config_file {
+        Some(path) => load_config_file(path).unwrap_or_else(|error| exit_with(error)),
+        None => PartialConfig::default(),
+    };
+
+    let events_file: PathBuf = matches.merge_value(Argument::EventsFile, file.events_file);
+    let base_url: String = matches.merge_value(Argument::BaseUrl, file.fetch.base_url);
+    let clubs = merge_clubs(matches, &base_url, file.clubs).unwrap_or_else(|message| {
+        clap::Error::with_description(&message, ErrorKind::ArgumentConflict).exit()
+    });
+    let filter = merge_filter(matches, file.filter).unwrap_or_else(|message| {
+        clap::Error::with_description(&message, ErrorKind::ArgumentConflict).exit()
+    });
+    let quiet_hours = merge_quiet_hours(matches, file.quiet_hours).unwrap_or_else(|message| {
+        clap::Error::with_description(&message, ErrorKind::MissingRequiredArgument).exit()
+    });
 
     Config {
+        config_file,
         log: LogConfig {
-            level: matches.parse_value(Argument::LogLevel),
-            directory: matches.parse_value(Argument::LogDirectory),
+            level: merge_log_level(matches, file.log.level),
+            directory: matches.merge_value(Argument::LogDirectory, file.log.directory),
+            format: matches.merge_value(Argument::LogFormat, file.log.format),
+            rotation: matches.merge_value(Argument::LogRotate, file.log.rotate),
+            keep: matches.merge_value(Argument::LogKeep, file.log.keep),
+            to_file: !matches.is_present(Argument::NoLogFile.name())
+                && file.log.file.unwrap_or(true),
+        },
+        notifier: match matches.merge_value(Argument::Notifier, file.notifier) {
+            NotifierKind::Pushover => NotifierConfig::Pushover(PushoverConfig {
+                api_key: matches.merge_required_value(
+                    Argument::PushoverApiKey,
+                    file.pushover.api_key,
+                    "pushover.api_key",
+                    "<PUSHOVER API KEY>",
+                ),
+                group_key: matches.merge_required_value(
+                    Argument::PushoverGroupKey,
+                    file.pushover.group_key,
+                    "pushover.group_key",
+                    "<PUSHOVER GROUP KEY>",
+                ),
+                routes: matches.merge_values(
+                    Argument::PushoverRoute,
+                    file.pushover
+                        .routes
+                        .map(|routes| routes.into_iter().map(PushoverRoute::from).collect()),
+                ),
+                priority: matches
+                    .merge_optional_value(Argument::PushoverPriority, file.pushover.priority),
+                sound: matches.merge_optional_value(Argument::PushoverSound, file.pushover.sound),
+                device: matches
+                    .merge_optional_value(Argument::PushoverDevice, file.pushover.device),
+            }),
+            NotifierKind::Telegram => NotifierConfig::Telegram(TelegramConfig {
+                token: matches.merge_required_value(
+                    Argument::TelegramToken,
+                    file.telegram.token,
+                    "telegram.token",
+                    "--telegram-token",
+                ),
+                chat_id: matches.merge_required_value(
+                    Argument::TelegramChatId,
+                    file.telegram.chat_id,
+                    "telegram.chat_id",
+                    "--telegram-chat-id",
+                ),
+            }),
+            NotifierKind::Discord => NotifierConfig::Discord(DiscordConfig {
+                webhook_url: matches.merge_required_value(
+                    Argument::DiscordWebhook,
+                    file.discord.webhook,
+                    "discord.webhook",
+                    "--discord-webhook",
+                ),
+            }),
+            NotifierKind::Webhook => NotifierConfig::Webhook(WebhookConfig {
+                url: matches.merge_required_value(
+                    Argument::WebhookUrl,
+                    file.webhook.url,
+                    "webhook.url",
+                    "--webhook-url",
+                ),
+                headers: matches.merge_values(Argument::WebhookHeader, file.webhook.headers),
+            }),
+            NotifierKind::Email => NotifierConfig::Email(EmailConfig {
+                host: matches.merge_required_value(
+                    Argument::SmtpHost,
+                    file.email.host,
+                    "email.host",
+                    "--smtp-host",
+                ),
+                port: matches.merge_value(Argument::SmtpPort, file.email.port),
+                username: matches.merge_required_value(
+                    Argument::SmtpUsername,
+                    file.email.username,
+                    "email.username",
+                    "--smtp-username",
+                ),
+                password: matches.merge_required_value(
+                    Argument::SmtpPassword,
+                    file.email.password,
+                    "email.password",
+                    "--smtp-password",
+                ),
+                from: matches.merge_required_value(
+                    Argument::EmailFrom,
+                    file.email.from,
+                    "email.from",
+                    "--email-from",
+                ),
+                to: matches.merge_required_value(
+                    Argument::EmailTo,
+                    file.email.to,
+                    "email.to",
+                    "--email-to",
+                ),
+            }),
+        },
+        fetch: FetchConfig {
+            retry: RetryPolicy::new(
+                matches.merge_value(Argument::FetchAttempts, file.fetch.attempts),
+                Duration::from_secs(1),
+                2.0,
+            ),
+            timeout: matches
+                .merge_value(
+                    Argument::RequestTimeout,
+                    file.fetch.request_timeout.map(DurationWrapper::from_secs),
+                )
+                .into(),
+            base_url,
+            proxy: matches.merge_optional_value(Argument::Proxy, file.fetch.proxy),
+            warm_up_url: matches.merge_optional_value(Argument::WarmUpUrl, file.fetch.warm_up_url),
+            page_delay: Duration::from_millis(
+                matches.merge_value(Argument::PageDelay, file.fetch.page_delay),
+            ),
+            selectors: Selectors {
+                event: matches.merge_value(Argument::EventSelector, file.selectors.event),
+                main_info: matches
+                    .merge_value(Argument::MainInfoSelector, file.selectors.main_info),
+                class_info: matches
+                    .merge_value(Argument::ClassInfoSelector, file.selectors.class_info),
+                location: matches.merge_value(Argument::LocationSelector, file.selectors.location),
+            },
+        },
+        export: ExportConfig {
+            ical_file: matches.merge_optional_value(Argument::IcalOut, file.export.ical_out),
+            csv_file: matches.merge_optional_value(Argument::CsvOut, file.export.csv_out),
+            rss_file: matches.merge_optional_value(Argument::RssOut, file.export.rss_out),
+            markdown_file: matches
+                .merge_optional_value(Argument::MarkdownOut, file.export.markdown_out),
         },
-        pushover: PushoverConfig {
-            api_key: matches.parse_value(Argument::PushoverApiKey),
-            group_key: matches.parse_value(Argument::PushoverGroupKey),
+        filter,
+        store: match matches.merge_value(Argument::Store, file.store) {
+            StoreKind::Json => StoreConfig::Json(events_file.clone()),
+            StoreKind::Sqlite(path) => StoreConfig::Sqlite(path),
         },
-        events_file: matches.parse_value(Argument::EventsFile),
-        fetch_interval: matches
-            .parse_value::<DurationWrapper>(Argument::FetchInterval)
-            .into(),
+        lock: !matches.is_present(Argument::NoLock.name()) && file.lock.unwrap_or(true),
+        events_file,
+        clubs,
+        events_backups: matches.merge_value(Argument::EventsBackups, file.events_backups),
+        prune_past: merge_prune_past(matches, file.prune_past, file.keep_days),
+        fetch_interval: merge_fetch_interval(matches, file.fetch_interval),
+        interval_jitter: matches.merge_value(Argument::IntervalJitter, file.interval_jitter),
+        align_schedule: matches.is_present(Argument::AlignSchedule.name())
+            || file.align_schedule.unwrap_or(false),
+        heartbeat: matches
+            .merge_optional_value::<u64>(Argument::Heartbeat, file.heartbeat)
+            .map(|hours| Duration::from_secs(hours.saturating_mul(3600))),
+        error_alert_after: matches.merge_value(Argument::ErrorAlertAfter, file.error_alert_after),
+        max_new_per_cycle: matches
+            .merge_optional_value(Argument::MaxNewPerCycle, file.max_new_per_cycle),
+        id_change_threshold: matches
+            .merge_value(Argument::IdChangeThreshold, file.id_change_threshold),
+        quiet_hours,
+        digest_at: matches
+            .merge_optional_value::<TimeOfDay>(Argument::DigestAt, file.digest_at)
+            .map(|time| time.0),
+        message: MessageFormat {
+            template: matches.merge_value(Argument::MessageTemplate, file.message.template),
+            title: matches.merge_optional_value(Argument::NotificationTitle, file.message.title),
+            date_format: matches.merge_value(Argument::DateFormat, file.message.date_format),
+            date_locale: matches.merge_value(Argument::DateLocale, file.message.date_locale),
+            language: matches.merge_value(Argument::Lang, file.message.lang),
+        },
+        http_listen: matches.merge_optional_value(Argument::HttpListen, file.http_listen),
+        metrics_listen: matches.merge_optional_value(Argument::MetricsListen, file.metrics_listen),
+        notify_require_all: matches.is_present(Argument::NotifyRequireAll.name())
+            || file.notify_require_all.unwrap_or(false),
+        dry_run: matches.is_present(Argument::DryRun.name()) || file.dry_run.unwrap_or(false),
+        once: matches.is_present(Argument::Once.name()),
+        from_file: matches
+            .value_of_optional(Argument::FromFile)
+            .map(PathBuf::from),
+        test_notification: matches.is_present(Argument::TestNotification.name()),
+        validate_config: matches.is_present(Argument::ValidateConfig.name()),
+        strict: matches.is_present(Argument::Strict.name()) || file.strict.unwrap_or(false),
+        allow_empty: matches.is_present(Argument::AllowEmpty.name())
+            || file.allow_empty.unwrap_or(false),
+        emit_ndjson: matches.is_present(Argument::EmitNdjson.name())
+            || file.emit_ndjson.unwrap_or(false),
+        notify_on_first_run: matches.is_present(Argument::NotifyOnFirstRun.name())
+            || file.notify_on_first_run.unwrap_or(false),
     }
 }
 
+/// Reloads the settings that are safe to change while running, i.e. the fetch interval, the
+/// interval jitter, the filters, and the quiet hours, from the config file of `config`, if any.
+/// Command line arguments still take precedence over the file. Other settings, e.g. keys and the
+/// events file, are only read at startup.
+///
+/// # Errors
+///
+/// Returns an error if the config file cannot be loaded or the reloaded settings are invalid, in
+/// which case `config` is left unchanged.
+pub fn reload_config(config: &mut Config) -> Result<(), ConfigError> {
+    let file = match &config.config_file {
+        Some(path) => load_config_file(path)?,
+        None => return Ok(()),
+    };
+    // The command line is the same as at startup, so it is known to be valid
+    let matches = app().get_matches();
+
+    let filter = merge_filter(&matches, file.filter).map_err(|message| ConfigError::Invalid {
+        field: "filter",
+        message,
+    })?;
+    let quiet_hours =
+        merge_quiet_hours(&matches, file.quiet_hours).map_err(|message| ConfigError::Invalid {
+            field: "quiet_hours",
+            message,
+        })?;
+
+    config.fetch_interval = merge_fetch_interval(&matches, file.fetch_interval);
+    config.interval_jitter = matches.merge_value(Argument::IntervalJitter, file.interval_jitter);
+    config.filter = filter;
+    config.quiet_hours = quiet_hours;
+    Ok(())
+}
+
+/// Merges the further clubs given on the command line, i.e. every --base-url and --events-file
+/// after the first, or in the config file.
+///
+/// # Errors
+///
+/// Returns an error if the further base URLs and events files on the command line do not pair up,
+/// or if two clubs have the same name.
+fn merge_clubs(
+    matches: &ArgMatches,
+    base_url: &str,
+    file_clubs: Option<Vec<PartialClubConfig>>,
+) -> Result<Vec<ClubConfig>, String> {
+    let further_values = |argument| -> Vec<&str> {
+        match matches.values_of_optional(argument) {
+            Some(values) if matches.is_explicit(argument) => values.skip(1).collect(),
+            _ => Vec::new(),
+        }
+    };
+    let base_urls = further_values(Argument::BaseUrl);
+    let events_files = further_values(Argument::EventsFile);
+
+    let clubs: Vec<ClubConfig> = if base_urls.is_empty() && events_files.is_empty() {
+        file_clubs
+            .unwrap_or_default()
+            .into_iter()
+            .map(|club| ClubConfig {
+                base_url: club.base_url,
+                events_file: club.events_file,
+            })
+            .collect()
+    } else if base_urls.len() == events_files.len() {
+        base_urls
+            .into_iter()
+            .zip(events_files)
+            .map(|(base_url, events_file)| ClubConfig {
+                base_url: String::from(base_url),
+                events_file: PathBuf::from(events_file),
+            })
+            .collect()
+    } else {
+        return Err(String::from(
+            "Each --base-url after the first needs an --events-file of its own",
+        ));
+    };
+
+    let mut names = HashSet::new();
+    names.insert(club::name(base_url));
+    for other in &clubs {
+        let name = club::name(other.base_url());
+        if !names.insert(name.clone()) {
+            return Err(format!(
+                "Clubs must have different hosts, but {} is given twice",
+                name
+            ));
+        }
+    }
+    Ok(clubs)
+}
+
+/// Merges the log level given on the command line and in the config file. An explicit
+/// `--log-level` takes precedence, and otherwise the level in the config file, or the default
+/// level, is made more verbose by each `-v` and less verbose by each `-q`.
+fn merge_log_level(matches: &ArgMatches, file_value: Option<Level>) -> Level {
+    let level = matches.merge_value(Argument::LogLevel, file_value);
+    if matches.is_explicit(Argument::LogLevel) {
+        return level;
+    }
+
+    let verbosity = matches.occurrences_of(Argument::Verbose.name()) as i64
+        - matches.occurrences_of(Argument::Quiet.name()) as i64;
+    log::adjust_level(level, verbosity)
+}
+
+/// Merges whether to prune past events and for how many days to keep them into how long to keep
+/// past events for, or `None` if past events are not pruned.
+fn merge_prune_past(
+    matches: &ArgMatches,
+    file_prune_past: Option<bool>,
+    file_keep_days: Option<u64>,
+) -> Option<Duration> {
+    if !matches.is_present(Argument::PrunePast.name()) && !file_prune_past.unwrap_or(false) {
+        return None;
+    }
+    let keep_days: u64 = matches
+        .merge_optional_value(Argument::KeepDays, file_keep_days)
+        .unwrap_or(0);
+    Some(Duration::from_secs(keep_days.saturating_mul(24 * 3600)))
+}
+
+fn merge_fetch_interval(matches: &ArgMatches, file_value: Option<u64>) -> Duration {
+    matches
+        .merge_value(
+            Argument::FetchInterval,
+            file_value.map(DurationWrapper::from_secs),
+        )
+        .into()
+}
+
+/// Merges the filters given on the command line and in the config file.
+///
+/// # Errors
+///
+/// Returns an error if the `until` date is before the `from` date, or if either date is too far in
+/// the future to be represented.
+fn merge_filter(matches: &ArgMatches, file: PartialFilterConfig) -> Result<FilterConfig, String> {
+    let from: Option<NaiveDate> = matches.merge_optional_value(Argument::From, file.from);
+    let until: Option<NaiveDate> = matches.merge_optional_value(Argument::Until, file.until);
+    if let (Some(from), Some(until)) = (from, until) {
+        if until < from {
+            return Err(format!(
+                "The --until date {} is before the --from date {}",
+                until, from
+            ));
+        }
+    }
+
+    Ok(FilterConfig {
+        includes: matches.merge_values(Argument::Include, file.include),
+        excludes: matches.merge_values(Argument::Exclude, file.exclude),
+        from: from.map(start_of_day).transpose()?,
+        until: until
+            .map(|until| {
+                until
+                    .succ_opt()
+                    .ok_or_else(|| format!("The --until date {} is too far in the future", until))
+                    .and_then(start_of_day)
+            })
+            .transpose()?,
+        weekdays: matches
+            .merge_values(Argument::Weekdays, file.weekdays)
+            .into_iter()
+            .collect(),
+        min_advance: matches
+            .merge_optional_value::<u64>(Argument::MinAdvance, file.min_advance)
+            .map(|hours| Duration::from_secs(hours.saturating_mul(3600))),
+        levels: matches.merge_values(Argument::Level, file.level),
+        level_unknown_ok: matches.is_present(Argument::LevelUnknownOk.name())
+            || file.level_unknown_ok.unwrap_or(false),
+    })
+}
+
+/// Merges the quiet hours given on the command line and in the config file.
+///
+/// # Errors
+///
+/// Returns an error if only one end of the quiet hours is given.
+fn merge_quiet_hours(
+    matches: &ArgMatches,
+    file: PartialQuietHoursConfig,
+) -> Result<Option<QuietHours>, String> {
+    let from: Option<TimeOfDay> = matches.merge_optional_value(Argument::QuietFrom, file.from);
+    let until: Option<TimeOfDay> = matches.merge_optional_value(Argument::QuietUntil, file.until);
+    match (from, until) {
+        (Some(from), Some(until)) => Ok(Some(QuietHours {
+            from: from.0,
+            until: until.0,
+        })),
+        (None, None) => Ok(None),
+        _ => Err(String::from(
+            "Quiet hours need both --quiet-from and --quiet-until",
+        )),
+    }
+}
+
+/// Gets the time at which the given day starts in Denmark.
+///
+/// # Errors
+///
+/// Returns an error if the start of the day cannot be represented with its UTC offset.
+fn start_of_day(date: NaiveDate) -> Result<DateTime<FixedOffset>, String> {
+    // Denmark never switches between summer and winter time at midnight
+    date.and_hms_opt(0, 0, 0)
+        .and_then(event::local_date_time)
+        .ok_or_else(|| format!("The date {} is too far in the future", date))
+}
+
+fn exit_with(error: ConfigError) -> ! {
+    clap::Error::with_description(&error.to_string(), ErrorKind::InvalidValue).exit()
+}
+
+#[derive(Clone, Copy)]
 enum Argument {
+    ConfigFile,
     LogLevel,
+    Verbose,
+    Quiet,
     LogDirectory,
+    LogFormat,
+    LogRotate,
+    LogKeep,
+    NoLogFile,
+    Notifier,
     PushoverApiKey,
     PushoverGroupKey,
+    PushoverRoute,
+    PushoverPriority,
+    PushoverSound,
+    PushoverDevice,
+    TelegramToken,
+    TelegramChatId,
+    DiscordWebhook,
+    WebhookUrl,
+    WebhookHeader,
+    SmtpHost,
+    SmtpPort,
+    SmtpUsername,
+    SmtpPassword,
+    EmailFrom,
+    EmailTo,
     EventsFile,
+    EventsBackups,
+    PrunePast,
+    KeepDays,
+    Store,
+    NoLock,
     FetchInterval,
+    IntervalJitter,
+    AlignSchedule,
+    FetchAttempts,
+    RequestTimeout,
+    PageDelay,
+    Proxy,
+    WarmUpUrl,
+    BaseUrl,
+    EventSelector,
+    MainInfoSelector,
+    ClassInfoSelector,
+    LocationSelector,
+    IcalOut,
+    CsvOut,
+    RssOut,
+    MarkdownOut,
+    Include,
+    Exclude,
+    Weekdays,
+    MinAdvance,
+    Level,
+    LevelUnknownOk,
+    From,
+    Until,
+    Heartbeat,
+    ErrorAlertAfter,
+    MaxNewPerCycle,
+    IdChangeThreshold,
+    QuietFrom,
+    QuietUntil,
+    DigestAt,
+    MessageTemplate,
+    NotificationTitle,
+    DateFormat,
+    DateLocale,
+    Lang,
+    HttpListen,
+    MetricsListen,
+    NotifyRequireAll,
+    DryRun,
+    Once,
+    FromFile,
+    TestNotification,
+    ValidateConfig,
+    Strict,
+    AllowEmpty,
+    EmitNdjson,
+    NotifyOnFirstRun,
 }
 
 impl Argument {
     fn name(&self) -> &'static str {
         match self {
+            Self::ConfigFile => "ConfigFile",
             Self::LogLevel => "LogLevel",
+            Self::Verbose => "Verbose",
+            Self::Quiet => "Quiet",
             Self::LogDirectory => "LogDirectory",
+            Self::LogFormat => "LogFormat",
+            Self::LogRotate => "LogRotate",
+            Self::LogKeep => "LogKeep",
+            Self::NoLogFile => "NoLogFile",
+            Self::Notifier => "Notifier",
             Self::PushoverApiKey => "PushoverApiKey",
             Self::PushoverGroupKey => "PushoverGroupKey",
+            Self::PushoverRoute => "PushoverRoute",
+            Self::PushoverPriority => "PushoverPriority",
+            Self::PushoverSound => "PushoverSound",
+            Self::PushoverDevice => "PushoverDevice",
+            Self::TelegramToken => "TelegramToken",
+            Self::TelegramChatId => "TelegramChatId",
+            Self::DiscordWebhook => "DiscordWebhook",
+            Self::WebhookUrl => "WebhookUrl",
+            Self::WebhookHeader => "WebhookHeader",
+            Self::SmtpHost => "SmtpHost",
+            Self::SmtpPort => "SmtpPort",
+            Self::SmtpUsername => "SmtpUsername",
+            Self::SmtpPassword => "SmtpPassword",
+            Self::EmailFrom => "EmailFrom",
+            Self::EmailTo => "EmailTo",
             Self::EventsFile => "EventsFile",
+            Self::EventsBackups => "EventsBackups",
+            Self::PrunePast => "PrunePast",
+            Self::KeepDays => "KeepDays",
+            Self::Store => "Store",
+            Self::NoLock => "NoLock",
             Self::FetchInterval => "FetchInterval",
+            Self::IntervalJitter => "IntervalJitter",
+            Self::AlignSchedule => "AlignSchedule",
+            Self::FetchAttempts => "FetchAttempts",
+            Self::RequestTimeout => "RequestTimeout",
+            Self::PageDelay => "PageDelay",
+            Self::Proxy => "Proxy",
+            Self::WarmUpUrl => "WarmUpUrl",
+            Self::BaseUrl => "BaseUrl",
+            Self::EventSelector => "EventSelector",
+            Self::MainInfoSelector => "MainInfoSelector",
+            Self::ClassInfoSelector => "ClassInfoSelector",
+            Self::LocationSelector => "LocationSelector",
+            Self::IcalOut => "IcalOut",
+            Self::CsvOut => "CsvOut",
+            Self::RssOut => "RssOut",
+            Self::MarkdownOut => "MarkdownOut",
+            Self::Include => "Include",
+            Self::Exclude => "Exclude",
+            Self::Weekdays => "Weekdays",
+            Self::MinAdvance => "MinAdvance",
+            Self::Level => "Level",
+            Self::LevelUnknownOk => "LevelUnknownOk",
+            Self::From => "From",
+            Self::Until => "Until",
+            Self::Heartbeat => "Heartbeat",
+            Self::ErrorAlertAfter => "ErrorAlertAfter",
+            Self::MaxNewPerCycle => "MaxNewPerCycle",
+            Self::IdChangeThreshold => "IdChangeThreshold",
+            Self::QuietFrom => "QuietFrom",
+            Self::QuietUntil => "QuietUntil",
+            Self::DigestAt => "DigestAt",
+            Self::MessageTemplate => "MessageTemplate",
+            Self::NotificationTitle => "NotificationTitle",
+            Self::DateFormat => "DateFormat",
+            Self::DateLocale => "DateLocale",
+            Self::Lang => "Lang",
+            Self::HttpListen => "HttpListen",
+            Self::MetricsListen => "MetricsListen",
+            Self::NotifyRequireAll => "NotifyRequireAll",
+            Self::DryRun => "DryRun",
+            Self::Once => "Once",
+            Self::FromFile => "FromFile",
+            Self::TestNotification => "TestNotification",
+            Self::ValidateConfig => "ValidateConfig",
+            Self::Strict => "Strict",
+            Self::AllowEmpty => "AllowEmpty",
+            Self::EmitNdjson => "EmitNdjson",
+            Self::NotifyOnFirstRun => "NotifyOnFirstRun",
         }
     }
 }
@@ -68,6 +769,15 @@ impl Argument {
 impl From<Argument> for Arg<'_, '_> {
     fn from(argument: Argument) -> Self {
         match argument {
+            Argument::ConfigFile => Arg::with_name(argument.name())
+                .short("c")
+                .long("config")
+                .value_name("FILE")
+                .help(
+                    "Sets a TOML file to read configuration from. Command line arguments take \
+                     precedence over values in the file.",
+                )
+                .takes_value(true),
             Argument::LogLevel => Arg::with_name(argument.name())
                 .short("l")
                 .long("log-level")
@@ -76,6 +786,22 @@ impl From<Argument> for Arg<'_, '_> {
                 .takes_value(true)
                 .possible_values(&["error", "warn", "info", "debug", "trace"])
                 .default_value("info"),
+            Argument::Verbose => Arg::with_name(argument.name())
+                .short("v")
+                .long("verbose")
+                .help(
+                    "Logs more, i.e. at debug level for -v and trace level for -vv. Ignored if \
+                     --log-level is given.",
+                )
+                .multiple(true),
+            Argument::Quiet => Arg::with_name(argument.name())
+                .short("q")
+                .long("quiet")
+                .help(
+                    "Logs less, i.e. at warn level for -q and error level for -qq. Ignored if \
+                     --log-level is given.",
+                )
+                .multiple(true),
             Argument::LogDirectory => Arg::with_name(argument.name())
                 .short("d")
                 .long("log-directory")
@@ -84,34 +810,622 @@ impl From<Argument> for Arg<'_, '_> {
                 .takes_value(true)
                 .default_value("logs")
                 .validator(validate::length(1, 64)),
+            Argument::LogFormat => Arg::with_name(argument.name())
+                .long("log-format")
+                .value_name("FORMAT")
+                .help(
+                    "Sets the format of log lines, both in log files and on stdout. The json \
+                     format writes one JSON object per line.",
+                )
+                .takes_value(true)
+                .possible_values(&["text", "json"])
+                .default_value("text"),
+            Argument::LogRotate => Arg::with_name(argument.name())
+                .long("log-rotate")
+                .value_name("ROTATION")
+                .help(
+                    "Sets when to start a new log file: \"daily\", or \"size:<MB>\" for when the \
+                     current log file exceeds the given number of megabytes.",
+                )
+                .takes_value(true)
+                .default_value("daily")
+                .validator(validate::log_rotation),
+            Argument::LogKeep => Arg::with_name(argument.name())
+                .long("log-keep")
+                .value_name("N")
+                .help("Sets the number of log files to keep. Older log files are deleted.")
+                .takes_value(true)
+                .default_value("7")
+                .validator(validate::uint),
+            Argument::NoLogFile => Arg::with_name(argument.name()).long("no-log-file").help(
+                "Logs only to stdout without writing log files, e.g. when stdout is already \
+                     captured by journald or Docker.",
+            ),
+            Argument::Notifier => Arg::with_name(argument.name())
+                .short("n")
+                .long("notifier")
+                .value_name("NOTIFIER")
+                .help("Sets the service to send notifications through.")
+                .takes_value(true)
+                .possible_values(&["pushover", "telegram", "discord", "email", "webhook"])
+                .default_value("pushover"),
             Argument::PushoverApiKey => Arg::with_name(argument.name())
                 .value_name("PUSHOVER API KEY")
                 .help("The API key to use for sending Pushover notifications.")
-                .required(true)
                 .index(1)
                 .validator(validate::pushover_key),
             Argument::PushoverGroupKey => Arg::with_name(argument.name())
                 .value_name("PUSHOVER GROUP KEY")
                 .help("The group key to use for sending Pushover notifications.")
-                .required(true)
                 .index(2)
                 .validator(validate::pushover_key),
+            Argument::PushoverRoute => Arg::with_name(argument.name())
+                .long("pushover-route")
+                .value_name("GROUP KEY[:KEYWORDS]")
+                .help(
+                    "Also sends Pushover notifications about events with a title containing any \
+                     of the comma-separated keywords to another group. Events matching no route \
+                     are sent to the default group. Can be given multiple times.",
+                )
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .validator(validate::pushover_route),
+            Argument::PushoverPriority => Arg::with_name(argument.name())
+                .short("p")
+                .long("priority")
+                .value_name("PRIORITY")
+                .help(
+                    "Sets the priority of Pushover notifications, from -2 (lowest) to 2 \
+                     (emergency). Emergency notifications are repeated every minute for an hour \
+                     until acknowledged.",
+                )
+                .takes_value(true)
+                .allow_hyphen_values(true)
+                .validator(validate::pushover_priority),
+            Argument::PushoverSound => Arg::with_name(argument.name())
+                .long("pushover-sound")
+                .value_name("SOUND")
+                .help("Sets the sound of Pushover notifications instead of the account default.")
+                .takes_value(true)
+                .possible_values(SOUNDS),
+            Argument::PushoverDevice => Arg::with_name(argument.name())
+                .long("pushover-device")
+                .value_name("DEVICES")
+                .help(
+                    "Sets a comma-separated list of devices to send Pushover notifications to \
+                     instead of all devices.",
+                )
+                .takes_value(true)
+                .validator(validate::pushover_devices),
+            Argument::TelegramToken => Arg::with_name(argument.name())
+                .long("telegram-token")
+                .value_name("TOKEN")
+                .help("The bot token to use for sending Telegram notifications.")
+                .takes_value(true)
+                .validator(validate::length(1, 128)),
+            Argument::TelegramChatId => Arg::with_name(argument.name())
+                .long("telegram-chat-id")
+                .value_name("CHAT ID")
+                .help("The chat to send Telegram notifications to.")
+                .takes_value(true)
+                .validator(validate::length(1, 64)),
+            Argument::DiscordWebhook => Arg::with_name(argument.name())
+                .long("discord-webhook")
+                .value_name("URL")
+                .help("The webhook URL to use for sending Discord notifications.")
+                .takes_value(true)
+                .validator(validate::discord_webhook),
+            Argument::WebhookUrl => Arg::with_name(argument.name())
+                .long("webhook-url")
+                .value_name("URL")
+                .help(
+                    "The URL to post notifications to as JSON with the webhook notifier. \
+                     Changes are posted as the added, removed, and changed events.",
+                )
+                .takes_value(true)
+                .validator(validate::url),
+            Argument::WebhookHeader => Arg::with_name(argument.name())
+                .long("webhook-header")
+                .value_name("NAME:VALUE")
+                .help(
+                    "Adds a header to send with every request to the webhook, e.g. \
+                     \"Authorization: Bearer <TOKEN>\". Can be given multiple times.",
+                )
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .validator(validate::webhook_header),
+            Argument::SmtpHost => Arg::with_name(argument.name())
+                .long("smtp-host")
+                .value_name("HOST")
+                .help("The SMTP server to use for sending email notifications.")
+                .takes_value(true)
+                .validator(validate::length(1, 253)),
+            Argument::SmtpPort => Arg::with_name(argument.name())
+                .long("smtp-port")
+                .value_name("PORT")
+                .help("The port of the SMTP server. STARTTLS is always used.")
+                .takes_value(true)
+                .default_value("587")
+                .validator(validate::port),
+            Argument::SmtpUsername => Arg::with_name(argument.name())
+                .long("smtp-username")
+                .value_name("USERNAME")
+                .help("The username to log in to the SMTP server with.")
+                .takes_value(true)
+                .validator(validate::length(1, 256)),
+            Argument::SmtpPassword => Arg::with_name(argument.name())
+                .long("smtp-password")
+                .value_name("PASSWORD")
+                .help("The password to log in to the SMTP server with.")
+                .takes_value(true)
+                .validator(validate::length(1, 256)),
+            Argument::EmailFrom => Arg::with_name(argument.name())
+                .long("email-from")
+                .value_name("ADDRESS")
+                .help("The address to send email notifications from.")
+                .takes_value(true)
+                .validator(validate::mailbox),
+            Argument::EmailTo => Arg::with_name(argument.name())
+                .long("email-to")
+                .value_name("ADDRESS")
+                .help("The address to send email notifications to.")
+                .takes_value(true)
+                .validator(validate::mailbox),
             Argument::EventsFile => Arg::with_name(argument.name())
                 .short("e")
                 .long("events-file")
                 .value_name("FILE")
-                .help("Sets the file to save events to.")
+                .help(
+                    "Sets the file to save events to. Given again for each further --base-url, \
+                     sets the file to save the events of that club to.",
+                )
                 .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
                 .default_value("events.json")
                 .validator(validate::length(1, 64)),
+            Argument::EventsBackups => Arg::with_name(argument.name())
+                .long("events-backups")
+                .value_name("COUNT")
+                .help(
+                    "Sets the number of previous versions of the events file to keep as backups, \
+                     e.g. events.json.bak. Set to 0 to disable backups.",
+                )
+                .takes_value(true)
+                .default_value("1")
+                .validator(validate::uint),
+            Argument::PrunePast => Arg::with_name(argument.name()).long("prune-past").help(
+                "Drops events that have taken place from the stored events, so the events \
+                     file does not keep growing. Pruned events are not notified about as removed, \
+                     nor as new if they are listed again.",
+            ),
+            Argument::KeepDays => Arg::with_name(argument.name())
+                .long("keep-days")
+                .value_name("DAYS")
+                .help(
+                    "Sets the number of days that past events are kept for before being pruned \
+                     with --prune-past. Defaults to 0, i.e. pruning events as soon as they have \
+                     started.",
+                )
+                .takes_value(true)
+                .validator(validate::uint),
+            Argument::Store => Arg::with_name(argument.name())
+                .long("store")
+                .value_name("STORE")
+                .help(
+                    "Sets where to store known events: \"json\" for the events file, or \
+                     \"sqlite:<FILE>\" for an SQLite database that also keeps the history of \
+                     events.",
+                )
+                .takes_value(true)
+                .default_value("json")
+                .validator(validate::store),
+            Argument::NoLock => Arg::with_name(argument.name()).long("no-lock").help(
+                "Does not lock the stored events. By default, they are locked while running, \
+                     so a second instance using the same events exits instead of overwriting \
+                     them.",
+            ),
             Argument::FetchInterval => Arg::with_name(argument.name())
                 .short("f")
                 .long("fetch-interval")
-                .value_name("SECONDS")
-                .help("Sets the delay in between fetching events.")
+                .value_name("DURATION")
+                .help(
+                    "Sets the delay in between fetching events, in seconds or with the unit s, \
+                     m, or h, e.g. 90, 2m, or 1h.",
+                )
                 .takes_value(true)
                 .default_value("120")
+                .validator(validate::duration),
+            Argument::IntervalJitter => Arg::with_name(argument.name())
+                .long("interval-jitter")
+                .value_name("PERCENT")
+                .help(
+                    "Randomly varies the delay in between fetching events by up to the given \
+                     percentage, e.g. 10 with a fetch interval of 120 seconds gives delays \
+                     between 108 and 132 seconds.",
+                )
+                .takes_value(true)
+                .default_value("0")
+                .validator(validate::percent),
+            Argument::AlignSchedule => Arg::with_name(argument.name()).long("align-schedule").help(
+                "Fetches events at multiples of the fetch interval on the clock, e.g. at :00, \
+                     :05, :10, and so on with a fetch interval of 300 seconds, instead of the \
+                     fetch interval after the last fetch. Ignores --interval-jitter.",
+            ),
+            Argument::FetchAttempts => Arg::with_name(argument.name())
+                .long("fetch-attempts")
+                .value_name("ATTEMPTS")
+                .help("Sets the number of attempts made to fetch a page before giving up.")
+                .takes_value(true)
+                .default_value("3")
+                .validator(validate::attempts),
+            Argument::RequestTimeout => Arg::with_name(argument.name())
+                .long("request-timeout")
+                .value_name("SECONDS")
+                .help("Sets the timeout for each request made when fetching events.")
+                .takes_value(true)
+                .default_value("30")
+                .validator(validate::uint),
+            Argument::PageDelay => Arg::with_name(argument.name())
+                .long("page-delay")
+                .value_name("MS")
+                .help(
+                    "Sets the minimum delay in milliseconds between requests for successive \
+                     pages of events. A small random jitter is added to each delay.",
+                )
+                .takes_value(true)
+                .default_value("250")
+                .validator(validate::uint),
+            Argument::Proxy => Arg::with_name(argument.name())
+                .long("proxy")
+                .value_name("URL")
+                .help(
+                    "Sets a proxy to fetch events through. Otherwise the proxy in the HTTP_PROXY \
+                     and HTTPS_PROXY environment variables is used, if any.",
+                )
+                .takes_value(true)
+                .validator(validate::url),
+            Argument::WarmUpUrl => Arg::with_name(argument.name())
+                .long("warm-up-url")
+                .value_name("URL")
+                .help(
+                    "Sets a URL to request before fetching events, e.g. a landing page that sets \
+                     the cookies needed for all events to be listed. Relative URLs are resolved \
+                     against the base URL of each club.",
+                )
+                .takes_value(true),
+            Argument::BaseUrl => Arg::with_name(argument.name())
+                .short("u")
+                .long("base-url")
+                .value_name("URL")
+                .help(
+                    "Sets the URL of the halbooking site to fetch events from. Can be given more \
+                     than once to follow several clubs, each with its own --events-file.",
+                )
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .default_value(DEFAULT_BASE_URL)
+                .validator(validate::url),
+            Argument::EventSelector => Arg::with_name(argument.name())
+                .long("event-selector")
+                .value_name("SELECTOR")
+                .help("Sets the CSS selector for the row of each event on a page of events.")
+                .takes_value(true)
+                .default_value(DEFAULT_EVENT_SELECTOR)
+                .validator(validate::css_selector),
+            Argument::MainInfoSelector => Arg::with_name(argument.name())
+                .long("main-info-selector")
+                .value_name("SELECTOR")
+                .help(
+                    "Sets the CSS selector for the cell with the title, date, and time of an \
+                     event within its row.",
+                )
+                .takes_value(true)
+                .default_value(DEFAULT_MAIN_INFO_SELECTOR)
+                .validator(validate::css_selector),
+            Argument::ClassInfoSelector => Arg::with_name(argument.name())
+                .long("class-info-selector")
+                .value_name("SELECTOR")
+                .help(
+                    "Sets the CSS selector for the cell with class info such as the price and \
+                     instructor of an event within its row.",
+                )
+                .takes_value(true)
+                .default_value(DEFAULT_CLASS_INFO_SELECTOR)
+                .validator(validate::css_selector),
+            Argument::LocationSelector => Arg::with_name(argument.name())
+                .long("location-selector")
+                .value_name("SELECTOR")
+                .help(
+                    "Sets the CSS selector for the cell with the location of an event within its \
+                     row.",
+                )
+                .takes_value(true)
+                .default_value(DEFAULT_LOCATION_SELECTOR)
+                .validator(validate::css_selector),
+            Argument::IcalOut => Arg::with_name(argument.name())
+                .long("ical-out")
+                .value_name("FILE")
+                .help("Sets a file to export events to in iCalendar format after each fetch.")
+                .takes_value(true)
+                .validator(validate::length(1, 64)),
+            Argument::CsvOut => Arg::with_name(argument.name())
+                .long("csv-out")
+                .value_name("FILE")
+                .help("Sets a file to export events to in CSV format after each fetch.")
+                .takes_value(true)
+                .validator(validate::length(1, 64)),
+            Argument::RssOut => Arg::with_name(argument.name())
+                .long("rss-out")
+                .value_name("FILE")
+                .help("Sets a file to export events to as an RSS feed after each fetch.")
+                .takes_value(true)
+                .validator(validate::length(1, 64)),
+            Argument::MarkdownOut => Arg::with_name(argument.name())
+                .long("markdown-out")
+                .value_name("FILE")
+                .help("Sets a file to export events to as a Markdown table after each fetch.")
+                .takes_value(true)
+                .validator(validate::length(1, 64)),
+            Argument::Include => Arg::with_name(argument.name())
+                .long("include")
+                .value_name("KEYWORD")
+                .help(
+                    "Only notifies about events with a title containing the keyword, ignoring \
+                     case. Can be given multiple times to match any of several keywords.",
+                )
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .validator(validate::length(1, 64)),
+            Argument::Exclude => Arg::with_name(argument.name())
+                .long("exclude")
+                .value_name("KEYWORD")
+                .help(
+                    "Never notifies about events with a title containing the keyword, ignoring \
+                     case. Can be given multiple times.",
+                )
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .validator(validate::length(1, 64)),
+            Argument::Weekdays => Arg::with_name(argument.name())
+                .long("weekdays")
+                .value_name("mon,tue,...")
+                .help(
+                    "Only notifies about events on the given comma-separated weekdays, e.g. \
+                     sat,sun.",
+                )
+                .takes_value(true)
+                .use_delimiter(true)
+                .validator(validate::weekday),
+            Argument::MinAdvance => Arg::with_name(argument.name())
+                .long("min-advance")
+                .value_name("HOURS")
+                .help(
+                    "Never notifies about events starting sooner than the given number of hours \
+                     from now.",
+                )
+                .takes_value(true)
+                .validator(validate::uint),
+            Argument::Level => Arg::with_name(argument.name())
+                .long("level")
+                .value_name("LEVEL")
+                .help(
+                    "Only notifies about events at the level, e.g. Øvet, as given by the \
+                     \"Niveau\" of their class info, ignoring case. Can be given multiple times \
+                     to match any of several levels.",
+                )
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .validator(validate::length(1, 64)),
+            Argument::LevelUnknownOk => Arg::with_name(argument.name())
+                .long("level-unknown-ok")
+                .help("Also notifies about events without a level when filtering with --level."),
+            Argument::From => Arg::with_name(argument.name())
+                .long("from")
+                .value_name("YYYY-MM-DD")
+                .help("Only notifies about events on or after the date.")
+                .takes_value(true)
+                .validator(validate::date),
+            Argument::Until => Arg::with_name(argument.name())
+                .long("until")
+                .value_name("YYYY-MM-DD")
+                .help("Only notifies about events on or before the date.")
+                .takes_value(true)
+                .validator(validate::date),
+            Argument::Heartbeat => Arg::with_name(argument.name())
+                .long("heartbeat")
+                .value_name("HOURS")
+                .help(
+                    "Sends a low priority notification when no other notification has been sent \
+                     for the given number of hours, to show that the bot is still running.",
+                )
+                .takes_value(true)
                 .validator(validate::uint),
+            Argument::ErrorAlertAfter => Arg::with_name(argument.name())
+                .long("error-alert-after")
+                .value_name("FETCHES")
+                .help(
+                    "Sends a notification when fetching events has failed the given number of \
+                     times in a row, and another when it works again. 0 never sends one.",
+                )
+                .takes_value(true)
+                .default_value("5")
+                .validator(validate::uint),
+            Argument::MaxNewPerCycle => Arg::with_name(argument.name())
+                .long("max-new-per-cycle")
+                .value_name("N")
+                .help(
+                    "Sends a single summary instead of notifying about the new events when more \
+                     than the given number are found at once, e.g. because the site changed.",
+                )
+                .takes_value(true)
+                .validator(validate::uint),
+            Argument::IdChangeThreshold => Arg::with_name(argument.name())
+                .long("id-change-threshold")
+                .value_name("PERCENT")
+                .help(
+                    "Stores the events without notifying, and logs an error, when more than the \
+                     given percentage of the fetched events are new while there were stored \
+                     events, since the site has then likely changed its event ids. 0 never does.",
+                )
+                .takes_value(true)
+                .default_value("90")
+                .validator(validate::percent),
+            Argument::QuietFrom => Arg::with_name(argument.name())
+                .long("quiet-from")
+                .value_name("HH:MM")
+                .help(
+                    "Holds notifications from the given local time until --quiet-until, and then \
+                     sends them as a single notification.",
+                )
+                .takes_value(true)
+                .validator(validate::time),
+            Argument::QuietUntil => Arg::with_name(argument.name())
+                .long("quiet-until")
+                .value_name("HH:MM")
+                .help("Ends quiet hours started by --quiet-from at the given local time.")
+                .takes_value(true)
+                .validator(validate::time),
+            Argument::DigestAt => Arg::with_name(argument.name())
+                .long("digest-at")
+                .value_name("HH:MM")
+                .help(
+                    "Sends new events as a single daily notification grouped by date at the given \
+                     local time, instead of a notification per change.",
+                )
+                .takes_value(true)
+                .validator(validate::time),
+            Argument::MessageTemplate => Arg::with_name(argument.name())
+                .long("message-template")
+                .value_name("TEMPLATE")
+                .help(
+                    "Sets the template for the line about each event in notifications. The \
+                     placeholders {title}, {date}, {class_info}, {class_number}, {location}, \
+                     {instructor}, {price}, {availability}, {club}, and {url} are replaced with \
+                     the details of the event, and text in square brackets is left out if any \
+                     placeholder in it is empty.",
+                )
+                .takes_value(true)
+                .default_value(message::DEFAULT_TEMPLATE)
+                .validator(validate::message_template),
+            Argument::NotificationTitle => Arg::with_name(argument.name())
+                .long("notification-title")
+                .value_name("TITLE")
+                .help(
+                    "Sets the title of notifications about changes, instead of a title \
+                     describing the changes.",
+                )
+                .takes_value(true),
+            Argument::DateFormat => Arg::with_name(argument.name())
+                .long("date-format")
+                .value_name("FORMAT")
+                .help(
+                    "Sets the format of dates in notifications, using the specifiers of chrono's \
+                     strftime, e.g. %d/%m %H:%M.",
+                )
+                .takes_value(true)
+                .default_value(message::DEFAULT_DATE_FORMAT)
+                .validator(validate::date_format),
+            Argument::DateLocale => Arg::with_name(argument.name())
+                .long("date-locale")
+                .value_name("LOCALE")
+                .help("Sets the language of the names of weekdays and months in dates.")
+                .takes_value(true)
+                .possible_values(&["en", "da"])
+                .default_value("en"),
+            Argument::Lang => Arg::with_name(argument.name())
+                .long("lang")
+                .value_name("LANGUAGE")
+                .help(
+                    "Sets the language of the titles, headings, and other fixed texts of \
+                     notifications about events.",
+                )
+                .takes_value(true)
+                .possible_values(&["da", "en"])
+                .default_value("da"),
+            Argument::HttpListen => Arg::with_name(argument.name())
+                .long("http-listen")
+                .value_name("ADDR:PORT")
+                .help(
+                    "Serves the stored events as JSON on /events and a health check on /healthz \
+                     over HTTP at the given address, e.g. 127.0.0.1:8080.",
+                )
+                .takes_value(true)
+                .validator(validate::socket_address),
+            Argument::MetricsListen => Arg::with_name(argument.name())
+                .long("metrics-listen")
+                .value_name("ADDR:PORT")
+                .help(
+                    "Serves Prometheus metrics on /metrics over HTTP at the given address, e.g. \
+                     127.0.0.1:9090.",
+                )
+                .takes_value(true)
+                .validator(validate::socket_address),
+            Argument::NotifyRequireAll => Arg::with_name(argument.name())
+                .long("notify-require-all")
+                .help(
+                    "Fails the cycle when any notifier fails to send a notification. By default, \
+                     the cycle only fails when all notifiers, e.g. of --pushover-route, fail.",
+                ),
+            Argument::DryRun => Arg::with_name(argument.name()).long("dry-run").help(
+                "Logs notifications instead of sending them. Events are still fetched, \
+                     compared, and saved as usual.",
+            ),
+            Argument::Once => Arg::with_name(argument.name()).long("once").help(
+                "Fetches, compares, and notifies once and then exits, e.g. for running from \
+                     cron. Exits with a non-zero code if anything fails.",
+            ),
+            Argument::FromFile => Arg::with_name(argument.name())
+                .long("from-file")
+                .value_name("HTML")
+                .help(
+                    "Parses events from the given HTML file, e.g. a downloaded page of events, \
+                     instead of fetching them, prints them as JSON, and exits.",
+                )
+                .takes_value(true),
+            Argument::TestNotification => Arg::with_name(argument.name())
+                .long("test-notification")
+                .help(
+                    "Sends a single test notification through the configured notifier instead of \
+                     fetching anything, prints the response, and exits. Exits with a non-zero \
+                     code if the notification cannot be sent.",
+                ),
+            Argument::ValidateConfig => Arg::with_name(argument.name())
+                .long("validate-config")
+                .help(
+                    "Checks the configuration without fetching or sending anything, i.e. that \
+                     the directories are writable, the HTTP client can be built, the selectors \
+                     compile, and the notifier can be created, prints a summary, and exits. \
+                     Exits with a non-zero code if any check fails.",
+                ),
+            Argument::Strict => Arg::with_name(argument.name()).long("strict").help(
+                "Exits when fetched events cannot be parsed. By default, the error is logged \
+                     and fetching is tried again after the fetch interval.",
+            ),
+            Argument::AllowEmpty => Arg::with_name(argument.name()).long("allow-empty").help(
+                "Accepts fetching no events while there are stored events, and notifies about \
+                     all of them as removed. By default, such a fetch is assumed to be a \
+                     maintenance page, and the cycle is skipped with a warning. With several \
+                     clubs, the stored events of a club fetching none are kept instead.",
+            ),
+            Argument::EmitNdjson => Arg::with_name(argument.name()).long("emit-ndjson").help(
+                "Writes each new, removed, and changed event to stdout as a line of JSON, \
+                     alongside notifications, e.g. for piping into jq. Combine with --log-format \
+                     json to make every line on stdout JSON.",
+            ),
+            Argument::NotifyOnFirstRun => Arg::with_name(argument.name())
+                .long("notify-on-first-run")
+                .help(
+                    "Notifies about all current events as new when there are no stored events \
+                     yet, e.g. for a new subscriber. By default, the events found on the first run \
+                     are only stored, and only later changes are notified about.",
+                ),
         }
     }
 }
@@ -119,6 +1433,13 @@ impl From<Argument> for Arg<'_, '_> {
 trait ArgMatchesExt {
     fn value_of_unchecked(&self, name: Argument) -> &str;
 
+    fn value_of_optional(&self, name: Argument) -> Option<&str>;
+
+    fn values_of_optional(&self, name: Argument) -> Option<clap::Values<'_>>;
+
+    /// Whether the argument was given explicitly on the command line, i.e. not just defaulted.
+    fn is_explicit(&self, name: Argument) -> bool;
+
     fn parse_value<T>(&self, name: Argument) -> T
     where
         T: FromStr,
@@ -127,21 +1448,241 @@ trait ArgMatchesExt {
         let value = self.value_of_unchecked(name);
         T::from_str(value).unwrap()
     }
+
+    fn parse_optional_value<T>(&self, name: Argument) -> Option<T>
+    where
+        T: FromStr,
+        T::Err: fmt::Debug,
+    {
+        self.value_of_optional(name)
+            .map(|value| T::from_str(value).unwrap())
+    }
+
+    /// Gets the value of the argument if it was given explicitly on the command line, otherwise
+    /// `file_value` if present, and otherwise the default value of the argument.
+    fn merge_value<T>(&self, name: Argument, file_value: Option<T>) -> T
+    where
+        T: FromStr,
+        T::Err: fmt::Debug,
+    {
+        match file_value {
+            Some(value) if !self.is_explicit(name) => value,
+            _ => self.parse_value(name),
+        }
+    }
+
+    /// Gets the value of the argument if it was given on the command line, otherwise
+    /// `file_value`.
+    fn merge_optional_value<T>(&self, name: Argument, file_value: Option<T>) -> Option<T>
+    where
+        T: FromStr,
+        T::Err: fmt::Debug,
+    {
+        self.parse_optional_value(name).or(file_value)
+    }
+
+    /// Gets all values of the argument if it was given on the command line, otherwise
+    /// `file_values` if present, and otherwise no values.
+    fn merge_values<T>(&self, name: Argument, file_values: Option<Vec<T>>) -> Vec<T>
+    where
+        T: FromStr,
+        T::Err: fmt::Debug,
+    {
+        match self.values_of_optional(name) {
+            Some(values) => values.map(|value| T::from_str(value).unwrap()).collect(),
+            None => file_values.unwrap_or_default(),
+        }
+    }
+
+    /// Like [`merge_optional_value`][`ArgMatchesExt::merge_optional_value`], but exits with an
+    /// error describing both the command line `argument` and the config file `field` if the value
+    /// is given in neither place.
+    fn merge_required_value<T>(
+        &self,
+        name: Argument,
+        file_value: Option<T>,
+        field: &'static str,
+        argument: &'static str,
+    ) -> T
+    where
+        T: FromStr,
+        T::Err: fmt::Debug,
+    {
+        self.merge_optional_value(name, file_value)
+            .unwrap_or_else(|| exit_with(ConfigError::Missing { field, argument }))
+    }
 }
 
 impl ArgMatchesExt for ArgMatches<'_> {
     fn value_of_unchecked(&self, argument: Argument) -> &str {
         self.value_of(argument.name()).unwrap()
     }
+
+    fn value_of_optional(&self, argument: Argument) -> Option<&str> {
+        self.value_of(argument.name())
+    }
+
+    fn is_explicit(&self, argument: Argument) -> bool {
+        self.occurrences_of(argument.name()) > 0
+    }
+
+    fn values_of_optional(&self, argument: Argument) -> Option<clap::Values<'_>> {
+        self.values_of(argument.name())
+    }
+}
+
+/// The services that notifications can be sent through.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotifierKind {
+    Pushover,
+    Telegram,
+    Discord,
+    Email,
+    Webhook,
+}
+
+impl FromStr for NotifierKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pushover" => Ok(Self::Pushover),
+            "telegram" => Ok(Self::Telegram),
+            "discord" => Ok(Self::Discord),
+            "email" => Ok(Self::Email),
+            "webhook" => Ok(Self::Webhook),
+            _ => Err(format!("Unknown notifier: {}", s)),
+        }
+    }
+}
+
+impl FromStr for PushoverRoute {
+    type Err = String;
+
+    /// Parses a route from a group key optionally followed by a colon and a comma-separated list
+    /// of keywords, e.g. `qwertyuiopasdfghjklzxcvbnm0123:junior,ungdom`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (group_key, keywords) = s.split_once(':').unwrap_or((s, ""));
+        let group_key = PushoverKey::new(group_key)
+            .map_err(|error| format!("Invalid group key in route: {}", error))?;
+        let includes = keywords
+            .split(',')
+            .map(str::trim)
+            .filter(|keyword| !keyword.is_empty())
+            .map(String::from)
+            .collect();
+
+        Ok(PushoverRoute {
+            group_key,
+            includes,
+        })
+    }
+}
+
+impl From<PartialPushoverRoute> for PushoverRoute {
+    fn from(route: PartialPushoverRoute) -> Self {
+        PushoverRoute {
+            group_key: route.group_key,
+            includes: route.include,
+        }
+    }
+}
+
+impl FromStr for WebhookHeader {
+    type Err = String;
+
+    /// Parses a header from its name and value separated by a colon, e.g.
+    /// `Authorization: Bearer abc123`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, value) = s
+            .split_once(':')
+            .ok_or_else(|| format!("Invalid header: {} - must be NAME:VALUE", s))?;
+        let (name, value) = (name.trim(), value.trim());
+        HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| format!("Invalid header name: {}", name))?;
+        HeaderValue::from_str(value).map_err(|_| format!("Invalid header value: {}", value))?;
+
+        Ok(WebhookHeader {
+            name: name.to_string(),
+            value: value.to_string(),
+        })
+    }
+}
+
+/// The backends that known events can be stored in.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StoreKind {
+    /// The JSON events file.
+    Json,
+    /// An SQLite database at the given path.
+    Sqlite(PathBuf),
+}
+
+impl FromStr for StoreKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            None if s == "json" => Ok(Self::Json),
+            Some(("sqlite", path)) if !path.is_empty() => Ok(Self::Sqlite(PathBuf::from(path))),
+            _ => Err(format!(
+                "Unknown store: {} - must be \"json\" or \"sqlite:<FILE>\"",
+                s
+            )),
+        }
+    }
 }
 
-struct DurationWrapper(Duration);
+/// A time of day in the format `HH:MM`.
+#[derive(Debug, Clone, Copy)]
+pub struct TimeOfDay(NaiveTime);
+
+impl FromStr for TimeOfDay {
+    type Err = chrono::ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        NaiveTime::parse_from_str(s, "%H:%M").map(Self)
+    }
+}
+
+/// A duration given as a number of seconds, optionally followed by the unit `s`, `m`, or `h`, e.g.
+/// `90`, `90s`, `2m`, or `1h`.
+pub struct DurationWrapper(Duration);
+
+impl DurationWrapper {
+    fn from_secs(secs: u64) -> Self {
+        Self(Duration::from_secs(secs))
+    }
+}
 
 impl FromStr for DurationWrapper {
-    type Err = ParseIntError;
+    type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let secs: u64 = s.parse()?;
+        // Plain numbers are seconds, as they were before units were accepted
+        let (number, unit_secs) = match s.char_indices().last() {
+            Some((i, 's')) => (&s[..i], 1),
+            Some((i, 'm')) => (&s[..i], 60),
+            Some((i, 'h')) => (&s[..i], 60 * 60),
+            _ => (s, 1),
+        };
+        let invalid = || {
+            format!(
+                "Invalid duration: {} - must be a whole number of seconds, optionally followed \
+                 by s, m, or h, e.g. 90, 90s, 2m, or 1h",
+                s
+            )
+        };
+
+        if number.is_empty() || !number.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(invalid());
+        }
+        let secs = number
+            .parse::<u64>()
+            .ok()
+            .and_then(|number| number.checked_mul(unit_secs))
+            .ok_or_else(invalid)?;
         Ok(Self(Duration::from_secs(secs)))
     }
 }
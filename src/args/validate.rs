@@ -1,5 +1,15 @@
+use chrono::{
+    format::{Item, StrftimeItems},
+    NaiveDate, NaiveTime, Weekday,
+};
 use lazy_static::lazy_static;
+use lettre::message::Mailbox;
 use regex::Regex;
+use reqwest::Url;
+use std::net::SocketAddr;
+
+use super::{DurationWrapper, StoreKind};
+use crate::{event::parse, message, retry, LogRotation, PushoverRoute, WebhookHeader};
 
 /// Creates a closure for validating the length of given strings.
 ///
@@ -65,36 +75,421 @@ pub fn pushover_key(s: String) -> Result<(), String> {
     Ok(())
 }
 
-/// Checks that a given string is a valid unsigned integer.
+/// Checks that a given string is a valid Pushover priority, i.e. a whole number from -2 to 2.
+///
+/// # Examples
+///
+/// ```ignore
+/// assert!(validate::pushover_priority(String::from("3")).is_err());
+/// assert!(validate::pushover_priority(String::from("high")).is_err());
+/// assert!(validate::pushover_priority(String::from("-2")).is_ok());
+/// ```
+pub fn pushover_priority(s: String) -> Result<(), String> {
+    match s.parse::<i8>() {
+        Ok(priority) if (-2..=2).contains(&priority) => Ok(()),
+        _ => Err(String::from(
+            "Invalid Pushover priority - must be a whole number from -2 to 2",
+        )),
+    }
+}
+
+/// Checks that a given string is a valid comma-separated list of Pushover device names, which
+/// consist of up to 25 ASCII-alphanumeric characters, underscores, or dashes.
+///
+/// # Examples
+///
+/// ```ignore
+/// assert!(validate::pushover_devices(String::from("phone,")).is_err());
+/// assert!(validate::pushover_devices(String::from("my phone")).is_err());
+/// assert!(validate::pushover_devices(String::from("phone")).is_ok());
+/// assert!(validate::pushover_devices(String::from("phone,tablet_2")).is_ok());
+/// ```
+pub fn pushover_devices(s: String) -> Result<(), String> {
+    lazy_static! {
+        static ref RE: Regex = Regex::new(r"^[A-Za-z0-9_-]{1,25}(,[A-Za-z0-9_-]{1,25})*$").unwrap();
+    }
+
+    if !RE.is_match(s.as_str()) {
+        return Err(String::from(
+            "Invalid Pushover devices - must be a comma-separated list of device names",
+        ));
+    }
+    Ok(())
+}
+
+/// Checks that a given string is a valid unsigned 64-bit integer.
 ///
 /// # Examples
 ///
 /// ```ignore
-/// let too_short = String::from("");
-/// assert!(validate::uint(too_short).is_err());
+/// let empty = String::from("");
+/// assert!(validate::uint(empty).is_err());
 ///
-/// let too_long = String::from("12345678901234567890");
-/// assert!(validate::uint(too_long).is_err());
+/// let too_large = String::from("18446744073709551616");
+/// assert!(validate::uint(too_large).is_err());
 ///
 /// let invalid_character = String::from("-5435");
 /// assert!(validate::uint(invalid_character).is_err());
 ///
-/// let valid = String::from("1234567890123456789");
+/// let valid = String::from("18446744073709551615");
 /// assert!(validate::uint(valid).is_ok());
 /// ```
 pub fn uint(s: String) -> Result<(), String> {
+    // Parsing alone would also accept a leading plus sign
+    if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(String::from("Invalid uint - must consist of digits only"));
+    }
+    s.parse::<u64>()
+        .map(|_| ())
+        .map_err(|_| format!("Invalid uint - must be at most {}", u64::MAX))
+}
+
+/// Checks that a given string is a valid number of attempts, i.e. a whole number from 1 to
+/// [`retry::MAX_ATTEMPTS`].
+///
+/// # Examples
+///
+/// ```ignore
+/// assert!(validate::attempts(String::from("0")).is_err());
+/// assert!(validate::attempts(String::from("4294967296")).is_err());
+/// assert!(validate::attempts(String::from("3")).is_ok());
+/// ```
+pub fn attempts(s: String) -> Result<(), String> {
+    match s.parse::<u32>() {
+        Ok(attempts) if (1..=retry::MAX_ATTEMPTS).contains(&attempts) => Ok(()),
+        _ => Err(format!(
+            "Invalid number of attempts - must be a whole number from 1 to {}",
+            retry::MAX_ATTEMPTS
+        )),
+    }
+}
+
+/// Checks that a given string is a valid percentage, i.e. a whole number from 0 to 100.
+///
+/// # Examples
+///
+/// ```ignore
+/// assert!(validate::percent(String::from("101")).is_err());
+/// assert!(validate::percent(String::from("12.5")).is_err());
+/// assert!(validate::percent(String::from("10")).is_ok());
+/// ```
+pub fn percent(s: String) -> Result<(), String> {
+    match s.parse::<u8>() {
+        Ok(percent) if percent <= 100 => Ok(()),
+        _ => Err(String::from(
+            "Invalid percentage - must be a whole number from 0 to 100",
+        )),
+    }
+}
+
+/// Checks that a given string is a valid duration, i.e. a whole number of seconds optionally
+/// followed by the unit `s`, `m`, or `h`.
+///
+/// # Examples
+///
+/// ```ignore
+/// assert!(validate::duration(String::from("2x")).is_err());
+/// assert!(validate::duration(String::from("90")).is_ok());
+/// assert!(validate::duration(String::from("2m")).is_ok());
+/// ```
+pub fn duration(s: String) -> Result<(), String> {
+    s.parse::<DurationWrapper>().map(|_| ())
+}
+
+/// Checks that a given string is a valid HTTP(S) URL.
+///
+/// # Examples
+///
+/// ```ignore
+/// let no_scheme = String::from("ktk-tennis.halbooking.dk");
+/// assert!(validate::url(no_scheme).is_err());
+///
+/// let wrong_scheme = String::from("ftp://ktk-tennis.halbooking.dk");
+/// assert!(validate::url(wrong_scheme).is_err());
+///
+/// let valid = String::from("https://ktk-tennis.halbooking.dk");
+/// assert!(validate::url(valid).is_ok());
+/// ```
+pub fn url(s: String) -> Result<(), String> {
+    let url = Url::parse(&s).map_err(|error| format!("Invalid URL - {}", error))?;
+
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return Err(String::from("Invalid URL - must use http or https"));
+    }
+    Ok(())
+}
+
+/// Checks that a given string is a valid Discord webhook URL.
+///
+/// # Examples
+///
+/// ```ignore
+/// let not_discord = String::from("https://example.com/api/webhooks/1234/abcd");
+/// assert!(validate::discord_webhook(not_discord).is_err());
+///
+/// let missing_token = String::from("https://discord.com/api/webhooks/1234");
+/// assert!(validate::discord_webhook(missing_token).is_err());
+///
+/// let valid = String::from("https://discord.com/api/webhooks/1234/abc-DEF_123");
+/// assert!(validate::discord_webhook(valid).is_ok());
+/// ```
+pub fn discord_webhook(s: String) -> Result<(), String> {
     lazy_static! {
-        static ref RE: Regex = Regex::new(r"^[0-9]{1, 19}$").unwrap();
+        static ref RE: Regex =
+            Regex::new(r"^https://(discord|discordapp)\.com/api/webhooks/[0-9]+/[A-Za-z0-9_-]+$")
+                .unwrap();
     }
 
     if !RE.is_match(s.as_str()) {
-        return Err(String::from("Invalid uint - must consist of 1-19 digits"));
+        return Err(String::from(
+            "Invalid Discord webhook URL - must be of the form \
+             https://discord.com/api/webhooks/<ID>/<TOKEN>",
+        ));
     }
     Ok(())
 }
 
+/// Checks that a given string is a valid network port, i.e. an integer between 1 and 65535.
+///
+/// # Examples
+///
+/// ```ignore
+/// assert!(validate::port(String::from("0")).is_err());
+/// assert!(validate::port(String::from("65536")).is_err());
+/// assert!(validate::port(String::from("587")).is_ok());
+/// ```
+pub fn port(s: String) -> Result<(), String> {
+    match s.parse::<u16>() {
+        Ok(port) if port > 0 => Ok(()),
+        _ => Err(String::from(
+            "Invalid port - must be an integer between 1 and 65535",
+        )),
+    }
+}
+
+/// Checks that a given string is a valid email address, optionally with a display name.
+///
+/// # Examples
+///
+/// ```ignore
+/// assert!(validate::mailbox(String::from("not an address")).is_err());
+/// assert!(validate::mailbox(String::from("me@example.com")).is_ok());
+/// assert!(validate::mailbox(String::from("Me <me@example.com>")).is_ok());
+/// ```
+pub fn mailbox(s: String) -> Result<(), String> {
+    s.parse::<Mailbox>()
+        .map(|_| ())
+        .map_err(|error| format!("Invalid email address - {}", error))
+}
+
+/// Checks that a given string is a valid date in the format `YYYY-MM-DD`.
+///
+/// # Examples
+///
+/// ```ignore
+/// assert!(validate::date(String::from("2021-13-01")).is_err());
+/// assert!(validate::date(String::from("01-05-2021")).is_err());
+/// assert!(validate::date(String::from("2021-05-01")).is_ok());
+/// ```
+pub fn date(s: String) -> Result<(), String> {
+    NaiveDate::parse_from_str(&s, "%Y-%m-%d")
+        .map(|_| ())
+        .map_err(|error| {
+            format!(
+                "Invalid date - must be in the format YYYY-MM-DD ({})",
+                error
+            )
+        })
+}
+
+/// Checks that a given string is a valid time of day in the format `HH:MM`.
+///
+/// # Examples
+///
+/// ```ignore
+/// assert!(validate::time(String::from("24:00")).is_err());
+/// assert!(validate::time(String::from("7")).is_err());
+/// assert!(validate::time(String::from("07:00")).is_ok());
+/// ```
+pub fn time(s: String) -> Result<(), String> {
+    NaiveTime::parse_from_str(&s, "%H:%M")
+        .map(|_| ())
+        .map_err(|error| format!("Invalid time - must be in the format HH:MM ({})", error))
+}
+
+/// Checks that a given string is a weekday, either abbreviated or in full.
+///
+/// # Examples
+///
+/// ```ignore
+/// assert!(validate::weekday(String::from("lør")).is_err());
+/// assert!(validate::weekday(String::from("sat")).is_ok());
+/// assert!(validate::weekday(String::from("Saturday")).is_ok());
+/// ```
+pub fn weekday(s: String) -> Result<(), String> {
+    s.parse::<Weekday>()
+        .map(|_| ())
+        .map_err(|_| String::from("Invalid weekday - must be e.g. mon or monday"))
+}
+
+/// Checks that a given string is a valid socket address, i.e. an IP address and a port.
+///
+/// # Examples
+///
+/// ```ignore
+/// assert!(validate::socket_address(String::from("localhost:8080")).is_err());
+/// assert!(validate::socket_address(String::from("127.0.0.1")).is_err());
+/// assert!(validate::socket_address(String::from("127.0.0.1:8080")).is_ok());
+/// ```
+pub fn socket_address(s: String) -> Result<(), String> {
+    s.parse::<SocketAddr>().map(|_| ()).map_err(|error| {
+        format!(
+            "Invalid address - must be in the format ADDR:PORT ({})",
+            error
+        )
+    })
+}
+
+/// Checks that a given string is a valid Pushover route, i.e. a group key optionally followed by
+/// a colon and a comma-separated list of keywords.
+///
+/// # Examples
+///
+/// ```ignore
+/// assert!(validate::pushover_route(String::from("tooshort:junior")).is_err());
+///
+/// let group_key = "qwertyuiopasdfghjklzxcvbnm0123";
+/// assert!(validate::pushover_route(group_key.to_string()).is_ok());
+/// assert!(validate::pushover_route(format!("{}:junior,ungdom", group_key)).is_ok());
+/// ```
+pub fn pushover_route(s: String) -> Result<(), String> {
+    s.parse::<PushoverRoute>().map(|_| ())
+}
+
+/// Checks that a given string is a valid webhook header, i.e. a header name and value separated by
+/// a colon.
+///
+/// # Examples
+///
+/// ```ignore
+/// assert!(validate::webhook_header(String::from("Authorization")).is_err());
+/// assert!(validate::webhook_header(String::from("Bad name: value")).is_err());
+/// assert!(validate::webhook_header(String::from("Authorization: Bearer abc123")).is_ok());
+/// ```
+pub fn webhook_header(s: String) -> Result<(), String> {
+    s.parse::<WebhookHeader>().map(|_| ())
+}
+
+/// Checks that a given string is a valid log rotation, i.e. `daily` or `size:<MB>`.
+///
+/// # Examples
+///
+/// ```ignore
+/// assert!(validate::log_rotation(String::from("size:0")).is_err());
+/// assert!(validate::log_rotation(String::from("daily")).is_ok());
+/// assert!(validate::log_rotation(String::from("size:10")).is_ok());
+/// ```
+pub fn log_rotation(s: String) -> Result<(), String> {
+    s.parse::<LogRotation>().map(|_| ())
+}
+
+/// Checks that a given string is a valid store, i.e. `json` or `sqlite:<FILE>`.
+///
+/// # Examples
+///
+/// ```ignore
+/// assert!(validate::store(String::from("sqlite")).is_err());
+/// assert!(validate::store(String::from("json")).is_ok());
+/// assert!(validate::store(String::from("sqlite:events.db")).is_ok());
+/// ```
+pub fn store(s: String) -> Result<(), String> {
+    s.parse::<StoreKind>().map(|_| ())
+}
+
+/// Checks that a given string is a valid CSS selector.
+///
+/// # Examples
+///
+/// ```ignore
+/// assert!(validate::css_selector(String::from("tr[class=")).is_err());
+/// assert!(validate::css_selector(String::from("tr.infinite-item")).is_ok());
+/// ```
+pub fn css_selector(s: String) -> Result<(), String> {
+    parse::parse_selector(&s)
+        .map(|_| ())
+        .map_err(|error| error.to_string())
+}
+
+/// Checks that a given string is a valid date format, as accepted by
+/// [`chrono::format::strftime`].
+///
+/// # Examples
+///
+/// ```ignore
+/// assert!(validate::date_format(String::from("%a %Q")).is_err());
+/// assert!(validate::date_format(String::from("%a %e %b %Y")).is_ok());
+/// ```
+pub fn date_format(s: String) -> Result<(), String> {
+    if StrftimeItems::new(&s).any(|item| item == Item::Error) {
+        Err(String::from("invalid date format"))
+    } else {
+        Ok(())
+    }
+}
+
+/// Checks that a given string is a valid message template, i.e. that it only uses known
+/// placeholders, and that its optional parts in square brackets are closed and not nested.
+///
+/// # Examples
+///
+/// ```ignore
+/// assert!(validate::message_template(String::from("{title}: {time}")).is_err());
+/// assert!(validate::message_template(String::from("{title}[ ({location})")).is_err());
+/// assert!(validate::message_template(String::from("{title}: {date}[ ({location})]")).is_ok());
+/// ```
+pub fn message_template(s: String) -> Result<(), String> {
+    let mut in_optional = false;
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '[' if in_optional => return Err(String::from("cannot nest optional parts")),
+            '[' => in_optional = true,
+            ']' => in_optional = false,
+            '{' => {
+                let mut name = String::new();
+                let is_closed = loop {
+                    match chars.next() {
+                        Some('}') => break true,
+                        Some(c) => name.push(c),
+                        None => break false,
+                    }
+                };
+                if !is_closed {
+                    return Err(format!("placeholder {{{} is not closed with '}}'", name));
+                }
+                if !message::PLACEHOLDERS.contains(&name.as_str()) {
+                    return Err(format!(
+                        "unknown placeholder {{{}}}, expected one of {{{}}}",
+                        name,
+                        message::PLACEHOLDERS.join("}, {")
+                    ));
+                }
+            }
+            _ => (),
+        }
+    }
+
+    if in_optional {
+        Err(String::from("optional part is not closed with ']'"))
+    } else {
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use std::time::Duration;
+
     use super::*;
 
     #[test]
@@ -128,18 +523,218 @@ mod tests {
         assert!(pushover_key(valid).is_ok());
     }
 
+    #[test]
+    fn pushover_priority_test() {
+        assert!(pushover_priority(String::from("")).is_err());
+        assert!(pushover_priority(String::from("3")).is_err());
+        assert!(pushover_priority(String::from("-3")).is_err());
+        assert!(pushover_priority(String::from("high")).is_err());
+        assert!(pushover_priority(String::from("-2")).is_ok());
+        assert!(pushover_priority(String::from("2")).is_ok());
+    }
+
+    #[test]
+    fn pushover_devices_test() {
+        assert!(pushover_devices(String::from("")).is_err());
+        assert!(pushover_devices(String::from("phone,")).is_err());
+        assert!(pushover_devices(String::from("my phone")).is_err());
+        assert!(pushover_devices(String::from("phone")).is_ok());
+        assert!(pushover_devices(String::from("phone,tablet_2")).is_ok());
+    }
+
     #[test]
     fn uint_test() {
-        let too_short = String::from("");
-        assert!(uint(too_short).is_err());
+        assert!(uint(String::from("")).is_err());
+        assert!(uint(String::from("-5435")).is_err());
+        assert!(uint(String::from("+5435")).is_err());
+        assert!(uint(String::from("12 34")).is_err());
+        assert!(uint(String::from("0")).is_ok());
+        assert!(uint(String::from("12345678901234567890")).is_ok());
+    }
+
+    #[test]
+    fn uint_test_near_max() {
+        assert!(uint(String::from("18446744073709551614")).is_ok());
+        assert!(uint(String::from("18446744073709551615")).is_ok());
+        assert!(uint(String::from("18446744073709551616")).is_err());
+        assert!(uint(String::from("99999999999999999999")).is_err());
+        assert!(uint(String::from("100000000000000000000")).is_err());
+    }
+
+    #[test]
+    fn url_test() {
+        let no_scheme = String::from("ktk-tennis.halbooking.dk");
+        assert!(url(no_scheme).is_err());
+
+        let wrong_scheme = String::from("ftp://ktk-tennis.halbooking.dk");
+        assert!(url(wrong_scheme).is_err());
+
+        let valid = String::from("https://ktk-tennis.halbooking.dk");
+        assert!(url(valid).is_ok());
+    }
+
+    #[test]
+    fn discord_webhook_test() {
+        let not_discord = String::from("https://example.com/api/webhooks/1234/abcd");
+        assert!(discord_webhook(not_discord).is_err());
+
+        let missing_token = String::from("https://discord.com/api/webhooks/1234");
+        assert!(discord_webhook(missing_token).is_err());
+
+        let valid = String::from("https://discord.com/api/webhooks/1234/abc-DEF_123");
+        assert!(discord_webhook(valid).is_ok());
+
+        let valid_legacy = String::from("https://discordapp.com/api/webhooks/1234/abc-DEF_123");
+        assert!(discord_webhook(valid_legacy).is_ok());
+    }
+
+    #[test]
+    fn port_test() {
+        assert!(port(String::from("0")).is_err());
+        assert!(port(String::from("65536")).is_err());
+        assert!(port(String::from("-1")).is_err());
+        assert!(port(String::from("587")).is_ok());
+        assert!(port(String::from("65535")).is_ok());
+    }
+
+    #[test]
+    fn mailbox_test() {
+        assert!(mailbox(String::from("not an address")).is_err());
+        assert!(mailbox(String::from("me@example.com")).is_ok());
+        assert!(mailbox(String::from("Me <me@example.com>")).is_ok());
+    }
+
+    #[test]
+    fn duration_test() {
+        let parse = |s: &str| s.parse::<DurationWrapper>().map(Duration::from);
+        assert_eq!(parse("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse("90s"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse("2m"), Ok(Duration::from_secs(120)));
+        assert_eq!(parse("1h"), Ok(Duration::from_secs(3600)));
+
+        let error = duration(String::from("2x")).unwrap_err();
+        assert!(error.starts_with("Invalid duration: 2x"), "{}", error);
+        assert!(duration(String::from("")).is_err());
+        assert!(duration(String::from("m")).is_err());
+        assert!(duration(String::from("+2m")).is_err());
+        assert!(duration(String::from("2.5h")).is_err());
+    }
+
+    #[test]
+    fn attempts_test() {
+        assert!(attempts(String::from("")).is_err());
+        assert!(attempts(String::from("0")).is_err());
+        assert!(attempts(String::from("21")).is_err());
+        assert!(attempts(String::from("4294967296")).is_err());
+        assert!(attempts(String::from("1")).is_ok());
+        assert!(attempts(String::from("20")).is_ok());
+    }
 
-        let too_long = String::from("12345678901234567890");
-        assert!(uint(too_long).is_err());
+    #[test]
+    fn percent_test() {
+        assert!(percent(String::from("")).is_err());
+        assert!(percent(String::from("-1")).is_err());
+        assert!(percent(String::from("101")).is_err());
+        assert!(percent(String::from("12.5")).is_err());
+        assert!(percent(String::from("0")).is_ok());
+        assert!(percent(String::from("100")).is_ok());
+    }
+
+    #[test]
+    fn date_test() {
+        assert!(date(String::from("")).is_err());
+        assert!(date(String::from("2021-13-01")).is_err());
+        assert!(date(String::from("2021-02-30")).is_err());
+        assert!(date(String::from("01-05-2021")).is_err());
+        assert!(date(String::from("2021-05-01")).is_ok());
+    }
 
-        let invalid_character = String::from("-5435");
-        assert!(uint(invalid_character).is_err());
+    #[test]
+    fn weekday_test() {
+        assert!(weekday(String::from("")).is_err());
+        assert!(weekday(String::from("lør")).is_err());
+        assert!(weekday(String::from("sat")).is_ok());
+        assert!(weekday(String::from("Sun")).is_ok());
+        assert!(weekday(String::from("monday")).is_ok());
+    }
 
-        let valid = String::from("1234567890123456789");
-        assert!(uint(valid).is_ok());
+    #[test]
+    fn time_test() {
+        assert!(time(String::from("")).is_err());
+        assert!(time(String::from("7")).is_err());
+        assert!(time(String::from("24:00")).is_err());
+        assert!(time(String::from("22:60")).is_err());
+        assert!(time(String::from("7:00")).is_ok());
+        assert!(time(String::from("22:00")).is_ok());
+    }
+
+    #[test]
+    fn socket_address_test() {
+        assert!(socket_address(String::from("")).is_err());
+        assert!(socket_address(String::from("localhost:8080")).is_err());
+        assert!(socket_address(String::from("127.0.0.1")).is_err());
+        assert!(socket_address(String::from("127.0.0.1:65536")).is_err());
+        assert!(socket_address(String::from("127.0.0.1:8080")).is_ok());
+        assert!(socket_address(String::from("[::1]:8080")).is_ok());
+    }
+
+    #[test]
+    fn log_rotation_test() {
+        assert_eq!("daily".parse(), Ok(LogRotation::Daily));
+        assert_eq!("size:10".parse(), Ok(LogRotation::Size(10)));
+        assert!(log_rotation(String::from("")).is_err());
+        assert!(log_rotation(String::from("hourly")).is_err());
+        assert!(log_rotation(String::from("size:")).is_err());
+        assert!(log_rotation(String::from("size:0")).is_err());
+        assert!(log_rotation(String::from("size:-1")).is_err());
+    }
+
+    #[test]
+    fn store_test() {
+        assert!(store(String::from("")).is_err());
+        assert!(store(String::from("sqlite")).is_err());
+        assert!(store(String::from("sqlite:")).is_err());
+        assert!(store(String::from("postgres:events")).is_err());
+        assert!(store(String::from("json")).is_ok());
+        assert!(store(String::from("sqlite:events.db")).is_ok());
+    }
+
+    #[test]
+    fn pushover_route_test() {
+        let group_key = "qwertyuiopasdfghjklzxcvbnm0123";
+        assert!(pushover_route(String::from("")).is_err());
+        assert!(pushover_route(String::from("tooshort:junior")).is_err());
+        assert!(pushover_route(group_key.to_string()).is_ok());
+        assert!(pushover_route(format!("{}:junior", group_key)).is_ok());
+        assert!(pushover_route(format!("{}:junior,ungdom", group_key)).is_ok());
+    }
+
+    #[test]
+    fn css_selector_test() {
+        assert!(css_selector(String::from("")).is_err());
+        assert!(css_selector(String::from("tr[class=")).is_err());
+        assert!(css_selector(String::from("td..holdinfo")).is_err());
+        assert!(css_selector(String::from("tr.infinite-item")).is_ok());
+        assert!(css_selector(String::from("td[class=\"liste_wide min992 holdinfo\"]")).is_ok());
+    }
+
+    #[test]
+    fn date_format_test() {
+        assert!(date_format(String::from("%a %Q")).is_err());
+        assert!(date_format(String::from("%")).is_err());
+        assert!(date_format(String::from("")).is_ok());
+        assert!(date_format(String::from("%d/%m %H:%M")).is_ok());
+        assert!(date_format(String::from(message::DEFAULT_DATE_FORMAT)).is_ok());
+    }
+
+    #[test]
+    fn message_template_test() {
+        assert!(message_template(String::from("{title}: {time}")).is_err());
+        assert!(message_template(String::from("{title}: {date")).is_err());
+        assert!(message_template(String::from("{title}[ ({location})")).is_err());
+        assert!(message_template(String::from("{title}[ [{location}]]")).is_err());
+        assert!(message_template(String::from("")).is_ok());
+        assert!(message_template(String::from("{title} - {class_info}")).is_ok());
+        assert!(message_template(String::from(message::DEFAULT_TEMPLATE)).is_ok());
     }
 }
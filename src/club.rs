@@ -0,0 +1,124 @@
+use std::collections::HashSet;
+
+use reqwest::Url;
+
+#[cfg(feature = "async")]
+use crate::event::async_fetch::AsyncEventFetcher;
+use crate::event::{
+    fetch::{EventFetcher, FetchError},
+    Event,
+};
+
+/// The separator between the name of a club and the id of an event in namespaced ids.
+const SEPARATOR: char = '/';
+
+/// The clubs to fetch events from, each with a fetcher of its own. When following more than one
+/// club, the id of each event is namespaced by the name of its club, e.g.
+/// `ktk-tennis.halbooking.dk/hold10412`, so events of different clubs never collide.
+pub struct Clubs<F> {
+    clubs: Vec<(String, F)>,
+}
+
+impl<F> Clubs<F> {
+    /// Creates the clubs from the name of each club and its fetcher. See [`name`].
+    pub fn new(clubs: Vec<(String, F)>) -> Self {
+        Clubs { clubs }
+    }
+
+    fn namespace(&self, club: &str, events: HashSet<Event>) -> HashSet<Event> {
+        if self.clubs.len() == 1 {
+            return events;
+        }
+        events
+            .into_iter()
+            .map(|event| namespaced(club, event))
+            .collect()
+    }
+}
+
+impl Clubs<EventFetcher> {
+    /// Fetches the events of all clubs.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error of fetching the events of any club.
+    pub fn fetch_all(&self) -> Result<HashSet<Event>, FetchError> {
+        let mut events = HashSet::new();
+        for (club, fetcher) in &self.clubs {
+            events.extend(self.namespace(club, fetcher.fetch_all()?));
+        }
+        Ok(events)
+    }
+}
+
+#[cfg(feature = "async")]
+impl Clubs<AsyncEventFetcher> {
+    /// Fetches the events of all clubs like [`Clubs::<EventFetcher>::fetch_all`], but without
+    /// blocking.
+    pub async fn fetch_all(&self) -> Result<HashSet<Event>, FetchError> {
+        let mut events = HashSet::new();
+        for (club, fetcher) in &self.clubs {
+            events.extend(self.namespace(club, fetcher.fetch_all().await?));
+        }
+        Ok(events)
+    }
+}
+
+/// Gets the name of the club at `base_url`, which is its host, e.g. `ktk-tennis.halbooking.dk`.
+pub fn name(base_url: &str) -> String {
+    Url::parse(base_url)
+        .ok()
+        .and_then(|url| url.host_str().map(String::from))
+        .unwrap_or_else(|| base_url.to_string())
+}
+
+/// Gets the name of the club that `event` belongs to, if its id is namespaced.
+pub fn of(event: &Event) -> Option<&str> {
+    event.id.split_once(SEPARATOR).map(|(club, _)| club)
+}
+
+/// Namespaces the id of `event` by `club`.
+pub fn namespaced(club: &str, mut event: Event) -> Event {
+    event.id = format!("{}{}{}", club, SEPARATOR, event.id);
+    event
+}
+
+/// Removes the namespace from the id of `event`, or gets `None` if it does not belong to `club`.
+pub fn without_namespace(club: &str, event: &Event) -> Option<Event> {
+    let id = event.id.strip_prefix(club)?.strip_prefix(SEPARATOR)?;
+    let mut event = event.clone();
+    event.id = id.to_string();
+    Some(event)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::event;
+
+    #[test]
+    fn name_is_host_of_base_url() {
+        assert_eq!(
+            name("https://ktk-tennis.halbooking.dk"),
+            "ktk-tennis.halbooking.dk"
+        );
+        assert_eq!(name("http://localhost:8080/ktk"), "localhost");
+    }
+
+    #[test]
+    fn namespaced_ids_can_be_split() {
+        let namespaced_event = namespaced("ktk-tennis.halbooking.dk", event("hold10412"));
+
+        assert_eq!(namespaced_event.id, "ktk-tennis.halbooking.dk/hold10412");
+        assert_eq!(of(&namespaced_event), Some("ktk-tennis.halbooking.dk"));
+        assert_eq!(
+            without_namespace("ktk-tennis.halbooking.dk", &namespaced_event).map(|event| event.id),
+            Some(String::from("hold10412"))
+        );
+        assert_eq!(
+            without_namespace("ktk-tennis.halbooking", &namespaced_event),
+            None
+        );
+        assert_eq!(of(&event("hold10412")), None);
+    }
+}
@@ -0,0 +1,171 @@
+use std::{
+    collections::{BTreeSet, HashSet},
+    error::Error,
+    fs::{self, File},
+    path::PathBuf,
+};
+
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use serde::{Deserialize, Serialize};
+
+use crate::event::Event;
+
+/// New events buffered for the daily digest, which is sent once a day at a set local time instead
+/// of a notification per change. The buffer is persisted to a JSON file, so buffered events that
+/// have not been sent yet survive a restart.
+pub struct Digest {
+    path: PathBuf,
+    at: NaiveTime,
+    state: DigestState,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct DigestState {
+    /// The local date the digest was last sent on, if ever.
+    last_sent: Option<NaiveDate>,
+    /// The ids of the buffered events, which are looked up among the stored events when sending.
+    ids: BTreeSet<String>,
+}
+
+impl Digest {
+    /// Loads the digest buffered at `path`, or an empty one if the file does not exist yet. The
+    /// digest is due at the local time `at` every day.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file exists but cannot be read or parsed.
+    pub fn load(path: impl Into<PathBuf>, at: NaiveTime) -> Result<Self, Box<dyn Error>> {
+        let path = path.into();
+        let state = if path.exists() {
+            serde_json::from_reader(File::open(&path)?)?
+        } else {
+            DigestState::default()
+        };
+        Ok(Digest { path, at, state })
+    }
+
+    /// Creates an empty digest that is saved to `path`, e.g. when loading it failed.
+    pub fn empty(path: impl Into<PathBuf>, at: NaiveTime) -> Self {
+        Digest {
+            path: path.into(),
+            at,
+            state: DigestState::default(),
+        }
+    }
+
+    /// Checks whether the digest should be sent at the local date and time `now`, i.e. whether
+    /// the time of day it is due at has passed and it has not been sent yet today.
+    pub fn is_due(&self, now: NaiveDateTime) -> bool {
+        now.time() >= self.at && self.state.last_sent != Some(now.date())
+    }
+
+    /// Buffers `events` and saves the buffer.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer cannot be written to the file. The events are still
+    /// buffered in memory.
+    pub fn add(&mut self, events: &[&Event]) -> Result<(), Box<dyn Error>> {
+        self.state
+            .ids
+            .extend(events.iter().map(|event| event.id.clone()));
+        self.save()
+    }
+
+    /// Gets the current versions of the buffered events from `events`, sorted. Buffered events
+    /// that have since been removed are left out.
+    pub fn events_in<'a>(&self, events: &'a HashSet<Event>) -> Vec<&'a Event> {
+        let mut buffered: Vec<_> = events
+            .iter()
+            .filter(|event| self.state.ids.contains(&event.id))
+            .collect();
+        buffered.sort();
+        buffered
+    }
+
+    /// Empties the buffer after the digest was sent on the local date `date`, and saves it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer cannot be written to the file.
+    pub fn clear(&mut self, date: NaiveDate) -> Result<(), Box<dyn Error>> {
+        self.state.ids.clear();
+        self.state.last_sent = Some(date);
+        self.save()
+    }
+
+    fn save(&self) -> Result<(), Box<dyn Error>> {
+        // Write to a temporary file first, so an interrupted write leaves the old buffer intact
+        let mut temp_path = self.path.clone().into_os_string();
+        temp_path.push(".tmp");
+        fs::write(&temp_path, serde_json::to_vec(&self.state)?)?;
+        fs::rename(&temp_path, &self.path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::test_util::events;
+
+    fn at(date: &str, time: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(&format!("{} {}", date, time), "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn is_due_once_a_day_after_the_set_time() {
+        let directory = TempDir::new().unwrap();
+        let mut digest = Digest::empty(
+            directory.path().join("digest.json"),
+            NaiveTime::from_hms(18, 0, 0),
+        );
+
+        assert!(!digest.is_due(at("2021-05-04", "17:59")));
+        assert!(digest.is_due(at("2021-05-04", "18:00")));
+
+        digest.clear(NaiveDate::from_ymd(2021, 5, 4)).unwrap();
+
+        assert!(!digest.is_due(at("2021-05-04", "23:59")));
+        assert!(!digest.is_due(at("2021-05-05", "08:00")));
+        assert!(digest.is_due(at("2021-05-05", "19:00")));
+    }
+
+    #[test]
+    fn buffered_events_survive_restart() {
+        let directory = TempDir::new().unwrap();
+        let path = directory.path().join("digest.json");
+        let time = NaiveTime::from_hms(18, 0, 0);
+        let stored = events(&["event1", "event2", "event3"]);
+
+        let mut digest = Digest::load(&path, time).unwrap();
+        let added: Vec<_> = stored.iter().filter(|event| event.id != "event2").collect();
+        digest.add(&added).unwrap();
+
+        let digest = Digest::load(&path, time).unwrap();
+        let ids: Vec<_> = digest
+            .events_in(&stored)
+            .iter()
+            .map(|event| &event.id)
+            .collect();
+        assert_eq!(ids, vec!["event1", "event3"]);
+    }
+
+    #[test]
+    fn events_in_leaves_out_removed_events() {
+        let directory = TempDir::new().unwrap();
+        let mut digest = Digest::empty(
+            directory.path().join("digest.json"),
+            NaiveTime::from_hms(18, 0, 0),
+        );
+        let stored = events(&["event1", "event2"]);
+        digest.add(&stored.iter().collect::<Vec<_>>()).unwrap();
+
+        let remaining = events(&["event2"]);
+
+        assert_eq!(digest.events_in(&remaining).len(), 1);
+        assert_eq!(digest.events_in(&remaining)[0].id, "event2");
+    }
+}
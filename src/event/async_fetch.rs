@@ -0,0 +1,118 @@
+use std::{collections::HashSet, time::Duration};
+
+use reqwest::Client;
+
+use super::{
+    fetch::{self, FetchError},
+    parse::EventParser,
+    Event,
+};
+use crate::retry::RetryPolicy;
+
+/// Fetches events like [`EventFetcher`][fetch::EventFetcher], but without blocking the thread.
+/// Created with [`EventFetcherBuilder::build_async`][fetch::EventFetcherBuilder::build_async].
+pub struct AsyncEventFetcher {
+    pub(super) client: Client,
+    pub(super) parser: EventParser,
+    pub(super) retry: RetryPolicy,
+    pub(super) base_url: String,
+    pub(super) page_delay: Duration,
+    pub(super) warm_up_url: Option<String>,
+}
+
+impl AsyncEventFetcher {
+    pub async fn fetch_all(&self) -> Result<HashSet<Event>, FetchError> {
+        if let Some(url) = &self.warm_up_url {
+            self.warm_up(url).await?;
+        }
+
+        let mut events: HashSet<Event> = HashSet::new();
+
+        let mut i = 0;
+        loop {
+            if i > 0 {
+                tokio::time::sleep(fetch::jittered_page_delay(self.page_delay)).await;
+            }
+
+            let url = fetch::events_url(&self.base_url, i);
+            let new_events = self.fetch(url.as_str()).await?;
+            if new_events.is_subset(&events) {
+                // No new events, so stop
+                break;
+            }
+            events.extend(new_events);
+
+            i += 1;
+        }
+
+        Ok(events)
+    }
+
+    pub async fn fetch(&self, url: &str) -> Result<HashSet<Event>, FetchError> {
+        let body = self
+            .retry
+            .retry_async(|| self.fetch_body(url), FetchError::is_transient)
+            .await?;
+        let list_url = fetch::events_url(&self.base_url, 0);
+        fetch::parse_page(&self.parser, url, &body, Some(&list_url))
+    }
+
+    /// Requests `url` and discards the response, like
+    /// [`EventFetcher::fetch_all`][fetch::EventFetcher::fetch_all] does.
+    async fn warm_up(&self, url: &str) -> Result<(), FetchError> {
+        self.retry
+            .retry_async(|| self.fetch_body(url), FetchError::is_transient)
+            .await?;
+        Ok(())
+    }
+
+    async fn fetch_body(&self, url: &str) -> Result<String, FetchError> {
+        let body = self
+            .client
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        Ok(body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{parser, MockResponse, MockServer};
+
+    const EVENT_PAGE: &str = r#"<table><tbody>
+        <tr class="infinite-item" id="event1">
+            <td class="liste_wide min992">Tennisskole<br>tir 5. maj 2021<br>18:00 - 19:00</td>
+            <td class="liste_wide min992 holdinfo">Hold 1</td>
+        </tr>
+    </tbody></table>"#;
+
+    #[test]
+    fn fetch_all_retries_and_fetches_until_no_new_events() {
+        let server = MockServer::start(vec![
+            MockResponse::new(503, ""),
+            MockResponse::new(200, EVENT_PAGE),
+            MockResponse::new(200, EVENT_PAGE),
+        ]);
+        let fetcher = fetch::EventFetcher::builder()
+            .parser(parser())
+            .retry(RetryPolicy::new(2, Duration::from_millis(1), 2.0))
+            .base_url(server.url())
+            .page_delay(Duration::from_millis(0))
+            .build_async()
+            .unwrap();
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+
+        let events = runtime.block_on(fetcher.fetch_all()).unwrap();
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events.iter().next().unwrap().id, "event1");
+    }
+}
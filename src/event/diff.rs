@@ -0,0 +1,151 @@
+use std::{
+    collections::HashSet,
+    io::{self, Write},
+};
+
+use serde::Serialize;
+use serde_json::json;
+
+use super::Event;
+
+/// The differences between a previously known set of events and a newly fetched one.
+#[derive(Debug, Clone, Serialize)]
+pub struct Diff<'a> {
+    pub added: Vec<&'a Event>,
+    pub removed: Vec<&'a Event>,
+    pub changed: Vec<Change<'a>>,
+}
+
+/// An event whose contents changed while its `id` stayed the same.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Change<'a> {
+    pub old: &'a Event,
+    pub new: &'a Event,
+}
+
+impl<'a> Diff<'a> {
+    /// Computes which events were added, removed, and changed going from `old` to `new`. Events
+    /// are identified by `id`, and changes are found using [`Event::content_eq`]. All lists are
+    /// sorted.
+    pub fn between(old: &'a HashSet<Event>, new: &'a HashSet<Event>) -> Self {
+        let mut added: Vec<_> = new.difference(old).collect();
+        let mut removed: Vec<_> = old.difference(new).collect();
+        let mut changed: Vec<_> = new
+            .iter()
+            .filter_map(|new_event| {
+                let old_event = old.get(new_event)?;
+                if old_event.content_eq(new_event) {
+                    None
+                } else {
+                    Some(Change {
+                        old: old_event,
+                        new: new_event,
+                    })
+                }
+            })
+            .collect();
+
+        added.sort();
+        removed.sort();
+        changed.sort_by(|a, b| a.new.cmp(b.new));
+
+        Diff {
+            added,
+            removed,
+            changed,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    /// Writes each added, removed, and changed event as a single line of JSON to `writer`, i.e.
+    /// as newline-delimited JSON, e.g. `{"change":"added","event":{...}}` or
+    /// `{"change":"changed","old":{...},"new":{...}}`.
+    pub fn write_ndjson(&self, mut writer: impl Write) -> io::Result<()> {
+        let added = self
+            .added
+            .iter()
+            .map(|event| json!({"change": "added", "event": event}));
+        let removed = self
+            .removed
+            .iter()
+            .map(|event| json!({"change": "removed", "event": event}));
+        let changed = self
+            .changed
+            .iter()
+            .map(|change| json!({"change": "changed", "old": change.old, "new": change.new}));
+
+        for line in added.chain(removed).chain(changed) {
+            serde_json::to_writer(&mut writer, &line)?;
+            writeln!(writer)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::event;
+
+    #[test]
+    fn between_finds_added_and_removed() {
+        let old: HashSet<Event> = vec![event("1"), event("2")].into_iter().collect();
+        let new: HashSet<Event> = vec![event("2"), event("3")].into_iter().collect();
+
+        let diff = Diff::between(&old, &new);
+
+        assert_eq!(diff.added, vec![&event("3")]);
+        assert_eq!(diff.removed, vec![&event("1")]);
+    }
+
+    #[test]
+    fn between_finds_changed() {
+        let mut renamed = event("2");
+        renamed.title = String::from("Tennisskole");
+        let old: HashSet<Event> = vec![event("1"), event("2")].into_iter().collect();
+        let new: HashSet<Event> = vec![event("1"), renamed].into_iter().collect();
+
+        let diff = Diff::between(&old, &new);
+
+        assert!(diff.added.is_empty());
+        assert!(diff.removed.is_empty());
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(diff.changed[0].old.title, "");
+        assert_eq!(diff.changed[0].new.title, "Tennisskole");
+    }
+
+    #[test]
+    fn between_identical_sets_is_empty() {
+        let old: HashSet<Event> = vec![event("1")].into_iter().collect();
+        let new: HashSet<Event> = vec![event("1")].into_iter().collect();
+
+        assert!(Diff::between(&old, &new).is_empty());
+    }
+
+    #[test]
+    fn write_ndjson_writes_one_change_per_line() {
+        let mut renamed = event("2");
+        renamed.title = String::from("Tennisskole");
+        let old: HashSet<Event> = vec![event("1"), event("2")].into_iter().collect();
+        let new: HashSet<Event> = vec![renamed, event("3")].into_iter().collect();
+        let mut output = Vec::new();
+
+        Diff::between(&old, &new).write_ndjson(&mut output).unwrap();
+
+        let lines: Vec<serde_json::Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0]["change"], "added");
+        assert_eq!(lines[0]["event"]["id"], "3");
+        assert_eq!(lines[1]["change"], "removed");
+        assert_eq!(lines[1]["event"]["id"], "1");
+        assert_eq!(lines[2]["change"], "changed");
+        assert_eq!(lines[2]["new"]["title"], "Tennisskole");
+    }
+}
@@ -0,0 +1,294 @@
+use std::{
+    collections::HashSet,
+    io::{self, Write},
+};
+
+use chrono::Utc;
+
+use super::{fetch::DEFAULT_BASE_URL, Event};
+
+const ICAL_DATE_TIME_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+const ICAL_MAX_LINE_LENGTH: usize = 75;
+const RSS_DATE_TIME_FORMAT: &str = "%a, %d %b %Y %H:%M:%S %z";
+const MARKDOWN_DATE_TIME_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+/// Exports events as an iCalendar (RFC 5545) calendar with one `VEVENT` per event.
+///
+/// Start times are converted to UTC, since iCalendar has no notion of fixed offsets.
+pub fn to_ical(events: &HashSet<Event>) -> String {
+    let mut events: Vec<_> = events.iter().collect();
+    events.sort();
+
+    let now = Utc::now().format(ICAL_DATE_TIME_FORMAT).to_string();
+
+    let mut lines = vec![
+        String::from("BEGIN:VCALENDAR"),
+        String::from("VERSION:2.0"),
+        String::from("PRODID:-//ktkbot//ktkbot//EN"),
+    ];
+    for event in events {
+        let start = event.date_time.with_timezone(&Utc);
+        lines.push(String::from("BEGIN:VEVENT"));
+        lines.push(format!("UID:{}", escape_ical_text(&event.id)));
+        lines.push(format!("DTSTAMP:{}", now));
+        lines.push(format!("DTSTART:{}", start.format(ICAL_DATE_TIME_FORMAT)));
+        lines.push(format!("SUMMARY:{}", escape_ical_text(&event.title)));
+        lines.push(format!(
+            "DESCRIPTION:{}",
+            escape_ical_text(&event.class_info.join("\n"))
+        ));
+        lines.push(String::from("END:VEVENT"));
+    }
+    lines.push(String::from("END:VCALENDAR"));
+
+    lines
+        .iter()
+        .map(|line| fold_ical_line(line) + "\r\n")
+        .collect()
+}
+
+/// Writes events as CSV with a header row and one row per event, sorted by date and time.
+///
+/// The `class_info` lines of each event are joined by `;`.
+pub fn to_csv(events: &HashSet<Event>, mut writer: impl Write) -> io::Result<()> {
+    let mut events: Vec<_> = events.iter().collect();
+    events.sort();
+
+    writeln!(writer, "id,title,date_time,class_info")?;
+    for event in events {
+        writeln!(
+            writer,
+            "{},{},{},{}",
+            escape_csv_field(&event.id),
+            escape_csv_field(&event.title),
+            event.date_time.to_rfc3339(),
+            escape_csv_field(&event.class_info.join(";"))
+        )?;
+    }
+    writer.flush()
+}
+
+/// Exports events as an RSS 2.0 channel with one `<item>` per event, sorted newest first.
+pub fn to_rss(events: &HashSet<Event>) -> String {
+    let mut events: Vec<_> = events.iter().collect();
+    events.sort();
+    events.reverse();
+
+    let mut rss = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    rss.push_str("<rss version=\"2.0\">\n<channel>\n");
+    rss.push_str("<title>ktkbot</title>\n");
+    rss.push_str(&format!("<link>{}</link>\n", escape_xml(DEFAULT_BASE_URL)));
+    rss.push_str("<description>Upcoming events</description>\n");
+    for event in events {
+        rss.push_str("<item>\n");
+        rss.push_str(&format!("<title>{}</title>\n", escape_xml(&event.title)));
+        rss.push_str(&format!(
+            "<pubDate>{}</pubDate>\n",
+            event.date_time.format(RSS_DATE_TIME_FORMAT)
+        ));
+        rss.push_str(&format!(
+            "<description>{}</description>\n",
+            escape_xml(&event.class_info.join("\n"))
+        ));
+        rss.push_str(&format!(
+            "<guid isPermaLink=\"false\">{}</guid>\n",
+            escape_xml(&event.id)
+        ));
+        rss.push_str("</item>\n");
+    }
+    rss.push_str("</channel>\n</rss>\n");
+
+    rss
+}
+
+/// Exports events as a Markdown table with the columns Date, Title and Class Info, sorted by date
+/// and time.
+///
+/// The `class_info` lines of each event are joined by `<br>`, since a cell cannot span lines.
+pub fn to_markdown(events: &HashSet<Event>) -> String {
+    let mut events: Vec<_> = events.iter().collect();
+    events.sort();
+
+    let mut markdown = String::from("| Date | Title | Class Info |\n| --- | --- | --- |\n");
+    for event in events {
+        markdown.push_str(&format!(
+            "| {} | {} | {} |\n",
+            event.date_time.format(MARKDOWN_DATE_TIME_FORMAT),
+            escape_markdown_cell(&event.title),
+            escape_markdown_cell(&event.class_info.join("<br>"))
+        ));
+    }
+
+    markdown
+}
+
+fn escape_markdown_cell(s: &str) -> String {
+    s.replace('|', "\\|").replace('\n', " ")
+}
+
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn escape_csv_field(s: &str) -> String {
+    if s.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+fn escape_ical_text(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Splits lines longer than 75 octets into multiple lines, as required by iCalendar.
+fn fold_ical_line(line: &str) -> String {
+    let mut folded = String::new();
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > ICAL_MAX_LINE_LENGTH {
+            folded.push_str("\r\n ");
+            length = 1;
+        }
+        folded.push(c);
+        length += c.len_utf8();
+    }
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{FixedOffset, TimeZone};
+
+    use super::*;
+
+    fn event(id: &str, title: &str, hour: u32) -> Event {
+        let mut event = Event::new();
+        event.id = String::from(id);
+        event.title = String::from(title);
+        event.date_time = FixedOffset::east(2 * 3600)
+            .ymd(2021, 5, 4)
+            .and_hms(hour, 0, 0);
+        event.class_info = vec![String::from("Hold 1"), String::from("Niveau: Øvet")];
+        event
+    }
+
+    #[test]
+    fn to_ical_contains_required_fields() {
+        let events: HashSet<Event> = vec![
+            event("event1", "Tennisskole", 18),
+            event("event2", "Træning, voksne", 19),
+        ]
+        .into_iter()
+        .collect();
+
+        let ical = to_ical(&events);
+        let lines: Vec<_> = ical.split("\r\n").collect();
+
+        assert_eq!(lines.first(), Some(&"BEGIN:VCALENDAR"));
+        assert!(lines.contains(&"VERSION:2.0"));
+        assert_eq!(lines.iter().filter(|l| **l == "BEGIN:VEVENT").count(), 2);
+        assert_eq!(lines.iter().filter(|l| **l == "END:VEVENT").count(), 2);
+        assert_eq!(
+            lines.iter().filter(|l| l.starts_with("DTSTAMP:")).count(),
+            2
+        );
+
+        assert!(lines.contains(&"UID:event1"));
+        assert!(lines.contains(&"SUMMARY:Tennisskole"));
+        assert!(lines.contains(&"DTSTART:20210504T160000Z"));
+        assert!(lines.contains(&"DESCRIPTION:Hold 1\\nNiveau: Øvet"));
+
+        assert!(lines.contains(&"UID:event2"));
+        assert!(lines.contains(&"SUMMARY:Træning\\, voksne"));
+        assert!(lines.contains(&"DTSTART:20210504T170000Z"));
+
+        assert_eq!(lines[lines.len() - 2], "END:VCALENDAR");
+    }
+
+    #[test]
+    fn to_csv_writes_sorted_and_quoted_rows() {
+        let events: HashSet<Event> = vec![
+            event("event2", "Træning, voksne", 19),
+            event("event1", "Tennisskole", 18),
+        ]
+        .into_iter()
+        .collect();
+
+        let mut csv = Vec::new();
+        to_csv(&events, &mut csv).unwrap();
+
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "id,title,date_time,class_info\n\
+             event1,Tennisskole,2021-05-04T18:00:00+02:00,Hold 1;Niveau: Øvet\n\
+             event2,\"Træning, voksne\",2021-05-04T19:00:00+02:00,Hold 1;Niveau: Øvet\n"
+        );
+    }
+
+    #[test]
+    fn to_rss_writes_escaped_items_newest_first() {
+        let events: HashSet<Event> = vec![
+            event("event1", "Tennisskole", 18),
+            event("event2", "Træning <voksne> & børn", 19),
+        ]
+        .into_iter()
+        .collect();
+
+        let rss = to_rss(&events);
+
+        assert!(
+            rss.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<rss version=\"2.0\">")
+        );
+        assert_eq!(rss.matches("<item>").count(), 2);
+        assert!(rss.contains("<title>Træning &lt;voksne&gt; &amp; børn</title>"));
+        assert!(rss.contains("<pubDate>Tue, 04 May 2021 19:00:00 +0200</pubDate>"));
+        assert!(rss.contains("<description>Hold 1\nNiveau: Øvet</description>"));
+        assert!(rss.contains("<guid isPermaLink=\"false\">event1</guid>"));
+        assert!(rss.find("event2").unwrap() < rss.find("event1").unwrap());
+        assert!(rss.ends_with("</channel>\n</rss>\n"));
+    }
+
+    #[test]
+    fn to_markdown_writes_sorted_and_escaped_rows() {
+        let events: HashSet<Event> = vec![
+            event("event2", "Træning | voksne", 19),
+            event("event1", "Tennisskole", 18),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(
+            to_markdown(&events),
+            "| Date | Title | Class Info |\n\
+             | --- | --- | --- |\n\
+             | 2021-05-04 18:00 | Tennisskole | Hold 1<br>Niveau: Øvet |\n\
+             | 2021-05-04 19:00 | Træning \\| voksne | Hold 1<br>Niveau: Øvet |\n"
+        );
+    }
+
+    #[test]
+    fn escape_csv_field_doubles_quotes() {
+        assert_eq!(escape_csv_field("Hold \"A\""), "\"Hold \"\"A\"\"\"");
+    }
+
+    #[test]
+    fn fold_ical_line_limits_line_length() {
+        let line = format!("SUMMARY:{}", "a".repeat(100));
+
+        let folded = fold_ical_line(&line);
+
+        assert!(folded
+            .split("\r\n")
+            .all(|l| l.len() <= ICAL_MAX_LINE_LENGTH));
+        assert_eq!(folded.replace("\r\n ", ""), line);
+    }
+}
@@ -1,38 +1,100 @@
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet},
     fmt::{self, Formatter},
+    sync::Mutex,
+    thread,
+    time::Duration,
 };
 
-use reqwest::blocking::Client;
+use log_extern::{debug, warn};
+use rand::Rng;
+use reqwest::{
+    blocking::Client,
+    header::{HeaderName, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED},
+    Proxy, StatusCode, Url,
+};
 use scraper::Html;
 
+#[cfg(feature = "async")]
+use super::async_fetch::AsyncEventFetcher;
 use super::{
     parse::{EventParser, ParseError},
     Event,
 };
+use crate::retry::RetryPolicy;
+
+pub const DEFAULT_BASE_URL: &str = "https://ktk-tennis.halbooking.dk";
+pub const DEFAULT_PAGE_DELAY: Duration = Duration::from_millis(250);
 
 pub struct EventFetcher {
     client: Client,
     parser: EventParser,
+    retry: RetryPolicy,
+    base_url: String,
+    page_delay: Duration,
+    /// The URL to request before fetching pages of events, if any.
+    warm_up_url: Option<String>,
+    /// The pages fetched with validators, by URL, so they are only parsed again once modified.
+    cache: Mutex<HashMap<String, CachedPage>>,
+}
+
+/// A page of events fetched with an `ETag` or `Last-Modified` header.
+struct CachedPage {
+    validators: Validators,
+    events: HashSet<Event>,
+}
+
+/// The headers of a response that a server can use to tell whether a page was modified since.
+#[derive(Debug, Clone, Default)]
+struct Validators {
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+impl Validators {
+    fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none()
+    }
+}
+
+/// The response to a request for a page of events.
+enum Page {
+    /// The page was not modified since it was cached.
+    NotModified,
+    Modified {
+        body: String,
+        validators: Validators,
+    },
 }
 
 impl EventFetcher {
-    pub fn new() -> Result<EventFetcher, reqwest::Error> {
-        Ok(EventFetcher {
-            client: Client::builder()
-                .cookie_store(true) // Required to properly fetch all events
-                .build()?,
-            parser: EventParser::new(),
-        })
+    pub fn builder() -> EventFetcherBuilder {
+        EventFetcherBuilder {
+            retry: RetryPolicy::default(),
+            timeout: None,
+            base_url: String::from(DEFAULT_BASE_URL),
+            page_delay: DEFAULT_PAGE_DELAY,
+            parser: EventParser::default(),
+            proxy: None,
+            warm_up_url: None,
+        }
     }
 
     pub fn fetch_all(&self) -> Result<HashSet<Event>, FetchError> {
+        if let Some(url) = &self.warm_up_url {
+            self.warm_up(url)?;
+        }
+
         let mut events: HashSet<Event> = HashSet::new();
 
         let mut i = 0;
         loop {
+            if i > 0 {
+                thread::sleep(jittered_page_delay(self.page_delay));
+            }
+
             // Fetch and parse event page as HTML
-            let url = Self::events_url(i);
+            let url = events_url(&self.base_url, i);
             let new_events = self.fetch(url.as_str())?;
             if new_events.is_subset(&events) {
                 // No new events, so stop
@@ -46,20 +108,274 @@ impl EventFetcher {
         Ok(events)
     }
 
+    /// Fetches and parses the page of events at `url`. If the page was fetched before with an
+    /// `ETag` or `Last-Modified` header, it is only fetched and parsed again if it was modified
+    /// since, and otherwise the events from before are reused.
     pub fn fetch(&self, url: &str) -> Result<HashSet<Event>, FetchError> {
-        let response = self.client.get(url).send()?;
-        let body = response.text()?;
-        let document = Html::parse_document(&body);
-        let events = self.parser.parse_all(document)?;
-        Ok(events)
+        let cached_validators = self
+            .cache
+            .lock()
+            .unwrap()
+            .get(url)
+            .map(|page| page.validators.clone());
+        let page = self.retry.retry(
+            || self.fetch_page(url, cached_validators.as_ref()),
+            FetchError::is_transient,
+        )?;
+
+        match page {
+            Page::NotModified => {
+                debug!("{} was not modified, so reusing its events.", url);
+                // Validators are only sent for cached pages, so the page is still cached
+                let cache = self.cache.lock().unwrap();
+                Ok(cache
+                    .get(url)
+                    .map(|page| page.events.clone())
+                    .unwrap_or_default())
+            }
+            Page::Modified { body, validators } => {
+                let list_url = events_url(&self.base_url, 0);
+                let events = parse_page(&self.parser, url, &body, Some(&list_url))?;
+                let mut cache = self.cache.lock().unwrap();
+                if validators.is_empty() {
+                    cache.remove(url);
+                } else {
+                    let page = CachedPage {
+                        validators,
+                        events: events.clone(),
+                    };
+                    cache.insert(url.to_string(), page);
+                }
+                Ok(events)
+            }
+        }
     }
 
-    fn events_url(index: u32) -> String {
-        if index == 0 {
-            String::from("https://ktk-tennis.halbooking.dk/newlook/proc_liste.asp?pid=01")
-        } else {
-            format!("https://ktk-tennis.halbooking.dk/newlook/proc_liste.asp?liste=liste1&forrigetype=203&seson=0&scroll={}&pid=01", index - 1)
+    /// Requests `url` and discards the response, so the server can set cookies, e.g. for the
+    /// session or language, before any page of events is fetched.
+    fn warm_up(&self, url: &str) -> Result<(), FetchError> {
+        debug!("Warming up with a request to {}.", url);
+        self.retry
+            .retry(|| self.fetch_page(url, None), FetchError::is_transient)?;
+        Ok(())
+    }
+
+    fn fetch_page(&self, url: &str, validators: Option<&Validators>) -> Result<Page, FetchError> {
+        let mut request = self.client.get(url);
+        if let Some(validators) = validators {
+            if let Some(etag) = &validators.etag {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &validators.last_modified {
+                request = request.header(IF_MODIFIED_SINCE, last_modified);
+            }
         }
+
+        let response = request.send()?;
+        if validators.is_some() && response.status() == StatusCode::NOT_MODIFIED {
+            return Ok(Page::NotModified);
+        }
+        let response = response.error_for_status()?;
+
+        let header = |name: HeaderName| {
+            let value = response.headers().get(name)?;
+            value.to_str().ok().map(String::from)
+        };
+        let validators = Validators {
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+        };
+        Ok(Page::Modified {
+            body: response.text()?,
+            validators,
+        })
+    }
+}
+
+/// Gets the URL of page number `index` of events, starting at 0.
+pub(super) fn events_url(base_url: &str, index: u32) -> String {
+    if index == 0 {
+        format!("{}/newlook/proc_liste.asp?pid=01", base_url)
+    } else {
+        format!(
+            "{}/newlook/proc_liste.asp?liste=liste1&forrigetype=203&seson=0&scroll={}&pid=01",
+            base_url,
+            index - 1
+        )
+    }
+}
+
+/// Parses the events on a page fetched from `url`, skipping rows that fail to parse.
+///
+/// The links of events are resolved relative to `url`, and events without a link get `list_url`,
+/// if any, i.e. the URL of the list of events of the club.
+///
+/// # Errors
+///
+/// Returns an error if there are rows but none of them can be parsed.
+pub(crate) fn parse_page(
+    parser: &EventParser,
+    url: &str,
+    body: &str,
+    list_url: Option<&str>,
+) -> Result<HashSet<Event>, FetchError> {
+    let document = Html::parse_document(body);
+    let (events, mut errors) = parser.parse_all(document);
+
+    // If no rows could be parsed at all, the page layout has most likely changed
+    if events.is_empty() && !errors.is_empty() {
+        return Err(FetchError::Parse(errors.remove(0)));
+    }
+    for error in &errors {
+        warn!("Skipping event on {}: {}", url, error);
+    }
+
+    let page_url = Url::parse(url).ok();
+    Ok(events
+        .into_iter()
+        .map(|mut event| {
+            event.url = match (event.url.take(), &page_url) {
+                (Some(link), Some(page_url)) => {
+                    Some(page_url.join(&link).map(String::from).unwrap_or(link))
+                }
+                (link, _) => link.or_else(|| list_url.map(String::from)),
+            };
+            event
+        })
+        .collect())
+}
+
+/// Gets the delay to wait before fetching the next page, which is `page_delay` plus a random
+/// jitter of up to a quarter of it.
+pub(super) fn jittered_page_delay(page_delay: Duration) -> Duration {
+    let jitter = page_delay.mul_f64(rand::thread_rng().gen_range(0.0..0.25));
+    page_delay + jitter
+}
+
+/// A builder for configuring and creating an [`EventFetcher`].
+pub struct EventFetcherBuilder {
+    retry: RetryPolicy,
+    timeout: Option<Duration>,
+    base_url: String,
+    page_delay: Duration,
+    parser: EventParser,
+    proxy: Option<String>,
+    warm_up_url: Option<String>,
+}
+
+impl EventFetcherBuilder {
+    /// Sets the policy for retrying requests that fail due to network or server errors.
+    pub fn retry(&mut self, retry: RetryPolicy) -> &mut Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Sets the timeout for each request, after which it fails with [`FetchError::Request`].
+    pub fn timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the URL of the halbooking site to fetch events from, e.g.
+    /// `https://ktk-tennis.halbooking.dk`.
+    pub fn base_url(&mut self, base_url: impl Into<String>) -> &mut Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Sets the minimum delay between fetching successive pages, to avoid flooding the server
+    /// with requests. No delay is made before fetching the first page.
+    pub fn page_delay(&mut self, page_delay: Duration) -> &mut Self {
+        self.page_delay = page_delay;
+        self
+    }
+
+    /// Sets the parser for the fetched pages, e.g. one with selectors matching changed markup.
+    pub fn parser(&mut self, parser: EventParser) -> &mut Self {
+        self.parser = parser;
+        self
+    }
+
+    /// Sets the URL of a proxy to make all requests through, e.g. `http://proxy.example.com:3128`.
+    ///
+    /// Without a proxy, the proxy given by the `HTTP_PROXY` and `HTTPS_PROXY` environment
+    /// variables is used, if any. A proxy set here takes precedence over them.
+    pub fn proxy(&mut self, proxy: impl Into<String>) -> &mut Self {
+        self.proxy = Some(proxy.into());
+        self
+    }
+
+    /// Sets a URL to request before fetching pages of events, e.g. a landing page that sets the
+    /// session or language cookies needed for the pages to list all events. The URL is either
+    /// absolute or relative to the base URL. No request is made without it.
+    pub fn warm_up_url(&mut self, warm_up_url: impl Into<String>) -> &mut Self {
+        self.warm_up_url = Some(warm_up_url.into());
+        self
+    }
+
+    /// Resolves the warm-up URL against the base URL. URLs that cannot be resolved are kept as is,
+    /// so requesting them fails with an error describing the URL.
+    fn resolved_warm_up_url(&self) -> Option<String> {
+        let url = self.warm_up_url.as_ref()?;
+        let resolved = Url::parse(&format!("{}/", self.base_url))
+            .and_then(|base| base.join(url))
+            .map(String::from);
+        Some(resolved.unwrap_or_else(|_| url.clone()))
+    }
+
+    /// Builds an [`EventFetcher`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the proxy URL is invalid or the HTTP client cannot be created.
+    pub fn build(&self) -> Result<EventFetcher, reqwest::Error> {
+        // Storing cookies is required to properly fetch all events. Pages of events are large, so
+        // ask for them compressed, which is decompressed transparently before parsing.
+        let mut client = Client::builder()
+            .cookie_store(true)
+            .gzip(true)
+            .deflate(true);
+        if let Some(timeout) = self.timeout {
+            client = client.timeout(timeout);
+        }
+        // Without a proxy set here, reqwest uses the proxies of the environment variables
+        if let Some(proxy) = &self.proxy {
+            client = client.proxy(Proxy::all(proxy)?);
+        }
+
+        Ok(EventFetcher {
+            client: client.build()?,
+            parser: self.parser.clone(),
+            retry: self.retry,
+            base_url: self.base_url.clone(),
+            page_delay: self.page_delay,
+            warm_up_url: self.resolved_warm_up_url(),
+            cache: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Builds an [`AsyncEventFetcher`] instead, which fetches events without blocking.
+    #[cfg(feature = "async")]
+    pub fn build_async(&self) -> Result<AsyncEventFetcher, reqwest::Error> {
+        let mut client = reqwest::Client::builder()
+            .cookie_store(true)
+            .gzip(true)
+            .deflate(true);
+        if let Some(timeout) = self.timeout {
+            client = client.timeout(timeout);
+        }
+        if let Some(proxy) = &self.proxy {
+            client = client.proxy(Proxy::all(proxy)?);
+        }
+
+        Ok(AsyncEventFetcher {
+            client: client.build()?,
+            parser: self.parser.clone(),
+            retry: self.retry,
+            base_url: self.base_url.clone(),
+            page_delay: self.page_delay,
+            warm_up_url: self.resolved_warm_up_url(),
+        })
     }
 }
 
@@ -69,6 +385,19 @@ pub enum FetchError {
     Request(reqwest::Error),
 }
 
+impl FetchError {
+    /// Whether the error is likely to go away by itself, i.e. it is a network error or a server
+    /// error response. Parse errors and client error responses are never transient.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            FetchError::Parse(_) => false,
+            FetchError::Request(error) => {
+                error.status().is_none_or(|status| status.is_server_error())
+            }
+        }
+    }
+}
+
 impl fmt::Display for FetchError {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
@@ -89,3 +418,304 @@ impl From<ParseError> for FetchError {
         FetchError::Parse(error)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{parser, MockResponse, MockServer};
+
+    const EVENT_PAGE: &str = r#"<table><tbody>
+        <tr class="infinite-item" id="event1">
+            <td class="liste_wide min992">Tennisskole<br>tir 5. maj 2021<br>18:00 - 19:00</td>
+            <td class="liste_wide min992 holdinfo">Hold 1</td>
+        </tr>
+    </tbody></table>"#;
+
+    const MALFORMED_PAGE: &str = r#"<table><tbody>
+        <tr class="infinite-item" id="event1">
+            <td class="liste_wide min992">Tennisskole</td>
+        </tr>
+    </tbody></table>"#;
+
+    fn fetcher(max_attempts: u32) -> EventFetcher {
+        EventFetcher::builder()
+            .retry(RetryPolicy::new(
+                max_attempts,
+                Duration::from_millis(1),
+                2.0,
+            ))
+            .parser(parser())
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn events_url_uses_base_url() {
+        let fetcher = EventFetcher::builder()
+            .base_url("https://other-club.halbooking.dk/")
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            events_url(&fetcher.base_url, 0),
+            "https://other-club.halbooking.dk/newlook/proc_liste.asp?pid=01"
+        );
+        assert_eq!(
+            events_url(&fetcher.base_url, 3),
+            "https://other-club.halbooking.dk/newlook/proc_liste.asp?liste=liste1&forrigetype=203&seson=0&scroll=2&pid=01"
+        );
+    }
+
+    #[test]
+    fn parse_page_resolves_links_of_events() {
+        let page = r#"<table><tbody>
+            <tr class="infinite-item" id="event1">
+                <td class="liste_wide min992">Tennisskole<br>tir 5. maj 2021<br>18:00 - 19:00</td>
+                <td class="liste_wide min992 holdinfo">
+                    <a href="proc_holdinfo.asp?hold=1">Hold 1</a>
+                </td>
+            </tr>
+            <tr class="infinite-item" id="event2">
+                <td class="liste_wide min992">Padel<br>tir 5. maj 2021<br>19:00 - 20:00</td>
+            </tr>
+        </tbody></table>"#;
+        let list_url = events_url(DEFAULT_BASE_URL, 0);
+
+        let events = parse_page(&parser(), &list_url, page, Some(&list_url)).unwrap();
+
+        let url = |id: &str| {
+            events
+                .iter()
+                .find(|event| event.id == id)
+                .unwrap()
+                .url
+                .clone()
+        };
+        assert_eq!(
+            url("event1").as_deref(),
+            Some("https://ktk-tennis.halbooking.dk/newlook/proc_holdinfo.asp?hold=1")
+        );
+        assert_eq!(url("event2"), Some(list_url));
+    }
+
+    #[test]
+    fn fetch_all_delays_only_between_pages() {
+        let server = MockServer::start(vec![
+            MockResponse::new(200, EVENT_PAGE),
+            MockResponse::new(200, EVENT_PAGE),
+        ]);
+        let fetcher = EventFetcher::builder()
+            .parser(parser())
+            .base_url(server.url())
+            .page_delay(Duration::from_millis(200))
+            .build()
+            .unwrap();
+
+        let start = std::time::Instant::now();
+        let events = fetcher.fetch_all().unwrap();
+        let elapsed = start.elapsed();
+
+        assert_eq!(events.len(), 1);
+        // A single delay of at most 250ms between the two pages, and none before the first
+        assert!(elapsed >= Duration::from_millis(200), "{:?}", elapsed);
+        assert!(elapsed < Duration::from_millis(400), "{:?}", elapsed);
+    }
+
+    #[test]
+    fn fetch_all_decompresses_gzipped_pages() {
+        const GZIPPED_PAGE: &[u8] = include_bytes!("../../tests/fixtures/liste.html.gz");
+        let server = MockServer::start(vec![
+            MockResponse::new(200, GZIPPED_PAGE).header("Content-Encoding", "gzip"),
+            MockResponse::new(200, GZIPPED_PAGE).header("Content-Encoding", "gzip"),
+        ]);
+        let fetcher = EventFetcher::builder()
+            .parser(parser())
+            .base_url(server.url())
+            .page_delay(Duration::from_millis(0))
+            .build()
+            .unwrap();
+
+        let events = fetcher.fetch_all().unwrap();
+
+        assert_eq!(events.len(), 4);
+        let request = server.requests()[0].to_ascii_lowercase();
+        assert!(
+            request.contains("accept-encoding: gzip, deflate"),
+            "{}",
+            request
+        );
+    }
+
+    #[test]
+    fn fetch_all_reuses_events_of_pages_not_modified() {
+        let server = MockServer::start(vec![
+            MockResponse::new(200, EVENT_PAGE).header("ETag", "\"page0\""),
+            MockResponse::new(200, EVENT_PAGE)
+                .header("Last-Modified", "Tue, 04 May 2021 12:00:00 GMT"),
+            MockResponse::new(304, ""),
+            MockResponse::new(304, ""),
+        ]);
+        let fetcher = EventFetcher::builder()
+            .parser(parser())
+            .base_url(server.url())
+            .page_delay(Duration::from_millis(0))
+            .build()
+            .unwrap();
+
+        let events = fetcher.fetch_all().unwrap();
+        let unmodified_events = fetcher.fetch_all().unwrap();
+
+        assert_eq!(unmodified_events.len(), 1);
+        assert!(unmodified_events
+            .iter()
+            .zip(&events)
+            .all(|(a, b)| a.content_eq(b)));
+        let requests: Vec<_> = server
+            .requests()
+            .iter()
+            .map(|r| r.to_ascii_lowercase())
+            .collect();
+        assert!(!requests[0].contains("if-none-match"));
+        assert!(
+            requests[2].contains("if-none-match: \"page0\""),
+            "{}",
+            requests[2]
+        );
+        assert!(requests[3].contains("if-modified-since: tue, 04 may 2021 12:00:00 gmt"));
+    }
+
+    #[test]
+    fn fetch_all_requests_through_proxy() {
+        let proxy = MockServer::start(vec![
+            MockResponse::new(200, EVENT_PAGE),
+            MockResponse::new(200, EVENT_PAGE),
+        ]);
+        let fetcher = EventFetcher::builder()
+            .parser(parser())
+            .base_url("http://ktk-tennis.invalid")
+            .page_delay(Duration::from_millis(0))
+            .proxy(proxy.url())
+            .build()
+            .unwrap();
+
+        let events = fetcher.fetch_all().unwrap();
+
+        assert_eq!(events.len(), 1);
+        // Requests through a proxy have the absolute URL in the request line
+        let request = &proxy.requests()[0];
+        assert!(
+            request.starts_with("GET http://ktk-tennis.invalid/newlook/proc_liste.asp?pid=01 "),
+            "{}",
+            request
+        );
+    }
+
+    #[test]
+    fn fetch_all_warms_up_cookies_before_fetching_pages() {
+        let server = MockServer::start(vec![
+            MockResponse::new(200, "").header("Set-Cookie", "language=da; Path=/"),
+            MockResponse::new(200, EVENT_PAGE),
+            MockResponse::new(200, EVENT_PAGE),
+        ]);
+        let fetcher = EventFetcher::builder()
+            .parser(parser())
+            .base_url(server.url())
+            .page_delay(Duration::from_millis(0))
+            .warm_up_url("newlook/default.asp")
+            .build()
+            .unwrap();
+
+        let events = fetcher.fetch_all().unwrap();
+
+        assert_eq!(events.len(), 1);
+        let requests: Vec<_> = server
+            .requests()
+            .iter()
+            .map(|r| r.to_ascii_lowercase())
+            .collect();
+        assert!(
+            requests[0].starts_with("get /newlook/default.asp "),
+            "{}",
+            requests[0]
+        );
+        assert!(
+            requests[1].contains("cookie: language=da"),
+            "{}",
+            requests[1]
+        );
+    }
+
+    #[test]
+    fn build_fails_with_invalid_proxy() {
+        assert!(EventFetcher::builder()
+            .proxy("not a proxy")
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn fetch_retries_transient_errors() {
+        let server = MockServer::start(vec![
+            MockResponse::new(500, ""),
+            MockResponse::new(503, ""),
+            MockResponse::new(200, EVENT_PAGE),
+        ]);
+
+        let events = fetcher(3).fetch(server.url()).unwrap();
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events.iter().next().unwrap().id, "event1");
+    }
+
+    #[test]
+    fn fetch_gives_up_after_max_attempts() {
+        let server = MockServer::start(vec![
+            MockResponse::new(500, ""),
+            MockResponse::new(500, ""),
+            MockResponse::new(200, EVENT_PAGE),
+        ]);
+
+        let result = fetcher(2).fetch(server.url());
+
+        assert!(matches!(result, Err(FetchError::Request(_))));
+    }
+
+    #[test]
+    fn fetch_does_not_retry_client_errors() {
+        let server = MockServer::start(vec![
+            MockResponse::new(404, ""),
+            MockResponse::new(200, EVENT_PAGE),
+        ]);
+
+        let result = fetcher(3).fetch(server.url());
+
+        assert!(matches!(result, Err(FetchError::Request(_))));
+    }
+
+    #[test]
+    fn fetch_does_not_retry_parse_errors() {
+        let server = MockServer::start(vec![
+            MockResponse::new(200, MALFORMED_PAGE),
+            MockResponse::new(200, EVENT_PAGE),
+        ]);
+
+        let result = fetcher(3).fetch(server.url());
+
+        assert!(matches!(result, Err(FetchError::Parse(_))));
+    }
+
+    #[test]
+    fn fetch_skips_malformed_rows() {
+        let page = EVENT_PAGE.replace(
+            "</tbody>",
+            r#"<tr class="infinite-item" id="event2">
+                <td class="liste_wide min992">Tennisskole</td>
+            </tr></tbody>"#,
+        );
+        let server = MockServer::start(vec![MockResponse::new(200, page)]);
+
+        let events = fetcher(1).fetch(server.url()).unwrap();
+
+        assert_eq!(events.len(), 1);
+    }
+}
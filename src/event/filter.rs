@@ -0,0 +1,330 @@
+use std::{collections::HashSet, time::Duration};
+
+use chrono::{DateTime, Datelike, FixedOffset, Weekday};
+
+use super::Event;
+
+/// The keys of the class info that give the level of an event, in lowercase.
+const LEVEL_KEYS: &[&str] = &["niveau", "level"];
+
+/// Keeps only the events whose title matches the given keywords. See [`is_match`].
+pub fn apply_filters<'a>(
+    events: Vec<&'a Event>,
+    includes: &[String],
+    excludes: &[String],
+) -> Vec<&'a Event> {
+    events
+        .into_iter()
+        .filter(|event| is_match(event, includes, excludes))
+        .collect()
+}
+
+/// Whether the title of `event` contains any of `includes` and none of `excludes`, ignoring case.
+/// All events match an empty list of `includes`.
+pub fn is_match(event: &Event, includes: &[String], excludes: &[String]) -> bool {
+    let title = event.title.to_lowercase();
+    let contains = |keyword: &String| title.contains(&keyword.to_lowercase());
+
+    (includes.is_empty() || includes.iter().any(contains)) && !excludes.iter().any(contains)
+}
+
+/// Keeps only the events that take place in the given range. See [`is_in_range`].
+pub fn apply_date_range(
+    events: Vec<&Event>,
+    from: Option<DateTime<FixedOffset>>,
+    until: Option<DateTime<FixedOffset>>,
+) -> Vec<&Event> {
+    events
+        .into_iter()
+        .filter(|event| is_in_range(event, from, until))
+        .collect()
+}
+
+/// Whether `event` takes place at or after `from` and strictly before `until`. Missing bounds are
+/// not checked.
+pub fn is_in_range(
+    event: &Event,
+    from: Option<DateTime<FixedOffset>>,
+    until: Option<DateTime<FixedOffset>>,
+) -> bool {
+    from.is_none_or(|from| event.date_time >= from)
+        && until.is_none_or(|until| event.date_time < until)
+}
+
+/// Keeps only the events that take place on the given weekdays. See [`is_on_weekdays`].
+pub fn apply_weekdays<'a>(events: Vec<&'a Event>, weekdays: &HashSet<Weekday>) -> Vec<&'a Event> {
+    events
+        .into_iter()
+        .filter(|event| is_on_weekdays(event, weekdays))
+        .collect()
+}
+
+/// Whether `event` takes place on any of `weekdays`. All events match an empty set of `weekdays`.
+pub fn is_on_weekdays(event: &Event, weekdays: &HashSet<Weekday>) -> bool {
+    weekdays.is_empty() || weekdays.contains(&event.date_time.weekday())
+}
+
+/// Keeps only the events that take place at least `min_advance` after `now`. See
+/// [`is_in_advance`].
+pub fn apply_min_advance(
+    events: Vec<&Event>,
+    now: DateTime<FixedOffset>,
+    min_advance: Option<Duration>,
+) -> Vec<&Event> {
+    events
+        .into_iter()
+        .filter(|event| is_in_advance(event, now, min_advance))
+        .collect()
+}
+
+/// Whether `event` takes place at least `min_advance` after `now`. All events match a missing
+/// `min_advance`.
+pub fn is_in_advance(
+    event: &Event,
+    now: DateTime<FixedOffset>,
+    min_advance: Option<Duration>,
+) -> bool {
+    min_advance.is_none_or(|min_advance| {
+        chrono::Duration::from_std(min_advance)
+            .ok()
+            .and_then(|min_advance| now.checked_add_signed(min_advance))
+            .is_some_and(|earliest| event.date_time >= earliest)
+    })
+}
+
+/// Keeps only the events at any of the given levels. See [`is_at_level`].
+pub fn apply_levels<'a>(
+    events: Vec<&'a Event>,
+    levels: &[String],
+    unknown_ok: bool,
+) -> Vec<&'a Event> {
+    events
+        .into_iter()
+        .filter(|event| is_at_level(event, levels, unknown_ok))
+        .collect()
+}
+
+/// Whether the level of `event`, i.e. the "Niveau" of its class info, is any of `levels`, ignoring
+/// case. Events without a level only match if `unknown_ok`. All events match an empty list of
+/// `levels`.
+pub fn is_at_level(event: &Event, levels: &[String], unknown_ok: bool) -> bool {
+    if levels.is_empty() {
+        return true;
+    }
+
+    match level(event).map(str::to_lowercase) {
+        Some(level) => levels.iter().any(|wanted| wanted.to_lowercase() == level),
+        None => unknown_ok,
+    }
+}
+
+/// Gets the level of `event`, i.e. the "Niveau" of its class info, if listed.
+pub fn level(event: &Event) -> Option<&str> {
+    event
+        .class_info_map
+        .iter()
+        .find(|(key, _)| LEVEL_KEYS.contains(&key.to_lowercase().as_str()))
+        .map(|(_, level)| level.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Timelike};
+
+    use super::*;
+
+    fn event(title: &str) -> Event {
+        let mut event = Event::new();
+        event.id = String::from(title);
+        event.title = String::from(title);
+        event
+    }
+
+    fn keywords(keywords: &[&str]) -> Vec<String> {
+        keywords.iter().map(|keyword| keyword.to_string()).collect()
+    }
+
+    #[test]
+    fn apply_filters_keeps_everything_without_keywords() {
+        let events = [event("Tennisskole"), event("Padel")];
+
+        let filtered = apply_filters(events.iter().collect(), &[], &[]);
+
+        assert_eq!(filtered.len(), 2);
+    }
+
+    #[test]
+    fn apply_filters_keeps_only_included_events() {
+        let events = [
+            event("Tennisskole"),
+            event("Padel"),
+            event("Tennis for voksne"),
+        ];
+
+        let filtered = apply_filters(events.iter().collect(), &keywords(&["TENNIS"]), &[]);
+
+        assert_eq!(filtered, vec![&events[0], &events[2]]);
+    }
+
+    #[test]
+    fn apply_filters_removes_excluded_events() {
+        let events = [event("Tennisskole"), event("Padel for begyndere")];
+
+        let filtered = apply_filters(events.iter().collect(), &[], &keywords(&["padel"]));
+
+        assert_eq!(filtered, vec![&events[0]]);
+    }
+
+    #[test]
+    fn apply_filters_prefers_excludes_over_includes() {
+        let events = [event("Tennisskole"), event("Tennis og padel")];
+
+        let filtered = apply_filters(
+            events.iter().collect(),
+            &keywords(&["tennis"]),
+            &keywords(&["padel"]),
+        );
+
+        assert_eq!(filtered, vec![&events[0]]);
+    }
+
+    fn date_time(day: u32) -> DateTime<FixedOffset> {
+        FixedOffset::east(2 * 3600)
+            .ymd(2021, 5, day)
+            .and_hms(0, 0, 0)
+    }
+
+    fn event_on(day: u32) -> Event {
+        let mut event = event(&format!("event{}", day));
+        event.date_time = date_time(day).with_hour(18).unwrap();
+        event
+    }
+
+    #[test]
+    fn apply_date_range_keeps_everything_without_bounds() {
+        let events = [event_on(1), event_on(31)];
+
+        let filtered = apply_date_range(events.iter().collect(), None, None);
+
+        assert_eq!(filtered.len(), 2);
+    }
+
+    #[test]
+    fn apply_date_range_keeps_only_events_in_range() {
+        let events = [event_on(1), event_on(10), event_on(20), event_on(31)];
+
+        let filtered = apply_date_range(
+            events.iter().collect(),
+            Some(date_time(10)),
+            Some(date_time(21)),
+        );
+
+        assert_eq!(filtered, vec![&events[1], &events[2]]);
+    }
+
+    #[test]
+    fn is_on_weekdays_uses_weekday_of_event() {
+        // 1 May 2021 is a Saturday
+        assert_eq!(event_on(1).date_time.weekday(), Weekday::Sat);
+        let weekend: HashSet<_> = vec![Weekday::Sat, Weekday::Sun].into_iter().collect();
+
+        assert!(is_on_weekdays(&event_on(1), &weekend));
+        assert!(is_on_weekdays(&event_on(2), &weekend));
+        assert!(!is_on_weekdays(&event_on(3), &weekend));
+        assert!(is_on_weekdays(&event_on(3), &HashSet::new()));
+    }
+
+    #[test]
+    fn apply_weekdays_keeps_only_events_on_weekdays() {
+        let events = [event_on(1), event_on(3), event_on(8), event_on(9)];
+        let weekdays: HashSet<_> = vec![Weekday::Sat].into_iter().collect();
+
+        let filtered = apply_weekdays(events.iter().collect(), &weekdays);
+
+        assert_eq!(filtered, vec![&events[0], &events[2]]);
+    }
+
+    #[test]
+    fn is_in_advance_requires_min_advance_from_now() {
+        let now = date_time(10).with_hour(12).unwrap();
+        let hours = |hours: u64| Some(Duration::from_secs(hours * 3600));
+
+        // The event starts at 18:00, 6 hours from now
+        assert!(is_in_advance(&event_on(10), now, None));
+        assert!(is_in_advance(&event_on(10), now, hours(6)));
+        assert!(!is_in_advance(&event_on(10), now, hours(7)));
+        assert!(!is_in_advance(&event_on(9), now, hours(0)));
+    }
+
+    #[test]
+    fn apply_min_advance_keeps_only_events_far_enough_ahead() {
+        let events = [event_on(10), event_on(11), event_on(12)];
+        let now = date_time(10).with_hour(12).unwrap();
+
+        let filtered = apply_min_advance(
+            events.iter().collect(),
+            now,
+            Some(Duration::from_secs(24 * 3600)),
+        );
+
+        assert_eq!(filtered, vec![&events[1], &events[2]]);
+    }
+
+    fn event_at_level(level: Option<&str>) -> Event {
+        let mut event = event("Tennisskole");
+        if let Some(level) = level {
+            event
+                .class_info_map
+                .insert(String::from("Niveau"), String::from(level));
+        }
+        event
+    }
+
+    #[test]
+    fn is_at_level_ignores_case() {
+        let levels = keywords(&["øvet", "Let øvet"]);
+
+        assert!(is_at_level(&event_at_level(Some("Øvet")), &levels, false));
+        assert!(is_at_level(
+            &event_at_level(Some("LET ØVET")),
+            &levels,
+            false
+        ));
+        assert!(!is_at_level(
+            &event_at_level(Some("Begynder")),
+            &levels,
+            false
+        ));
+        assert!(is_at_level(&event_at_level(Some("Begynder")), &[], false));
+    }
+
+    #[test]
+    fn apply_levels_keeps_unknown_levels_only_if_ok() {
+        let events = [event_at_level(Some("Øvet")), event_at_level(None)];
+        let levels = keywords(&["Øvet"]);
+
+        assert_eq!(
+            apply_levels(events.iter().collect(), &levels, false),
+            vec![&events[0]]
+        );
+        assert_eq!(
+            apply_levels(events.iter().collect(), &levels, true),
+            vec![&events[0], &events[1]]
+        );
+    }
+
+    #[test]
+    fn filters_compose() {
+        let mut padel = event_on(10);
+        padel.title = String::from("Padel");
+        let events = [event_on(1), event_on(10), padel];
+
+        let filtered = apply_date_range(
+            apply_filters(events.iter().collect(), &[], &keywords(&["padel"])),
+            Some(date_time(10)),
+            None,
+        );
+
+        assert_eq!(filtered, vec![&events[1]]);
+    }
+}
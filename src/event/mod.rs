@@ -1,54 +1,273 @@
+#[cfg(feature = "async")]
+pub mod async_fetch;
+pub mod diff;
+pub(crate) mod export;
 pub mod fetch;
+pub mod filter;
 pub mod parse;
 
-use chrono::{DateTime, FixedOffset, TimeZone};
+use chrono::{DateTime, FixedOffset, NaiveDateTime, NaiveTime, Offset, TimeZone, Utc};
+use chrono_tz::Europe::Copenhagen;
 use serde::{Deserialize, Serialize};
 use std::{
     cmp::Ordering,
-    collections::HashSet,
-    fs::File,
+    collections::{BTreeMap, HashSet},
+    fmt::{self, Display, Formatter},
+    fs::{self, File},
     hash::{Hash, Hasher},
-    io::Write,
-    path::Path,
+    io::{self, BufWriter},
+    path::{Path, PathBuf},
 };
 
-pub fn serialize_events(
+/// Writes `events` to `path` as pretty-printed JSON, sorted by id so the same events are always
+/// written the same way.
+///
+/// The JSON is streamed to a temporary file next to `path`, which then replaces `path`, so `path`
+/// is never left partially written if the program is interrupted. Streaming avoids holding the
+/// whole JSON in memory, which matters for large sets of events.
+pub(crate) fn serialize_events(
     events: &HashSet<Event>,
     path: &Path,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let json = serde_json::to_string(&events)?;
+    let mut events: Vec<&Event> = events.iter().collect();
+    events.sort_by(|a, b| a.id.cmp(&b.id));
 
-    let mut file = File::create(&path)?;
-    file.write_all(json.as_bytes())?;
+    let temp_path = temp_path(path);
+    let mut writer = BufWriter::new(File::create(&temp_path)?);
+    serde_json::to_writer_pretty(&mut writer, &events)?;
+    let file = writer.into_inner()?;
+    file.sync_all()?;
+    fs::rename(&temp_path, path)?;
 
     Ok(())
 }
 
-pub fn deserialize_events(path: &Path) -> Result<HashSet<Event>, Box<dyn std::error::Error>> {
+/// Keeps up to `count` backups of the file at `path` by copying it to `path` with `.bak` appended,
+/// e.g. `events.json.bak`. Older backups are moved to `events.json.bak.2`, `events.json.bak.3`, and
+/// so on, and the oldest backup is removed once there are `count` backups.
+///
+/// Nothing is done if `count` is 0 or there is no file at `path`.
+pub(crate) fn back_up(path: &Path, count: usize) -> io::Result<()> {
+    if count == 0 || !path.exists() {
+        return Ok(());
+    }
+
+    for number in (1..count).rev() {
+        let backup = backup_path(path, number);
+        if backup.exists() {
+            fs::rename(&backup, backup_path(path, number + 1))?;
+        }
+    }
+    fs::copy(path, backup_path(path, 1))?;
+
+    Ok(())
+}
+
+/// Gets the path of backup number `number` of `path`, where 1 is the most recent backup.
+fn backup_path(path: &Path, number: usize) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".bak");
+    if number > 1 {
+        file_name.push(format!(".{}", number));
+    }
+    path.with_file_name(file_name)
+}
+
+/// Gets the path of the temporary file used when writing to `path`, e.g. `events.json.tmp` for
+/// `events.json`.
+fn temp_path(path: &Path) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".tmp");
+    path.with_file_name(file_name)
+}
+
+pub(crate) fn deserialize_events(
+    path: &Path,
+) -> Result<HashSet<Event>, Box<dyn std::error::Error>> {
     let file = File::open(&path)?;
     let events: HashSet<Event> = serde_json::from_reader(file)?;
     Ok(events)
 }
 
-#[derive(Debug, Serialize, Deserialize, Eq)]
+/// Converts a wall-clock time in Denmark to a time with the UTC offset in effect at that time, i.e.
+/// +01:00 in winter and +02:00 in summer.
+///
+/// Returns `None` if the time does not exist because it is skipped when switching to summer time.
+/// Times that occur twice when switching to winter time resolve to the first occurrence.
+pub(crate) fn local_date_time(naive: NaiveDateTime) -> Option<DateTime<FixedOffset>> {
+    let local = Copenhagen.from_local_datetime(&naive).earliest()?;
+    Some(local.with_timezone(&local.offset().fix()))
+}
+
+/// Gets the current wall-clock date and time in Denmark.
+pub(crate) fn local_now() -> NaiveDateTime {
+    Utc::now().with_timezone(&Copenhagen).naive_local()
+}
+
+/// Gets the current time in Denmark with the UTC offset in effect now.
+pub(crate) fn local_date_time_now() -> DateTime<FixedOffset> {
+    let now = Utc::now().with_timezone(&Copenhagen);
+    now.with_timezone(&now.offset().fix())
+}
+
+/// Gets the current wall-clock time of day in Denmark.
+pub(crate) fn local_time_now() -> NaiveTime {
+    local_now().time()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Eq)]
+#[serde(from = "StoredEvent")]
 pub struct Event {
     pub id: String,
     pub title: String,
+    /// The start of the event in local time, as listed by the club.
     pub date_time: DateTime<FixedOffset>,
+    /// The start of the event in UTC, computed once from `date_time` when the event is parsed.
+    pub start_utc: DateTime<Utc>,
     pub class_info: Vec<String>,
+    /// The lines of the class info that are key/value pairs, e.g. "Niveau: Øvet", by key. Sorted,
+    /// so events are always written the same way.
+    pub class_info_map: BTreeMap<String, String>,
+    /// The lines of the class info that are not key/value pairs.
+    pub class_info_rest: Vec<String>,
+    /// The number of the class (hold) that the event belongs to, if listed in the class info.
+    pub class_number: Option<String>,
+    /// The price in kroner, if listed in the class info.
+    pub price: Option<u32>,
+    /// The court or hall the event takes place at.
+    pub location: Option<String>,
+    /// The name of the trainer of coaching sessions, if listed in the class info.
+    pub instructor: Option<String>,
+    /// The number of open spots, where fully booked events have `Some(0)`, or `None` if unknown.
+    pub available_spots: Option<u32>,
+    /// The link to the event, e.g. its booking page, or to the list of events of its club if the
+    /// event has no link of its own.
+    pub url: Option<String>,
+    /// When the bot first fetched the event.
+    pub first_seen: DateTime<Utc>,
+    /// When the bot last fetched the event.
+    pub last_seen: DateTime<Utc>,
+}
+
+/// An [`Event`] as stored in an events file, where fields added in later versions may be missing.
+/// The key/value pairs of the class info are always split from the raw lines again.
+#[derive(Deserialize)]
+struct StoredEvent {
+    id: String,
+    title: String,
+    date_time: DateTime<FixedOffset>,
+    #[serde(default)]
+    start_utc: Option<DateTime<Utc>>,
+    class_info: Vec<String>,
+    #[serde(default)]
+    class_number: Option<String>,
+    #[serde(default)]
+    price: Option<u32>,
+    #[serde(default)]
+    location: Option<String>,
+    #[serde(default)]
+    instructor: Option<String>,
+    #[serde(default)]
+    available_spots: Option<u32>,
+    #[serde(default)]
+    url: Option<String>,
+    #[serde(default)]
+    first_seen: Option<DateTime<Utc>>,
+    #[serde(default)]
+    last_seen: Option<DateTime<Utc>>,
+}
+
+impl From<StoredEvent> for Event {
+    fn from(stored: StoredEvent) -> Self {
+        // Events stored before titles and class info were canonicalized would otherwise look
+        // changed on the next fetch
+        let class_info: Vec<String> = stored
+            .class_info
+            .iter()
+            .map(|line| parse::canonicalize(line))
+            .collect();
+        let (class_info_map, class_info_rest) = parse::split_class_info(&class_info);
+        // Events stored before the bot kept track of when events were seen are first seen now
+        let now = Utc::now();
+        Event {
+            start_utc: stored
+                .start_utc
+                .unwrap_or_else(|| stored.date_time.with_timezone(&Utc)),
+            id: stored.id,
+            title: parse::canonicalize(&stored.title),
+            date_time: stored.date_time,
+            class_info,
+            class_info_map,
+            class_info_rest,
+            class_number: stored.class_number,
+            price: stored.price,
+            location: stored.location,
+            instructor: stored.instructor,
+            available_spots: stored.available_spots,
+            url: stored.url,
+            first_seen: stored.first_seen.unwrap_or(now),
+            last_seen: stored.last_seen.unwrap_or(now),
+        }
+    }
 }
 
 impl Event {
+    pub fn builder() -> EventBuilder {
+        EventBuilder::default()
+    }
+
+    /// Creates a placeholder event for tests, which only need to set the fields they care about.
+    #[cfg(test)]
     pub fn new() -> Event {
+        let date_time =
+            local_date_time(chrono::NaiveDate::from_ymd(2021, 6, 30).and_hms(0, 0, 0)).unwrap();
         Event {
             id: String::default(),
             title: String::default(),
-            date_time: FixedOffset::east(2 * 3600)
-                .ymd(2021, 6, 30)
-                .and_hms(0, 0, 0),
+            date_time,
+            start_utc: date_time.with_timezone(&Utc),
             class_info: Vec::default(),
+            class_info_map: BTreeMap::default(),
+            class_info_rest: Vec::default(),
+            class_number: None,
+            price: None,
+            location: None,
+            instructor: None,
+            available_spots: None,
+            url: None,
+            first_seen: date_time.with_timezone(&Utc),
+            last_seen: date_time.with_timezone(&Utc),
         }
     }
+
+    /// Whether the event can still be booked. Events with an unknown number of open spots are
+    /// assumed to have availability.
+    pub fn has_availability(&self) -> bool {
+        self.available_spots != Some(0)
+    }
+
+    /// Compares the contents of two events, i.e. all fields except `id` and when the events were
+    /// seen.
+    ///
+    /// This is in contrast to the [`PartialEq`] implementation, which only compares ids in order
+    /// to identify the same event across fetches.
+    pub fn content_eq(&self, other: &Self) -> bool {
+        self.title == other.title
+            && self.date_time == other.date_time
+            && self.class_info == other.class_info
+            && self.class_number == other.class_number
+            && self.price == other.price
+            && self.location == other.location
+            && self.instructor == other.instructor
+            && self.available_spots == other.available_spots
+    }
+}
+
+#[cfg(test)]
+impl Default for Event {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl PartialEq for Event {
@@ -65,20 +284,403 @@ impl Hash for Event {
 
 impl PartialOrd for Event {
     fn partial_cmp(&self, other: &Event) -> Option<Ordering> {
-        if self.id == other.id {
-            Some(Ordering::Equal)
-        } else {
-            self.date_time.partial_cmp(&other.date_time)
-        }
+        Some(self.cmp(other))
     }
 }
 
+/// Orders events by `date_time`, with ties broken by `id` so the order is always the same. Events
+/// with the same `id` are equal, consistent with the [`PartialEq`] implementation.
 impl Ord for Event {
     fn cmp(&self, other: &Self) -> Ordering {
         if self.id == other.id {
             Ordering::Equal
         } else {
-            self.date_time.cmp(&other.date_time)
+            self.date_time
+                .cmp(&other.date_time)
+                .then_with(|| self.id.cmp(&other.id))
         }
     }
 }
+
+/// Builds an [`Event`], failing if any of the required fields `id`, `title` and `date_time` are
+/// not set.
+#[derive(Debug, Default)]
+pub struct EventBuilder {
+    id: Option<String>,
+    title: Option<String>,
+    date_time: Option<DateTime<FixedOffset>>,
+    class_info: Vec<String>,
+    class_info_map: BTreeMap<String, String>,
+    class_info_rest: Vec<String>,
+    class_number: Option<String>,
+    price: Option<u32>,
+    location: Option<String>,
+    instructor: Option<String>,
+    available_spots: Option<u32>,
+    url: Option<String>,
+}
+
+impl EventBuilder {
+    pub fn id(&mut self, id: impl Into<String>) -> &mut Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    pub fn title(&mut self, title: impl Into<String>) -> &mut Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    pub fn date_time(&mut self, date_time: DateTime<FixedOffset>) -> &mut Self {
+        self.date_time = Some(date_time);
+        self
+    }
+
+    pub fn class_info(&mut self, class_info: Vec<String>) -> &mut Self {
+        self.class_info = class_info;
+        self
+    }
+
+    pub fn class_info_map(&mut self, class_info_map: BTreeMap<String, String>) -> &mut Self {
+        self.class_info_map = class_info_map;
+        self
+    }
+
+    pub fn class_info_rest(&mut self, class_info_rest: Vec<String>) -> &mut Self {
+        self.class_info_rest = class_info_rest;
+        self
+    }
+
+    pub fn class_number(&mut self, class_number: Option<String>) -> &mut Self {
+        self.class_number = class_number;
+        self
+    }
+
+    pub fn price(&mut self, price: Option<u32>) -> &mut Self {
+        self.price = price;
+        self
+    }
+
+    pub fn location(&mut self, location: Option<String>) -> &mut Self {
+        self.location = location;
+        self
+    }
+
+    pub fn instructor(&mut self, instructor: Option<String>) -> &mut Self {
+        self.instructor = instructor;
+        self
+    }
+
+    pub fn available_spots(&mut self, available_spots: Option<u32>) -> &mut Self {
+        self.available_spots = available_spots;
+        self
+    }
+
+    pub fn url(&mut self, url: Option<String>) -> &mut Self {
+        self.url = url;
+        self
+    }
+
+    /// Builds the event, computing its start in UTC from `date_time`. The event is seen for the
+    /// first time now.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`MissingFieldError`] naming the first required field that was not set.
+    pub fn build(&self) -> Result<Event, MissingFieldError> {
+        let id = self.id.clone().ok_or(MissingFieldError("id"))?;
+        let title = self.title.clone().ok_or(MissingFieldError("title"))?;
+        let date_time = self.date_time.ok_or(MissingFieldError("date_time"))?;
+        let now = Utc::now();
+        Ok(Event {
+            id,
+            title,
+            date_time,
+            start_utc: date_time.with_timezone(&Utc),
+            class_info: self.class_info.clone(),
+            class_info_map: self.class_info_map.clone(),
+            class_info_rest: self.class_info_rest.clone(),
+            class_number: self.class_number.clone(),
+            price: self.price,
+            location: self.location.clone(),
+            instructor: self.instructor.clone(),
+            available_spots: self.available_spots,
+            url: self.url.clone(),
+            first_seen: now,
+            last_seen: now,
+        })
+    }
+}
+
+/// An error representing a required [`Event`] field that was not set on an [`EventBuilder`].
+#[derive(Debug, PartialEq)]
+pub struct MissingFieldError(&'static str);
+
+impl Display for MissingFieldError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Missing event field `{}`", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::test_util::events;
+
+    #[test]
+    fn serialize_events_replaces_file() {
+        let directory = TempDir::new().unwrap();
+        let path = directory.path().join("events.json");
+
+        serialize_events(&events(&["event1"]), &path).unwrap();
+        serialize_events(&events(&["event2", "event3"]), &path).unwrap();
+
+        assert_eq!(
+            deserialize_events(&path).unwrap(),
+            events(&["event2", "event3"])
+        );
+        assert!(!temp_path(&path).exists());
+    }
+
+    #[test]
+    fn serialize_events_is_deterministic() {
+        let directory = TempDir::new().unwrap();
+        let (path, other_path) = (
+            directory.path().join("events.json"),
+            directory.path().join("other.json"),
+        );
+        let ids = ["event3", "event1", "event2", "event5", "event4"];
+        let mut reversed = ids;
+        reversed.reverse();
+
+        serialize_events(&events(&ids), &path).unwrap();
+        serialize_events(&events(&reversed), &other_path).unwrap();
+
+        let json = fs::read_to_string(&path).unwrap();
+        assert_eq!(json, fs::read_to_string(&other_path).unwrap());
+        assert!(
+            json.starts_with("[\n  {\n    \"id\": \"event1\""),
+            "{}",
+            json
+        );
+        let positions: Vec<_> = ["event1", "event2", "event3", "event4", "event5"]
+            .iter()
+            .map(|id| json.find(id).unwrap())
+            .collect();
+        assert!(positions.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn serialize_events_streams_many_events() {
+        let directory = TempDir::new().unwrap();
+        let path = directory.path().join("events.json");
+        let ids: Vec<_> = (0..5000).map(|i| format!("event{:04}", i)).collect();
+        let ids: Vec<_> = ids.iter().map(String::as_str).collect();
+        let many = events(&ids);
+
+        serialize_events(&many, &path).unwrap();
+
+        let json = fs::read_to_string(&path).unwrap();
+        let mut sorted: Vec<_> = many.iter().collect();
+        sorted.sort_by(|a, b| a.id.cmp(&b.id));
+        assert_eq!(json, serde_json::to_string_pretty(&sorted).unwrap());
+        assert_eq!(deserialize_events(&path).unwrap(), many);
+    }
+
+    #[test]
+    fn deserialize_events_accepts_compact_json() {
+        let directory = TempDir::new().unwrap();
+        let path = directory.path().join("events.json");
+        let compact = serde_json::to_string(&events(&["event1", "event2"])).unwrap();
+        fs::write(&path, compact).unwrap();
+
+        assert_eq!(
+            deserialize_events(&path).unwrap(),
+            events(&["event1", "event2"])
+        );
+    }
+
+    #[test]
+    fn interrupted_write_leaves_file_untouched() {
+        let directory = TempDir::new().unwrap();
+        let path = directory.path().join("events.json");
+        serialize_events(&events(&["event1"]), &path).unwrap();
+
+        // Simulate a write that is interrupted before the temporary file is renamed
+        fs::write(temp_path(&path), "[{\"id\": \"eve").unwrap();
+
+        assert_eq!(deserialize_events(&path).unwrap(), events(&["event1"]));
+
+        // The next write is not affected by the leftover temporary file
+        serialize_events(&events(&["event2"]), &path).unwrap();
+        assert_eq!(deserialize_events(&path).unwrap(), events(&["event2"]));
+    }
+
+    #[test]
+    fn back_up_rotates_backups() {
+        let directory = TempDir::new().unwrap();
+        let path = directory.path().join("events.json");
+
+        for ids in &[["event1"], ["event2"], ["event3"], ["event4"]] {
+            back_up(&path, 2).unwrap();
+            serialize_events(&events(ids), &path).unwrap();
+        }
+
+        assert_eq!(deserialize_events(&path).unwrap(), events(&["event4"]));
+        assert_eq!(
+            deserialize_events(&backup_path(&path, 1)).unwrap(),
+            events(&["event3"])
+        );
+        assert_eq!(
+            deserialize_events(&backup_path(&path, 2)).unwrap(),
+            events(&["event2"])
+        );
+        assert!(!backup_path(&path, 3).exists());
+    }
+
+    #[test]
+    fn back_up_does_nothing_without_count_or_file() {
+        let directory = TempDir::new().unwrap();
+        let path = directory.path().join("events.json");
+
+        back_up(&path, 1).unwrap();
+        assert!(!backup_path(&path, 1).exists());
+
+        serialize_events(&events(&["event1"]), &path).unwrap();
+        back_up(&path, 0).unwrap();
+        assert!(!backup_path(&path, 1).exists());
+    }
+
+    #[test]
+    fn backup_path_is_next_to_path() {
+        assert_eq!(
+            backup_path(Path::new("events.json"), 1),
+            Path::new("events.json.bak")
+        );
+        assert_eq!(
+            backup_path(Path::new("events.json"), 3),
+            Path::new("events.json.bak.3")
+        );
+    }
+
+    #[test]
+    fn temp_path_is_next_to_path() {
+        assert_eq!(
+            temp_path(Path::new("data/events.json")),
+            Path::new("data/events.json.tmp")
+        );
+    }
+
+    #[test]
+    fn event_deserializes_without_optional_fields() {
+        let json = r#"{
+            "id": "event1",
+            "title": "Tennisskole",
+            "date_time": "2021-05-04T18:00:00+02:00",
+            "class_info": ["Hold 1"]
+        }"#;
+
+        let event: Event = serde_json::from_str(json).unwrap();
+
+        assert_eq!(event.id, "event1");
+        assert_eq!(event.start_utc.to_rfc3339(), "2021-05-04T16:00:00+00:00");
+        assert_eq!(event.class_info_rest, vec!["Hold 1"]);
+        assert_eq!(event.price, None);
+        assert_eq!(event.location, None);
+        assert_eq!(event.instructor, None);
+        assert_eq!(event.available_spots, None);
+        assert_eq!(event.first_seen, event.last_seen);
+    }
+
+    #[test]
+    fn content_eq_ignores_when_events_were_seen() {
+        let event = Event::new();
+        let mut seen_later = Event::new();
+        seen_later.first_seen += chrono::Duration::hours(1);
+        seen_later.last_seen += chrono::Duration::days(1);
+
+        assert!(event.content_eq(&seen_later));
+    }
+
+    #[test]
+    fn local_date_time_uses_summer_time() {
+        let date_time = local_date_time(NaiveDate::from_ymd(2021, 6, 30).and_hms(18, 0, 0));
+
+        assert_eq!(date_time.unwrap().to_rfc3339(), "2021-06-30T18:00:00+02:00");
+    }
+
+    #[test]
+    fn local_date_time_uses_standard_time() {
+        let date_time = local_date_time(NaiveDate::from_ymd(2021, 12, 1).and_hms(18, 0, 0));
+
+        assert_eq!(date_time.unwrap().to_rfc3339(), "2021-12-01T18:00:00+01:00");
+    }
+
+    #[test]
+    fn local_date_time_rejects_skipped_times() {
+        assert!(local_date_time(NaiveDate::from_ymd(2021, 3, 28).and_hms(2, 30, 0)).is_none());
+    }
+
+    #[test]
+    fn has_availability() {
+        let mut event = Event::new();
+        assert!(event.has_availability());
+
+        event.available_spots = Some(3);
+        assert!(event.has_availability());
+
+        event.available_spots = Some(0);
+        assert!(!event.has_availability());
+    }
+
+    #[test]
+    fn ord_breaks_date_time_ties_on_id() {
+        let event = |id: &str, hour: u32| {
+            let mut event = Event::new();
+            event.id = id.to_string();
+            event.date_time =
+                local_date_time(NaiveDate::from_ymd(2021, 6, 30).and_hms(hour, 0, 0)).unwrap();
+            event
+        };
+        let mut events = [event("b", 18), event("c", 17), event("a", 18)];
+
+        events.sort();
+
+        let ids: Vec<_> = events.iter().map(|event| event.id.as_str()).collect();
+        assert_eq!(ids, vec!["c", "a", "b"]);
+        assert_eq!(event("a", 17).cmp(&event("a", 18)), Ordering::Equal);
+    }
+
+    #[test]
+    fn builder_builds_event() {
+        let date_time = FixedOffset::east(2 * 3600)
+            .ymd(2021, 6, 30)
+            .and_hms(18, 0, 0);
+        let event = Event::builder()
+            .id("event1")
+            .title("Tennisskole")
+            .date_time(date_time)
+            .class_info(vec![String::from("Træner: Jens")])
+            .instructor(Some(String::from("Jens")))
+            .build()
+            .unwrap();
+
+        assert_eq!(event.id, "event1");
+        assert_eq!(event.title, "Tennisskole");
+        assert_eq!(event.date_time, date_time);
+        assert_eq!(event.class_info, vec!["Træner: Jens"]);
+        assert_eq!(event.instructor.as_deref(), Some("Jens"));
+        assert_eq!(event.price, None);
+    }
+
+    #[test]
+    fn builder_fails_on_missing_required_field() {
+        let mut builder = Event::builder();
+        builder.id("event1").title("Tennisskole");
+
+        assert_eq!(builder.build().unwrap_err(), MissingFieldError("date_time"));
+    }
+}
@@ -1,123 +1,292 @@
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     fmt::{self, Formatter},
+    ops::RangeInclusive,
 };
 
-use chrono::{FixedOffset, TimeZone};
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDateTime};
+use lazy_static::lazy_static;
+use regex::Regex;
 use scraper::{ElementRef, Html, Selector};
+use unicode_normalization::UnicodeNormalization;
+
+use super::{local_date_time, local_now, Event, EventBuilder, MissingFieldError};
+
+pub const DEFAULT_EVENT_SELECTOR: &str = "tr[class=\"infinite-item\"]";
+pub const DEFAULT_MAIN_INFO_SELECTOR: &str = "td[class=\"liste_wide min992\"]";
+pub const DEFAULT_CLASS_INFO_SELECTOR: &str = "td[class=\"liste_wide min992 holdinfo\"]";
+pub const DEFAULT_LOCATION_SELECTOR: &str = "td[class=\"liste_wide min992 lokale\"]";
+/// Selects the link to the event, e.g. its booking page, within an event row.
+const LINK_SELECTOR: &str = "a[href]";
+const INSTRUCTOR_PREFIXES: &[&str] = &["træner:", "instruktør:"];
+/// How many years before or after the current year events can plausibly take place.
+const PLAUSIBLE_YEARS_FROM_NOW: i32 = 2;
+lazy_static! {
+    /// Matches a time of day such as "18:00".
+    static ref TIME_REGEX: Regex = Regex::new(r"\b\d{1,2}:\d{2}\b").unwrap();
+}
+
+const DANISH_MONTHS: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "maj", "jun", "jul", "aug", "sep", "okt", "nov", "dec",
+];
+const ENGLISH_MONTHS: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const FULLY_BOOKED_PATTERNS: &[&str] = &["fuldt booket", "venteliste"];
+
+/// Parses the events on a page of the event list given as `html`, e.g. a saved copy of the page.
+/// Rows that fail to parse are skipped, and their errors are returned alongside the events that
+/// did parse.
+///
+/// Since saved copies may be of any age, events are not rejected for taking place in an
+/// implausible year, unlike with [`EventParser`].
+pub fn parse_document(html: &str) -> (HashSet<Event>, Vec<ParseError>) {
+    EventParser::default()
+        .plausible_years(i32::MIN..=i32::MAX)
+        .parse_all(Html::parse_document(html))
+}
+
+/// The CSS selectors used to find events and their details on a page of events.
+#[derive(Debug, Clone)]
+pub struct Selectors {
+    /// Selects the row of each event.
+    pub event: String,
+    /// Selects the cell with the title, date, and time within an event row.
+    pub main_info: String,
+    /// Selects the cell with class info such as the price and instructor within an event row.
+    pub class_info: String,
+    /// Selects the cell with the location within an event row.
+    pub location: String,
+}
+
+impl Default for Selectors {
+    fn default() -> Self {
+        Selectors {
+            event: String::from(DEFAULT_EVENT_SELECTOR),
+            main_info: String::from(DEFAULT_MAIN_INFO_SELECTOR),
+            class_info: String::from(DEFAULT_CLASS_INFO_SELECTOR),
+            location: String::from(DEFAULT_LOCATION_SELECTOR),
+        }
+    }
+}
+
+/// Splits lines of class info into key/value pairs such as "Varighed: 60 min", split on the first
+/// colon, and the remaining lines. Lines like "18:00 - 19:00", where the key would end in a digit,
+/// are times rather than pairs. The first occurrence of a key is kept.
+pub(crate) fn split_class_info(class_info: &[String]) -> (BTreeMap<String, String>, Vec<String>) {
+    let mut map = BTreeMap::new();
+    let mut rest = Vec::new();
+    for line in class_info {
+        let pair = line
+            .split_once(':')
+            .map(|(key, value)| (key.trim(), value.trim()))
+            .filter(|(key, value)| {
+                !key.is_empty() && !value.is_empty() && !key.ends_with(|c: char| c.is_ascii_digit())
+            });
+        match pair {
+            Some((key, value)) => {
+                map.entry(key.to_string())
+                    .or_insert_with(|| value.to_string());
+            }
+            None => rest.push(line.clone()),
+        }
+    }
+    (map, rest)
+}
 
-use super::Event;
+/// Canonicalizes text from the site, i.e. normalizes unicode to NFC and collapses all whitespace
+/// to single spaces, so markup that only changes how the same text is written, e.g. "Tennis  Skole"
+/// and "Tennis Skole", does not make events look changed.
+pub(crate) fn canonicalize(text: &str) -> String {
+    text.nfc()
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
 
-const EVENT_SELECTOR: &str = "tr[class=\"infinite-item\"]";
-const MAIN_INFO_SELECTOR: &str = "td[class=\"liste_wide min992\"]";
-const CLASS_INFO_SELECTOR: &str = "td[class=\"liste_wide min992 holdinfo\"]";
+/// Parses a single CSS selector.
+///
+/// # Errors
+///
+/// Returns an error if `selector` is not a valid CSS selector.
+pub(crate) fn parse_selector(selector: &str) -> Result<Selector, SelectorError> {
+    Selector::parse(selector).map_err(|error| SelectorError {
+        selector: selector.to_string(),
+        message: format!("{:?}", error.kind),
+    })
+}
 
+#[derive(Clone)]
 pub struct EventParser {
     event_selector: Selector,
     main_info_selector: Selector,
     class_info_selector: Selector,
+    location_selector: Selector,
+    link_selector: Selector,
     month_lookup: HashMap<String, u32>,
+    /// The years that events can take place in. Events in other years are most likely misparsed.
+    plausible_years: RangeInclusive<i32>,
 }
 
 impl EventParser {
-    pub fn new() -> EventParser {
-        EventParser {
-            event_selector: Selector::parse(EVENT_SELECTOR).unwrap(),
-            main_info_selector: Selector::parse(MAIN_INFO_SELECTOR).unwrap(),
-            class_info_selector: Selector::parse(CLASS_INFO_SELECTOR).unwrap(),
-            month_lookup: [
-                "jan", "feb", "mar", "apr", "maj", "jun", "jul", "aug", "sep", "okt", "nov", "dec",
-            ]
-            .iter()
-            .cloned()
-            .map(|s| s.to_string())
-            .zip((1..13).into_iter())
-            .collect(),
-        }
+    /// Creates a parser that finds events and their details using `selectors`, and rejects events
+    /// that do not take place within two years of the current year.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any of the selectors is not a valid CSS selector.
+    pub fn new(selectors: &Selectors) -> Result<EventParser, SelectorError> {
+        Ok(EventParser {
+            event_selector: parse_selector(&selectors.event)?,
+            main_info_selector: parse_selector(&selectors.main_info)?,
+            class_info_selector: parse_selector(&selectors.class_info)?,
+            location_selector: parse_selector(&selectors.location)?,
+            link_selector: parse_selector(LINK_SELECTOR)?,
+            // The site is usually in Danish, but switches to English depending on the session
+            month_lookup: DANISH_MONTHS
+                .iter()
+                .zip(1..13)
+                .chain(ENGLISH_MONTHS.iter().zip(1..13))
+                .map(|(s, month)| (s.to_string(), month))
+                .collect(),
+            plausible_years: {
+                let year = local_now().year();
+                year - PLAUSIBLE_YEARS_FROM_NOW..=year + PLAUSIBLE_YEARS_FROM_NOW
+            },
+        })
     }
 
-    pub fn parse_all(&self, document: Html) -> Result<HashSet<Event>, ParseError> {
-        document
-            .select(&self.event_selector)
-            .map(|row| self.parse_one(row))
-            .collect()
+    /// Sets the years that events can take place in, instead of within two years of the current
+    /// year. Events in other years are rejected with a [`ParseError`].
+    pub fn plausible_years(mut self, years: RangeInclusive<i32>) -> Self {
+        self.plausible_years = years;
+        self
+    }
+
+    /// Parses all event rows in `document`. Rows that fail to parse are skipped, and their errors
+    /// are returned alongside the events that did parse.
+    pub fn parse_all(&self, document: Html) -> (HashSet<Event>, Vec<ParseError>) {
+        let mut events = HashSet::new();
+        let mut errors = Vec::new();
+        for row in document.select(&self.event_selector) {
+            match self.parse_one(row) {
+                Ok(event) => {
+                    events.insert(event);
+                }
+                Err(error) => errors.push(error),
+            }
+        }
+        (events, errors)
     }
 
     pub fn parse_one(&self, row: ElementRef) -> Result<Event, ParseError> {
-        let mut event: Event = Event::new();
+        let mut builder = Event::builder();
 
-        event.id = row
+        builder.id(row
             .value()
             .attr("id")
-            .ok_or(ParseError::from("No 'id' attribute in event HTML."))?
-            .to_string();
-
-        self.parse_main_info(row, &mut event)?;
-        self.parse_class_info(row, &mut event);
+            .ok_or(ParseError::from("No 'id' attribute in event HTML."))?);
+
+        self.parse_main_info(row, &mut builder)?;
+        self.parse_class_info(row, &mut builder);
+        self.parse_location(row, &mut builder);
+        self.parse_link(row, &mut builder);
+
+        let event = builder.build()?;
+        // A shift in the markup can make the date parse as a wildly wrong one
+        let year = event.date_time.year();
+        if !self.plausible_years.contains(&year) {
+            return Err(ParseError::from(format!(
+                "Implausible year {} of event {}, expected {} to {}",
+                year,
+                event.id,
+                self.plausible_years.start(),
+                self.plausible_years.end()
+            )));
+        }
 
         Ok(event)
     }
 
-    fn parse_main_info(&self, row: ElementRef, event: &mut Event) -> Result<(), ParseError> {
+    fn parse_main_info(
+        &self,
+        row: ElementRef,
+        builder: &mut EventBuilder,
+    ) -> Result<(), ParseError> {
         for line in row.select(&self.main_info_selector) {
             let text = EventParser::parse_text(line);
 
-            let (title, date, time) = match text.len() {
-                3 => (text[0], text[1], text[2]),
-                5 => (text[0], text[3], text[4]),
-                _ => {
-                    return Err(format!(
-                        "Expected event main info to have 3 or 5 lines, found {:?}",
-                        text
-                    ))?
-                }
-            };
-
-            event.title = String::from(title);
-
-            // Parse day, month, and year
-            let mut date_iter = date.split_whitespace();
-
-            date_iter.next(); // skip name of day
-
-            let day = date_iter.next().ok_or(ParseError::from(format!(
-                "Failed to parse day: No more values in date iterator"
-            )))?;
-            let day: u32 = self.parse_day(&day[..(day.len() - 1)])?;
-
-            let month = date_iter.next().ok_or(ParseError::from(format!(
-                "Failed to parse month: No more values in date iterator"
-            )))?;
-            let month: u32 = self.parse_month(month)?;
-
-            let year = date_iter.next().ok_or(ParseError::from(format!(
-                "Failed to parse year: No more values in date iterator"
-            )))?;
-            let year: i32 = self.parse_year(year)?;
-
-            // Parse time
-            let hours = self.parse_hours(&time[..2])?;
-            let minutes = self.parse_minutes(&time[3..5])?;
-
-            event.date_time = FixedOffset::east(2 * 3600)
-                .ymd(year, month, day)
-                .and_hms(hours, minutes, 0);
+            // The title is always the first line, while the date and time may be surrounded by any
+            // number of descriptive lines, so they are found by their format
+            let (title, details) = text
+                .split_first()
+                .ok_or_else(|| ParseError::from("Event main info is empty"))?;
+            let date = details
+                .iter()
+                .find(|line| self.is_date(line))
+                .ok_or_else(|| {
+                    ParseError::from(format!("No date in event main info {:?}", text))
+                })?;
+            let time = details
+                .iter()
+                .find_map(|line| TIME_REGEX.find(line))
+                .ok_or_else(|| ParseError::from(format!("No time in event main info {:?}", text)))?
+                .as_str();
+
+            builder
+                .title(canonicalize(title))
+                .date_time(self.parse_date_time(date, time)?);
         }
 
         Ok(())
     }
 
-    fn parse_day(&self, s: &str) -> Result<u32, ParseError> {
-        let day = s
-            .parse()
-            .map_err(|_| ParseError::from(format!("Failed to parse day from: '{}'", s)))?;
-        Ok(day)
+    /// Whether `line` looks like a date, i.e. contains a number and a month.
+    fn is_date(&self, line: &str) -> bool {
+        line.chars().any(|c| c.is_ascii_digit())
+            && line.split_whitespace().any(|word| {
+                self.month_lookup
+                    .contains_key(&word.trim_end_matches('.').to_lowercase())
+            })
+    }
+
+    /// Parses a date such as "tir 5. maj 2021" and a time span such as "18:00 - 19:00" into the
+    /// start time of an event.
+    ///
+    /// The date is normalized to "5 5 2021 18:00" and then parsed with an explicit format, so any
+    /// deviation from the expected format results in an error.
+    fn parse_date_time(&self, date: &str, time: &str) -> Result<DateTime<FixedOffset>, ParseError> {
+        let mut date_iter = date.split_whitespace().skip(1); // skip name of day
+        let (day, month, year) = match (date_iter.next(), date_iter.next(), date_iter.next()) {
+            (Some(day), Some(month), Some(year)) => (day.trim_end_matches('.'), month, year),
+            _ => {
+                return Err(ParseError::from(format!(
+                    "Unexpected date format: '{}'",
+                    date
+                )))
+            }
+        };
+        let month = self.parse_month(month)?;
+        let start = time.split('-').next().unwrap_or_default().trim();
+
+        let normalized = format!("{} {} {} {}", day, month, year, start);
+        let naive =
+            NaiveDateTime::parse_from_str(&normalized, "%d %m %Y %H:%M").map_err(|error| {
+                ParseError::from(format!(
+                    "Failed to parse date and time from: '{}' and '{}': {}",
+                    date, time, error
+                ))
+            })?;
+
+        local_date_time(naive)
+            .ok_or_else(|| ParseError::from(format!("Nonexistent local time: '{}'", naive)))
     }
 
     fn parse_month(&self, s: &str) -> Result<u32, ParseError> {
         let month = self
             .month_lookup
-            .get(s)
+            .get(&s.trim_end_matches('.').to_lowercase())
             .ok_or(ParseError::from(format!(
                 "No month matching pattern: '{}'",
                 s
@@ -126,32 +295,114 @@ impl EventParser {
         Ok(month)
     }
 
-    fn parse_year(&self, s: &str) -> Result<i32, ParseError> {
-        let year = s
-            .parse()
-            .map_err(|_| ParseError::from(format!("Failed to parse year from: '{}'", s)))?;
-        Ok(year)
+    fn parse_class_info(&self, row: ElementRef, builder: &mut EventBuilder) {
+        let mut class_info: Vec<String> = Vec::new();
+        for line in row.select(&self.class_info_selector) {
+            let text = EventParser::parse_text(line);
+            // Some rows repeat lines, so only keep the first occurrence of each
+            class_info.clear();
+            for t in text.into_iter().map(canonicalize) {
+                if !class_info.contains(&t) {
+                    class_info.push(t);
+                }
+            }
+        }
+        builder
+            .class_number(
+                class_info
+                    .iter()
+                    .find_map(|line| EventParser::parse_class_number(line)),
+            )
+            .price(
+                class_info
+                    .iter()
+                    .find_map(|line| EventParser::parse_price(line)),
+            )
+            .instructor(
+                class_info
+                    .iter()
+                    .find_map(|line| EventParser::parse_instructor(line)),
+            )
+            .available_spots(
+                class_info
+                    .iter()
+                    .find_map(|line| EventParser::parse_available_spots(line)),
+            );
+        let (class_info_map, class_info_rest) = split_class_info(&class_info);
+        builder
+            .class_info_map(class_info_map)
+            .class_info_rest(class_info_rest)
+            .class_info(class_info);
+    }
+
+    fn parse_location(&self, row: ElementRef, builder: &mut EventBuilder) {
+        builder.location(
+            row.select(&self.location_selector)
+                .map(|cell| EventParser::parse_text(cell).join(" "))
+                .find(|location| !location.is_empty()),
+        );
     }
 
-    fn parse_hours(&self, s: &str) -> Result<u32, ParseError> {
-        let hours = s
-            .parse()
-            .map_err(|_| ParseError::from(format!("Failed to parse hours from: '{}'", s)))?;
-        Ok(hours)
+    /// Parses the first link in the event row. Relative links are kept as is, since the URL of the
+    /// page is not known here.
+    fn parse_link(&self, row: ElementRef, builder: &mut EventBuilder) {
+        builder.url(
+            row.select(&self.link_selector)
+                .find_map(|link| link.value().attr("href"))
+                .map(str::trim)
+                .filter(|href| !href.is_empty())
+                .map(String::from),
+        );
     }
 
-    fn parse_minutes(&self, s: &str) -> Result<u32, ParseError> {
-        let minutes = s
-            .parse()
-            .map_err(|_| ParseError::from(format!("Failed to parse minutes from: '{}'", s)))?;
-        Ok(minutes)
+    /// Parses the number of a class from a line such as "Hold 4321" or "Holdnr. 4321".
+    fn parse_class_number(line: &str) -> Option<String> {
+        lazy_static! {
+            static ref RE: Regex = Regex::new(r"(?i)\bhold(?:nummer|nr\.?)?:?\s*(\d+)\b").unwrap();
+        }
+
+        Some(RE.captures(line)?.get(1)?.as_str().to_string())
     }
 
-    fn parse_class_info(&self, row: ElementRef, event: &mut Event) {
-        for line in row.select(&self.class_info_selector) {
-            let text = EventParser::parse_text(line);
-            event.class_info = text.iter().map(|t| t.to_string()).collect();
+    /// Parses the name of an instructor from a line such as "Træner: Jens".
+    fn parse_instructor(line: &str) -> Option<String> {
+        let lowercase = line.to_lowercase();
+        INSTRUCTOR_PREFIXES
+            .iter()
+            .find(|prefix| lowercase.starts_with(*prefix))
+            .and_then(|prefix| line.get(prefix.len()..))
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(String::from)
+    }
+
+    /// Parses the number of open spots from a line such as "3 ledige pladser", or `Some(0)` if the
+    /// line says the event is fully booked.
+    fn parse_available_spots(line: &str) -> Option<u32> {
+        lazy_static! {
+            static ref RE: Regex = Regex::new(r"(?i)(\d+)\s+ledige?\s+plads").unwrap();
+        }
+
+        let lowercase = line.to_lowercase();
+        if FULLY_BOOKED_PATTERNS
+            .iter()
+            .any(|pattern| lowercase.contains(pattern))
+        {
+            return Some(0);
+        }
+
+        RE.captures(line)?.get(1)?.as_str().parse().ok()
+    }
+
+    /// Parses a price in kroner from a line such as "Pris: 120 kr." or "Pris: 1.200,-".
+    fn parse_price(line: &str) -> Option<u32> {
+        lazy_static! {
+            static ref RE: Regex =
+                Regex::new(r"(?i)(\d{1,3}(?:\.\d{3})+|\d+)\s*(?:kr\b|,-)").unwrap();
         }
+
+        let amount = RE.captures(line)?.get(1)?.as_str().replace('.', "");
+        amount.parse().ok()
     }
 
     fn parse_text(line: ElementRef) -> Vec<&str> {
@@ -162,6 +413,30 @@ impl EventParser {
     }
 }
 
+impl Default for EventParser {
+    /// Creates a parser using the default selectors, which match the current markup of the site.
+    fn default() -> Self {
+        EventParser::new(&Selectors::default()).unwrap()
+    }
+}
+
+/// An error representing a CSS selector that cannot be parsed.
+#[derive(Debug)]
+pub struct SelectorError {
+    selector: String,
+    message: String,
+}
+
+impl fmt::Display for SelectorError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Invalid CSS selector '{}': {}",
+            self.selector, self.message
+        )
+    }
+}
+
 #[derive(Debug)]
 pub struct ParseError {
     message: String,
@@ -184,3 +459,386 @@ impl From<&str> for ParseError {
         ParseError::from(message.to_string())
     }
 }
+
+impl From<MissingFieldError> for ParseError {
+    fn from(error: MissingFieldError) -> Self {
+        ParseError::from(error.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Datelike, TimeZone, Timelike, Utc};
+
+    use super::*;
+    use crate::test_util::parser;
+
+    fn parse(class_info: &str) -> Event {
+        parse_row(&format!(
+            r#"<td class="liste_wide min992">Tennisskole<br>tir 5. maj 2021<br>18:00 - 19:00</td>
+            <td class="liste_wide min992 holdinfo">{}</td>"#,
+            class_info
+        ))
+    }
+
+    fn parse_row(cells: &str) -> Event {
+        let html = format!(
+            r#"<table><tbody><tr class="infinite-item" id="event1">{}</tr></tbody></table>"#,
+            cells
+        );
+        let document = Html::parse_document(&html);
+        let (mut events, errors) = parser().parse_all(document);
+        assert!(errors.is_empty(), "{:?}", errors);
+        let event = events.drain().next().unwrap();
+        event
+    }
+
+    fn main_info(date: &str) -> Event {
+        parse_row(&format!(
+            r#"<td class="liste_wide min992">Tennisskole<br>{}<br>18:00 - 19:00</td>"#,
+            date
+        ))
+    }
+
+    #[test]
+    fn parse_one_parses_danish_and_english_dates() {
+        let expected = FixedOffset::east(2 * 3600)
+            .ymd(2021, 5, 5)
+            .and_hms(18, 0, 0);
+
+        assert_eq!(main_info("tir 5. maj 2021").date_time, expected);
+        assert_eq!(main_info("Tue 5 May 2021").date_time, expected);
+    }
+
+    #[test]
+    fn parse_one_converts_start_to_utc() {
+        let summer = main_info("tir 5. maj 2021");
+        let winter = main_info("tir 5. jan 2021");
+
+        assert_eq!(summer.start_utc, Utc.ymd(2021, 5, 5).and_hms(16, 0, 0));
+        assert_eq!(winter.start_utc, Utc.ymd(2021, 1, 5).and_hms(17, 0, 0));
+        assert_eq!(summer.start_utc, summer.date_time);
+    }
+
+    #[test]
+    fn parse_one_uses_summer_time_offset() {
+        assert_eq!(
+            main_info("tir 5. maj 2021").date_time.offset(),
+            &FixedOffset::east(2 * 3600)
+        );
+    }
+
+    #[test]
+    fn parse_one_uses_winter_time_offset() {
+        assert_eq!(
+            main_info("tir 5. jan 2021").date_time.offset(),
+            &FixedOffset::east(3600)
+        );
+    }
+
+    #[test]
+    fn parse_one_parses_single_digit_days() {
+        let event = main_info("tir 5. maj 2021");
+
+        assert_eq!(event.date_time.day(), 5);
+        assert_eq!(event.date_time.month(), 5);
+        assert_eq!(event.date_time.hour(), 18);
+    }
+
+    #[test]
+    fn parse_one_parses_double_digit_days() {
+        let event = main_info("ons 23. jun 2021");
+
+        assert_eq!(event.date_time.day(), 23);
+        assert_eq!(event.date_time.month(), 6);
+    }
+
+    #[test]
+    fn parse_one_rejects_malformed_dates() {
+        for cells in &[
+            r#"<td class="liste_wide min992">Tennisskole<br>tir 5. maj<br>18:00 - 19:00</td>"#,
+            r#"<td class="liste_wide min992">Tennisskole<br>tir 5. foo 2021<br>18:00</td>"#,
+            r#"<td class="liste_wide min992">Tennisskole<br>tir 31. feb 2021<br>18:00</td>"#,
+            r#"<td class="liste_wide min992">Tennisskole<br>tir 5. maj 2021<br>18</td>"#,
+            r#"<td class="liste_wide min992">Tennisskole<br>tir 5. maj 2021<br>1</td>"#,
+        ] {
+            let html = format!(
+                r#"<table><tbody><tr class="infinite-item" id="event1">{}</tr></tbody></table>"#,
+                cells
+            );
+            let document = Html::parse_document(&html);
+
+            let (events, errors) = parser().parse_all(document);
+
+            assert!(events.is_empty(), "{}", cells);
+            assert_eq!(errors.len(), 1, "{}", cells);
+        }
+    }
+
+    #[test]
+    fn parse_one_finds_date_and_time_among_extra_lines() {
+        for lines in &[
+            "Tennisskole<br>tir 5. maj 2021<br>18:00 - 19:00",
+            "Tennisskole<br>Husk ketsjer<br>tir 5. maj 2021<br>18:00 - 19:00",
+            "Tennisskole<br>Hold 1<br>Niveau 2<br>tir 5. maj 2021<br>18:00 - 19:00",
+            "Tennisskole<br>Hold 1<br>tir 5. maj 2021<br>Bane 3<br>18:00 - 19:00<br>Husk vand",
+        ] {
+            let event = parse_row(&format!(r#"<td class="liste_wide min992">{}</td>"#, lines));
+
+            assert_eq!(event.title, "Tennisskole");
+            assert_eq!(event.date_time.day(), 5);
+            assert_eq!(event.date_time.hour(), 18);
+        }
+    }
+
+    #[test]
+    fn parse_one_rejects_rows_without_main_info() {
+        let document = Html::parse_document(
+            r#"<table><tbody><tr class="infinite-item" id="event1">
+                <td class="liste_wide min992 holdinfo">Træner: Jens</td>
+            </tr></tbody></table>"#,
+        );
+
+        let (events, errors) = parser().parse_all(document);
+
+        assert!(events.is_empty());
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].to_string().contains("title"));
+    }
+
+    #[test]
+    fn parse_all_skips_malformed_rows() {
+        let document = Html::parse_document(
+            r#"<table><tbody>
+                <tr class="infinite-item" id="event1">
+                    <td class="liste_wide min992">Tennisskole<br>tir 5. maj 2021<br>18:00</td>
+                </tr>
+                <tr class="infinite-item" id="event2">
+                    <td class="liste_wide min992">Tennisskole</td>
+                </tr>
+            </tbody></table>"#,
+        );
+
+        let (events, errors) = parser().parse_all(document);
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events.iter().next().unwrap().id, "event1");
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn parse_one_parses_english_months() {
+        assert_eq!(main_info("Fri 1 Oct 2021").date_time.month(), 10);
+        assert_eq!(main_info("Wed 1 Dec 2021").date_time.month(), 12);
+    }
+
+    #[test]
+    fn parse_one_parses_price_in_kroner() {
+        assert_eq!(parse("Hold 1<br>Pris: 120 kr.").price, Some(120));
+        assert_eq!(parse("Hold 1<br>Pris: 1.200 kr.").price, Some(1200));
+    }
+
+    #[test]
+    fn parse_one_parses_price_with_dash() {
+        assert_eq!(parse("Hold 1<br>Pris: 120,-").price, Some(120));
+    }
+
+    #[test]
+    fn parse_one_leaves_price_empty_when_absent() {
+        assert_eq!(parse("Hold 1<br>12 deltagere").price, None);
+    }
+
+    #[test]
+    fn parse_one_parses_class_number() {
+        let event = parse("Hold 4321<br>Pris: 120 kr.");
+        assert_eq!(event.class_number.as_deref(), Some("4321"));
+        assert_eq!(event.class_info, vec!["Hold 4321", "Pris: 120 kr."]);
+
+        assert_eq!(parse("Holdnr. 12").class_number.as_deref(), Some("12"));
+    }
+
+    #[test]
+    fn parse_one_splits_class_info_into_key_value_pairs() {
+        let event = parse(
+            "Hold 1<br>Niveau: Øvet<br>Varighed: 60 min<br>Pris: 120 kr.<br>18:00 - 19:00<br>\
+             Niveau: Begynder",
+        );
+
+        let pairs: Vec<_> = event
+            .class_info_map
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect();
+        assert_eq!(
+            pairs,
+            vec![
+                ("Niveau", "Øvet"),
+                ("Pris", "120 kr."),
+                ("Varighed", "60 min")
+            ]
+        );
+        assert_eq!(event.class_info_rest, vec!["Hold 1", "18:00 - 19:00"]);
+        assert_eq!(event.class_info.len(), 6);
+    }
+
+    #[test]
+    fn parse_one_leaves_class_number_empty_when_absent() {
+        assert_eq!(parse("Pris: 120 kr.").class_number, None);
+        assert_eq!(parse("Holdsport").class_number, None);
+    }
+
+    #[test]
+    fn parse_one_parses_instructor() {
+        let event = parse("Hold 1<br>  Træner: Jens  <br>Pris: 120 kr.");
+
+        assert_eq!(event.instructor.as_deref(), Some("Jens"));
+        assert!(event.class_info.contains(&String::from("Træner: Jens")));
+    }
+
+    #[test]
+    fn parse_one_leaves_instructor_empty_when_absent() {
+        assert_eq!(parse("Hold 1<br>Pris: 120 kr.").instructor, None);
+        assert_eq!(parse("Hold 1<br>Træner:").instructor, None);
+    }
+
+    #[test]
+    fn parse_one_parses_available_spots() {
+        assert_eq!(parse("Hold 1<br>3 ledige pladser").available_spots, Some(3));
+        assert_eq!(parse("Hold 1<br>1 ledig plads").available_spots, Some(1));
+    }
+
+    #[test]
+    fn parse_one_parses_fully_booked_as_no_available_spots() {
+        assert_eq!(parse("Hold 1<br>Fuldt booket").available_spots, Some(0));
+        assert_eq!(
+            parse("Hold 1<br>Skriv dig på venteliste").available_spots,
+            Some(0)
+        );
+    }
+
+    #[test]
+    fn parse_one_leaves_available_spots_empty_when_absent() {
+        assert_eq!(parse("Hold 1<br>Pris: 120 kr.").available_spots, None);
+    }
+
+    #[test]
+    fn parse_one_parses_location() {
+        let event = parse_row(
+            r#"<td class="liste_wide min992">Tennisskole<br>tir 5. maj 2021<br>18:00 - 19:00</td>
+            <td class="liste_wide min992 lokale"> Bane 3 </td>"#,
+        );
+
+        assert_eq!(event.location.as_deref(), Some("Bane 3"));
+    }
+
+    #[test]
+    fn parse_one_leaves_location_empty_when_absent() {
+        assert_eq!(parse("Hold 1").location, None);
+    }
+
+    #[test]
+    fn parse_one_removes_duplicate_class_info_lines() {
+        let event = parse("Hold 1<br>Pris: 120 kr.<br>Hold 1<br>Niveau: Øvet<br>Pris: 120 kr.");
+
+        assert_eq!(
+            event.class_info,
+            vec!["Hold 1", "Pris: 120 kr.", "Niveau: Øvet"]
+        );
+    }
+
+    #[test]
+    fn parse_one_canonicalizes_title_and_class_info() {
+        let spaced = parse_row(
+            "<td class=\"liste_wide min992\">Tennis  Skole<br>tir 5. maj 2021<br>18:00 - 19:00</td>
+            <td class=\"liste_wide min992 holdinfo\">Niveau:\u{a0} Øvet<br>Niveau: Øvet</td>",
+        );
+        let single = parse_row(
+            r#"<td class="liste_wide min992">Tennis Skole<br>tir 5. maj 2021<br>18:00 - 19:00</td>
+            <td class="liste_wide min992 holdinfo">Niveau: Øvet</td>"#,
+        );
+
+        assert_eq!(spaced.title, "Tennis Skole");
+        assert_eq!(spaced.class_info, vec!["Niveau: Øvet"]);
+        assert!(spaced.content_eq(&single));
+    }
+
+    #[test]
+    fn canonicalize_collapses_whitespace_and_normalizes_unicode() {
+        assert_eq!(canonicalize("Tennis  Skole"), canonicalize("Tennis Skole"));
+        assert_eq!(canonicalize(" Tennis\t\nSkole "), "Tennis Skole");
+        // "å" written as "a" followed by a combining ring
+        assert_eq!(
+            canonicalize("Tr\u{e6}ning p\u{61}\u{30a}"),
+            "Træning p\u{e5}"
+        );
+    }
+
+    #[test]
+    fn parse_one_parses_link() {
+        let event = parse_row(
+            r#"<td class="liste_wide min992">Tennisskole<br>tir 5. maj 2021<br>18:00 - 19:00</td>
+            <td class="liste_wide min992 holdinfo">
+                <a href="proc_holdinfo.asp?hold=10412">Tilmeld</a>
+            </td>"#,
+        );
+
+        assert_eq!(event.url.as_deref(), Some("proc_holdinfo.asp?hold=10412"));
+        assert_eq!(parse("Hold 1").url, None);
+    }
+
+    #[test]
+    fn new_rejects_invalid_selectors() {
+        let selectors = Selectors {
+            class_info: String::from("td[class="),
+            ..Selectors::default()
+        };
+
+        let error = EventParser::new(&selectors).err().unwrap();
+
+        assert!(error
+            .to_string()
+            .starts_with("Invalid CSS selector 'td[class='"));
+    }
+
+    #[test]
+    fn parse_one_rejects_implausible_years() {
+        let html = r#"<table><tbody><tr class="infinite-item" id="event1">
+            <td class="liste_wide min992">Tennisskole<br>tir 5. maj 1921<br>18:00 - 19:00</td>
+        </tr></tbody></table>"#;
+        let parse = |parser: &EventParser| parser.parse_all(Html::parse_document(html));
+
+        let (events, errors) = parse(&parser());
+        assert!(events.is_empty());
+        assert!(
+            errors[0].to_string().contains("Implausible year 1921"),
+            "{}",
+            errors[0]
+        );
+
+        let (events, errors) = parse(&parser().plausible_years(1900..=2021));
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(events.len(), 1);
+    }
+
+    #[test]
+    fn parse_all_uses_given_selectors() {
+        let selectors = Selectors {
+            event: String::from("tr.event"),
+            main_info: String::from("td.main"),
+            ..Selectors::default()
+        };
+        let document = Html::parse_document(
+            r#"<table><tbody><tr class="event" id="event1">
+                <td class="main">Tennisskole<br>tir 5. maj 2021<br>18:00 - 19:00</td>
+            </tr></tbody></table>"#,
+        );
+
+        let (events, errors) = EventParser::new(&selectors)
+            .unwrap()
+            .plausible_years(2021..=2021)
+            .parse_all(document);
+
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(events.iter().next().unwrap().title, "Tennisskole");
+    }
+}
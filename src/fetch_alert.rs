@@ -0,0 +1,85 @@
+use std::time::{Duration, Instant};
+
+/// Keeps track of consecutive failed fetches, to alert once when fetching has failed a given
+/// number of times in a row, and once more when fetching works again.
+pub struct FetchAlert {
+    threshold: u32,
+    failures: u32,
+    /// When the first of the consecutive failures happened, if the last fetch failed.
+    failing_since: Option<Instant>,
+    alerted: bool,
+}
+
+impl FetchAlert {
+    /// Creates an alert for when `threshold` fetches in a row have failed.
+    pub fn new(threshold: u32) -> Self {
+        FetchAlert {
+            threshold,
+            failures: 0,
+            failing_since: None,
+            alerted: false,
+        }
+    }
+
+    /// Records a failed fetch. Returns how long fetching has been failing for if the threshold
+    /// was just reached, i.e. the alert is due, and `None` otherwise.
+    pub fn record_failure(&mut self) -> Option<Duration> {
+        self.failures += 1;
+        let failing_since = *self.failing_since.get_or_insert_with(Instant::now);
+
+        if self.alerted || self.failures < self.threshold {
+            return None;
+        }
+        self.alerted = true;
+        Some(failing_since.elapsed())
+    }
+
+    /// Records a successful fetch, resetting the count of failures. Returns how long fetching had
+    /// been failing for if an alert was sent, i.e. a recovery notice is due, and `None` otherwise.
+    pub fn record_success(&mut self) -> Option<Duration> {
+        let failing_since = self.failing_since.take();
+        let alerted = self.alerted;
+        self.failures = 0;
+        self.alerted = false;
+
+        failing_since
+            .filter(|_| alerted)
+            .map(|since| since.elapsed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alerts_once_at_threshold() {
+        let mut alert = FetchAlert::new(3);
+
+        assert!(alert.record_failure().is_none());
+        assert!(alert.record_failure().is_none());
+        assert!(alert.record_failure().is_some());
+        assert!(alert.record_failure().is_none());
+    }
+
+    #[test]
+    fn success_resets_failures() {
+        let mut alert = FetchAlert::new(2);
+
+        assert!(alert.record_failure().is_none());
+        assert!(alert.record_success().is_none());
+        assert!(alert.record_failure().is_none());
+        assert!(alert.record_failure().is_some());
+    }
+
+    #[test]
+    fn recovers_only_after_alert() {
+        let mut alert = FetchAlert::new(1);
+        assert!(alert.record_success().is_none());
+
+        assert!(alert.record_failure().is_some());
+
+        assert!(alert.record_success().is_some());
+        assert!(alert.record_success().is_none());
+    }
+}
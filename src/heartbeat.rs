@@ -0,0 +1,57 @@
+use std::time::{Duration, Instant};
+
+/// Keeps track of when a heartbeat notification is due, i.e. when no notification has been sent
+/// for a given interval.
+pub struct Heartbeat {
+    interval: Duration,
+    last_notification: Instant,
+}
+
+impl Heartbeat {
+    pub fn new(interval: Duration) -> Self {
+        Heartbeat {
+            interval,
+            last_notification: Instant::now(),
+        }
+    }
+
+    /// Whether the interval has passed since the last notification.
+    pub fn is_due(&self) -> bool {
+        self.last_notification.elapsed() >= self.interval
+    }
+
+    /// Restarts the interval, e.g. because a notification was just sent.
+    pub fn reset(&mut self) {
+        self.last_notification = Instant::now();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn is_due_after_interval() {
+        let heartbeat = Heartbeat::new(Duration::from_secs(0));
+
+        assert!(heartbeat.is_due());
+    }
+
+    #[test]
+    fn is_not_due_before_interval() {
+        let heartbeat = Heartbeat::new(Duration::from_secs(3600));
+
+        assert!(!heartbeat.is_due());
+    }
+
+    #[test]
+    fn reset_restarts_interval() {
+        let mut heartbeat = Heartbeat::new(Duration::from_millis(10));
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(heartbeat.is_due());
+
+        heartbeat.reset();
+
+        assert!(!heartbeat.is_due());
+    }
+}
@@ -0,0 +1,174 @@
+use std::{
+    collections::HashSet,
+    io::{self, BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{Arc, RwLock},
+    thread,
+    time::Duration,
+};
+
+use log_extern::warn;
+
+use crate::event::Event;
+
+/// Events shared between the main loop, which updates them each cycle, and the HTTP server.
+pub type SharedEvents = Arc<RwLock<HashSet<Event>>>;
+
+/// A response to an HTTP request.
+#[derive(Debug, PartialEq)]
+pub struct Response {
+    status: u16,
+    content_type: &'static str,
+    body: String,
+}
+
+impl Response {
+    pub fn new(status: u16, content_type: &'static str, body: impl Into<String>) -> Self {
+        Response {
+            status,
+            content_type,
+            body: body.into(),
+        }
+    }
+
+    pub fn not_found() -> Self {
+        Response::new(404, "text/plain", "Not Found")
+    }
+}
+
+/// Listens on `address` and answers each GET request on a background thread with the response
+/// `handler` gives for the requested path. Returns the address listened on, which differs from
+/// `address` if its port is 0.
+///
+/// # Errors
+///
+/// Returns an error if listening on `address` fails.
+pub fn serve<F>(address: SocketAddr, handler: F) -> io::Result<SocketAddr>
+where
+    F: Fn(&str) -> Response + Send + 'static,
+{
+    let listener = TcpListener::bind(address)?;
+    let address = listener.local_addr()?;
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let result = stream.and_then(|stream| handle(stream, &handler));
+            if let Err(error) = result {
+                warn!("Failed to handle HTTP request on {}: {}", address, error);
+            }
+        }
+    });
+
+    Ok(address)
+}
+
+/// Creates a handler serving `events` as JSON on `/events` and a health check on `/healthz`.
+pub fn events_handler(events: SharedEvents) -> impl Fn(&str) -> Response {
+    move |path| match path {
+        "/events" => {
+            let events = events.read().unwrap();
+            let mut events: Vec<_> = events.iter().collect();
+            events.sort();
+            match serde_json::to_string(&events) {
+                Ok(json) => Response::new(200, "application/json", json),
+                Err(error) => Response::new(500, "text/plain", error.to_string()),
+            }
+        }
+        "/healthz" => Response::new(200, "text/plain", "OK"),
+        _ => Response::not_found(),
+    }
+}
+
+fn handle(mut stream: TcpStream, handler: &impl Fn(&str) -> Response) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // Read the rest of the head, since requests are expected to have no body
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 0 && line != "\r\n" {
+        line.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let response = match (parts.next(), parts.next()) {
+        (Some("GET"), Some(target)) => handler(target.split('?').next().unwrap_or_default()),
+        (Some(_), Some(_)) => Response::new(405, "text/plain", "Method Not Allowed"),
+        _ => Response::new(400, "text/plain", "Bad Request"),
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        reason(response.status),
+        response.content_type,
+        response.body.len(),
+        response.body
+    )
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "Internal Server Error",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::blocking::Client;
+
+    use super::*;
+    use crate::test_util;
+
+    fn events(ids: &[&str]) -> SharedEvents {
+        Arc::new(RwLock::new(test_util::events(ids)))
+    }
+
+    fn start(events: SharedEvents) -> String {
+        let address = serve("127.0.0.1:0".parse().unwrap(), events_handler(events)).unwrap();
+        format!("http://{}", address)
+    }
+
+    #[test]
+    fn serves_current_events() {
+        let events = events(&["event1"]);
+        let url = start(Arc::clone(&events));
+        let client = Client::new();
+
+        let response = client.get(format!("{}/events", url)).send().unwrap();
+        assert_eq!(response.status(), 200);
+        let served: HashSet<Event> = response.json().unwrap();
+        assert_eq!(served, *events.read().unwrap());
+
+        // Updates made by the main loop are served on the next request
+        events.write().unwrap().insert(test_util::event("event2"));
+        let response = client.get(format!("{}/events", url)).send().unwrap();
+        let served: HashSet<Event> = response.json().unwrap();
+        assert_eq!(served.len(), 2);
+    }
+
+    #[test]
+    fn serves_health_check() {
+        let url = start(events(&[]));
+
+        let response = reqwest::blocking::get(format!("{}/healthz", url)).unwrap();
+
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.text().unwrap(), "OK");
+    }
+
+    #[test]
+    fn answers_unknown_paths_with_not_found() {
+        let url = start(events(&[]));
+
+        let response = reqwest::blocking::get(format!("{}/unknown", url)).unwrap();
+
+        assert_eq!(response.status(), 404);
+    }
+}
@@ -1,43 +1,206 @@
+//! Fetches the events of a KTK-style Halbooking club and notifies about new and changed ones.
+//!
+//! Besides the `ktkbot` executable, the crate can be embedded in other programs: fetch events with
+//! [`EventFetcher`], narrow them down with the functions in [`event::filter`], compare fetches
+//! with [`event::diff::Diff`], and send notifications with any [`Notifier`].
+
 pub mod args;
-mod event;
+mod club;
+mod digest;
+pub mod event;
+mod fetch_alert;
+mod heartbeat;
+mod http;
+mod lock;
 mod log;
+mod message;
+mod metrics;
 pub mod notification;
+mod notified;
+mod preflight;
+mod retry;
+mod route;
+mod stats;
+mod store;
+mod strings;
+#[cfg(test)]
+mod test_util;
 mod validate;
 
 use std::{
+    collections::HashSet,
+    error::Error,
+    fmt::{self, Display, Formatter},
+    fs::{self, File},
+    io::{self, Write},
+    net::SocketAddr,
     path::{Path, PathBuf},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock,
+    },
     thread,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
+use chrono::{DateTime, FixedOffset, NaiveTime, Utc, Weekday};
 use flexi_logger;
+use lettre::{message::Mailbox, transport::smtp::authentication::Credentials};
 use log_extern::{error, info, warn};
-use reqwest::blocking::Response;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::Deserialize;
 
 use event::{
-    fetch::{EventFetcher, FetchError},
-    Event,
+    diff::Diff,
+    fetch::{EventFetcherBuilder, FetchError},
+    filter,
+    parse::{EventParser, Selectors},
+};
+pub use event::{fetch::EventFetcher, parse::parse_document, Event};
+pub use notification::Notifier;
+
+use club::Clubs;
+use digest::Digest;
+use fetch_alert::FetchAlert;
+use heartbeat::Heartbeat;
+use lock::InstanceLock;
+use message::MessageFormat;
+use metrics::Metrics;
+use notification::{
+    DiscordNotifier, DryRunNotifier, EmailNotifier, GenericWebhookNotifier, NotifyError,
+    PushoverKey, PushoverNotifier, RetryingNotifier, TelegramNotifier,
 };
-use notification::{Notification, PushoverKey};
+use notified::NotifiedIds;
+use preflight::DirectoryError;
+use retry::RetryPolicy;
+use route::Router;
+use store::{ClubStore, JsonStore, SqliteStore, Store, StoreError};
+use strings::Strings;
 
 #[derive(Debug)]
 pub struct Config {
+    /// The TOML file the configuration was loaded from, if any, which is reloaded on SIGHUP.
+    pub config_file: Option<PathBuf>,
     pub log: LogConfig,
-    pub pushover: PushoverConfig,
+    pub notifier: NotifierConfig,
+    pub fetch: FetchConfig,
+    pub export: ExportConfig,
+    pub filter: FilterConfig,
     events_file: PathBuf,
+    /// Further clubs to fetch events from besides the one at [`FetchConfig::base_url`], each with
+    /// its own events file.
+    pub clubs: Vec<ClubConfig>,
+    pub events_backups: usize,
+    /// How long past events are kept in the stored events before being pruned, or `None` to never
+    /// prune them.
+    pub prune_past: Option<Duration>,
+    pub store: StoreConfig,
+    /// Whether to lock the stored events, so a second instance using them exits instead of
+    /// overwriting them.
+    pub lock: bool,
     pub fetch_interval: Duration,
+    /// The percentage by which the delay in between fetches randomly varies.
+    pub interval_jitter: u8,
+    /// Whether to fetch at multiples of the fetch interval on the clock, e.g. at :00, :05, :10, and
+    /// so on for 5 minutes, instead of the fetch interval after the last fetch.
+    pub align_schedule: bool,
+    pub heartbeat: Option<Duration>,
+    /// The number of failed fetches in a row after which to send an alert, or 0 to never alert.
+    pub error_alert_after: u32,
+    /// The number of new events in a cycle above which a single summary is sent instead of a
+    /// notification about the events, if any.
+    pub max_new_per_cycle: Option<usize>,
+    /// The percentage of fetched events that must be new, while there are stored events, for the
+    /// site to be considered to have changed its event ids, in which case nothing is notified
+    /// about. 0 never considers the ids changed.
+    pub id_change_threshold: u8,
+    pub quiet_hours: Option<QuietHours>,
+    /// The local time to send a daily digest of new events at, instead of a notification per
+    /// change, if any. Quiet hours do not apply to the digest.
+    pub digest_at: Option<NaiveTime>,
+    /// The format of the notification messages.
+    pub message: MessageFormat,
+    /// The address to serve the stored events on over HTTP, if any.
+    pub http_listen: Option<SocketAddr>,
+    /// The address to serve Prometheus metrics on over HTTP, if any.
+    pub metrics_listen: Option<SocketAddr>,
+    /// Whether a notification fails when any notifier fails to send it, instead of only when all
+    /// notifiers do.
+    pub notify_require_all: bool,
+    pub dry_run: bool,
+    pub once: bool,
+    /// A local HTML file to parse and print events from instead of fetching them, if any.
+    pub from_file: Option<PathBuf>,
+    /// Whether to send a test notification and exit instead of fetching anything.
+    pub test_notification: bool,
+    /// Whether to check the configuration and exit instead of running.
+    pub validate_config: bool,
+    /// Whether to exit when the fetched events cannot be parsed, instead of skipping the cycle.
+    pub strict: bool,
+    /// Whether to accept fetching no events while there are stored events, instead of skipping
+    /// the cycle, e.g. because the site serves a maintenance page.
+    pub allow_empty: bool,
+    /// Whether to write the changes of each cycle to stdout as newline-delimited JSON.
+    pub emit_ndjson: bool,
+    /// Whether to notify about all events as new when there are no stored events yet, instead of
+    /// only storing them as the events that are already known.
+    pub notify_on_first_run: bool,
 }
 
 impl Config {
     pub fn events_file(&self) -> &Path {
         &self.events_file.as_path()
     }
+
+    /// Gets the file that the ids of events notified about are stored in, which is
+    /// `notified.json` next to the events file.
+    pub fn notified_file(&self) -> PathBuf {
+        self.events_file.with_file_name("notified.json")
+    }
+
+    /// Gets the file that new events are buffered in until the daily digest is sent, which is
+    /// `digest.json` next to the events file.
+    pub fn digest_file(&self) -> PathBuf {
+        self.events_file.with_file_name("digest.json")
+    }
+
+    /// Gets the base URLs of all clubs to fetch events from, starting with
+    /// [`FetchConfig::base_url`].
+    pub fn base_urls(&self) -> Vec<&str> {
+        let mut base_urls = vec![self.fetch.base_url()];
+        base_urls.extend(self.clubs.iter().map(|club| club.base_url()));
+        base_urls
+    }
+}
+
+/// A further club to fetch events from, see [`Config::clubs`].
+#[derive(Debug)]
+pub struct ClubConfig {
+    base_url: String,
+    events_file: PathBuf,
+}
+
+impl ClubConfig {
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Gets the file that the events of the club are stored in. Events of further clubs are
+    /// always stored as JSON.
+    pub fn events_file(&self) -> &Path {
+        &self.events_file
+    }
 }
 
 #[derive(Debug)]
 pub struct LogConfig {
     level: flexi_logger::Level,
     directory: PathBuf,
+    format: LogFormat,
+    rotation: LogRotation,
+    keep: usize,
+    to_file: bool,
 }
 
 impl LogConfig {
@@ -48,139 +211,2559 @@ impl LogConfig {
     pub fn directory(&self) -> &Path {
         &self.directory.as_path()
     }
+
+    pub fn format(&self) -> LogFormat {
+        self.format
+    }
+
+    pub fn rotation(&self) -> LogRotation {
+        self.rotation
+    }
+
+    pub fn keep(&self) -> usize {
+        self.keep
+    }
+
+    /// Whether to write log files, or only log to stdout.
+    pub fn to_file(&self) -> bool {
+        self.to_file
+    }
+}
+
+/// The formats that log lines can be written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human readable lines with colored levels.
+    Text,
+    /// One JSON object per line.
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            _ => Err(format!("Unknown log format: {}", s)),
+        }
+    }
+}
+
+/// When to start a new log file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogRotation {
+    /// A new log file every day.
+    Daily,
+    /// A new log file whenever the current one exceeds the given number of megabytes.
+    Size(u64),
+}
+
+impl FromStr for LogRotation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "daily" {
+            return Ok(Self::Daily);
+        }
+        match s.strip_prefix("size:").map(str::parse) {
+            Some(Ok(megabytes)) if megabytes > 0 => Ok(Self::Size(megabytes)),
+            _ => Err(format!(
+                "Invalid log rotation: {} - must be daily or size:<MB>, e.g. size:10",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct FetchConfig {
+    retry: RetryPolicy,
+    timeout: Duration,
+    base_url: String,
+    page_delay: Duration,
+    selectors: Selectors,
+    proxy: Option<String>,
+    warm_up_url: Option<String>,
+}
+
+impl FetchConfig {
+    pub fn retry(&self) -> &RetryPolicy {
+        &self.retry
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Gets the minimum delay between requests for successive pages of events.
+    pub fn page_delay(&self) -> Duration {
+        self.page_delay
+    }
+
+    /// Gets the CSS selectors used to find events and their details on a page of events.
+    pub fn selectors(&self) -> &Selectors {
+        &self.selectors
+    }
+
+    /// Gets the URL of the proxy to fetch events through, if any.
+    pub fn proxy(&self) -> Option<&str> {
+        self.proxy.as_deref()
+    }
+
+    /// Gets the URL to request before fetching events to set cookies, if any.
+    pub fn warm_up_url(&self) -> Option<&str> {
+        self.warm_up_url.as_deref()
+    }
+}
+
+#[derive(Debug)]
+pub struct ExportConfig {
+    ical_file: Option<PathBuf>,
+    csv_file: Option<PathBuf>,
+    rss_file: Option<PathBuf>,
+    markdown_file: Option<PathBuf>,
+}
+
+impl ExportConfig {
+    pub fn ical_file(&self) -> Option<&Path> {
+        self.ical_file.as_deref()
+    }
+
+    pub fn csv_file(&self) -> Option<&Path> {
+        self.csv_file.as_deref()
+    }
+
+    pub fn rss_file(&self) -> Option<&Path> {
+        self.rss_file.as_deref()
+    }
+
+    pub fn markdown_file(&self) -> Option<&Path> {
+        self.markdown_file.as_deref()
+    }
+}
+
+/// Where to store known events.
+#[derive(Debug)]
+pub enum StoreConfig {
+    Json(PathBuf),
+    Sqlite(PathBuf),
+}
+
+impl Display for StoreConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Json(path) => write!(f, "JSON file {:?}", path),
+            Self::Sqlite(path) => write!(f, "SQLite database {:?}", path),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct FilterConfig {
+    includes: Vec<String>,
+    excludes: Vec<String>,
+    from: Option<DateTime<FixedOffset>>,
+    until: Option<DateTime<FixedOffset>>,
+    weekdays: HashSet<Weekday>,
+    min_advance: Option<Duration>,
+    levels: Vec<String>,
+    level_unknown_ok: bool,
+}
+
+impl FilterConfig {
+    pub fn includes(&self) -> &[String] {
+        &self.includes
+    }
+
+    pub fn excludes(&self) -> &[String] {
+        &self.excludes
+    }
+
+    /// Gets the start of the first day to notify about events on.
+    pub fn from(&self) -> Option<DateTime<FixedOffset>> {
+        self.from
+    }
+
+    /// Gets the start of the day after the last day to notify about events on.
+    pub fn until(&self) -> Option<DateTime<FixedOffset>> {
+        self.until
+    }
+
+    /// Gets the weekdays to notify about events on, or no weekdays to notify about all events.
+    pub fn weekdays(&self) -> &HashSet<Weekday> {
+        &self.weekdays
+    }
+
+    /// Gets how long before an event starts a notification about it must be sent at the latest.
+    pub fn min_advance(&self) -> Option<Duration> {
+        self.min_advance
+    }
+
+    /// Gets the levels to notify about events at, or no levels to notify about all events.
+    pub fn levels(&self) -> &[String] {
+        &self.levels
+    }
+
+    /// Whether to notify about events without a level when filtering by level.
+    pub fn level_unknown_ok(&self) -> bool {
+        self.level_unknown_ok
+    }
+}
+
+/// A daily window of Danish local time in which notifications are held back and then sent together
+/// once the window ends.
+#[derive(Debug, Clone, Copy)]
+pub struct QuietHours {
+    from: NaiveTime,
+    until: NaiveTime,
+}
+
+impl QuietHours {
+    /// Whether `time` falls within quiet hours, from `from` inclusive to `until` exclusive. If
+    /// `from` is after `until`, e.g. 22:00 to 07:00, the window spans midnight.
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.from <= self.until {
+            self.from <= time && time < self.until
+        } else {
+            self.from <= time || time < self.until
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum NotifierConfig {
+    Pushover(PushoverConfig),
+    Telegram(TelegramConfig),
+    Discord(DiscordConfig),
+    Email(EmailConfig),
+    Webhook(WebhookConfig),
 }
 
 #[derive(Debug)]
 pub struct PushoverConfig {
     api_key: PushoverKey,
     group_key: PushoverKey,
+    routes: Vec<PushoverRoute>,
+    priority: Option<i8>,
+    sound: Option<String>,
+    device: Option<String>,
+}
+
+/// A Pushover group that receives notifications about events with a title containing any of
+/// `includes`, in addition to or instead of the default group.
+#[derive(Debug)]
+pub struct PushoverRoute {
+    group_key: PushoverKey,
+    includes: Vec<String>,
 }
 
-pub fn run(config: &Config) {
-    let _logger_handle = log::init_logger(config.log.level(), config.log.directory())
-        .unwrap_or_else(|error| panic!("Failed to initialize logger: {}", error));
+#[derive(Debug)]
+pub struct TelegramConfig {
+    token: String,
+    chat_id: String,
+}
+
+#[derive(Debug)]
+pub struct DiscordConfig {
+    webhook_url: String,
+}
+
+#[derive(Debug)]
+pub struct WebhookConfig {
+    url: String,
+    headers: Vec<WebhookHeader>,
+}
+
+/// A header sent with every request to a webhook, given as `NAME:VALUE`.
+#[derive(Debug, Clone)]
+pub struct WebhookHeader {
+    name: String,
+    value: String,
+}
+
+#[derive(Debug)]
+pub struct EmailConfig {
+    host: String,
+    port: u16,
+    username: String,
+    password: String,
+    from: Mailbox,
+    to: Mailbox,
+}
+
+/// Runs the bot, either continuously or, if [`Config::once`] is set, for a single cycle.
+///
+/// # Errors
+///
+/// Returns an error if the single cycle fails. Running continuously never returns.
+pub fn run(mut config: Config) -> Result<(), CycleError> {
+    let _logger_handle = init_logger(&config.log);
+
+    if config.test_notification {
+        return send_test_notification(&config);
+    }
+
+    let parser = create_parser_or_exit(&config);
+    if let Some(path) = &config.from_file {
+        return print_events_from_file(path, &parser);
+    }
 
     info!("Creating EventFetcher...");
 
-    let fetcher = EventFetcher::new()
+    let clubs = create_clubs(&config, parser, EventFetcherBuilder::build)
         .unwrap_or_else(|error| exit(format!("Failed to create EventFetcher: {}", error).as_str()));
 
+    let router = create_router_or_exit(&config);
+
     info!(
-        "Created EventFetcher. Loading local list of events from {:?}...",
-        config.events_file()
+        "Created EventFetcher. Loading stored events from {}...",
+        config.store
     );
 
-    // Try to load known events from local file. If it fails, then fetch the events and write them
-    // to the file. If writing fails, then continue with in-memory list `stored_events`.
-    let mut stored_events =
-        event::deserialize_events(config.events_file()).unwrap_or_else(|error| {
-            warn!("Failed to load local list of events: {}", error);
-            warn!(
-                "Fetching events and creating new local list at {:?} instead...",
-                config.events_file()
-            );
-
-            let events = fetcher.fetch_all().unwrap_or_else(|error| {
-                exit(format!("Failed to fetch events: {}", error).as_str())
-            });
-
-            info!(
-                "Fetched events. Writing them to {:?}...",
-                config.events_file()
-            );
+    let _locks = lock_stores_or_exit(&config);
+    let mut store = open_store(&config)
+        .unwrap_or_else(|error| exit(format!("Failed to open store: {}", error).as_str()));
+    let mut stored_events = load_stored_events(store.as_mut(), &config, || clubs.fetch_all());
+    let mut notified = load_notified_ids(&config);
+    let mut digest = load_digest(&config);
 
-            if let Err(error) = event::serialize_events(&events, config.events_file()) {
-                warn!(
-                    "Failed to save fetched events to {:?}: {}",
-                    config.events_file(),
-                    error
-                );
-                warn!("Continuing without saving events to disk, only storing them in memory.");
-            } else {
-                info!("Wrote events to {:?}.", config.events_file());
-            }
+    if config.once {
+        info!("Running once.");
 
-            events
-        });
+        let result = once_delivery(digest.as_mut(), &stored_events, &router, &mut notified)
+            .and_then(|delivery| {
+                run_once(
+                    &clubs,
+                    &config,
+                    store.as_mut(),
+                    &mut stored_events,
+                    &router,
+                    &mut notified,
+                    delivery,
+                )
+            });
+        return finish_once(result);
+    }
 
-    // Continuously fetch events and compare to local list of events. If there are any new ones,
-    // then send a push notification and update local list.
+    // Continuously fetch events and compare to local list of events. If any have been added,
+    // removed, or changed, then send a notification and update local list.
+    let mut state = LoopState::start(&config, &stored_events);
+    let mut rng = StdRng::from_entropy();
     let mut running = false;
+    if config.align_schedule && config.interval_jitter > 0 {
+        warn!("Interval jitter is ignored when the schedule is aligned to the clock.");
+    }
     loop {
         if running {
-            info!("Fetching again in 2 minutes.\n");
-            thread::sleep(config.fetch_interval);
+            let interval = state.take_backoff().unwrap_or_else(|| {
+                if config.align_schedule {
+                    until_aligned(Utc::now(), config.fetch_interval)
+                } else {
+                    jittered_interval(config.fetch_interval, config.interval_jitter, &mut rng)
+                }
+            });
+            info!("Fetching again in {} seconds.\n", interval.as_secs());
+            thread::sleep(interval);
         } else {
             running = true;
             info!("Now running.");
         }
 
-        info!("Fetching events...");
+        state.reload_if_requested(&mut config);
+        let delivery = state.before_cycle(
+            &config,
+            &stored_events,
+            &router,
+            &mut notified,
+            digest.as_mut(),
+        );
+        let result = run_once(
+            &clubs,
+            &config,
+            store.as_mut(),
+            &mut stored_events,
+            &router,
+            &mut notified,
+            delivery,
+        );
+        state.after_cycle(
+            result,
+            &stored_events,
+            &router,
+            config.message.language.strings(),
+        );
+    }
+}
+
+/// Creates the log directory and the directory of the events file if missing, and checks that
+/// they are writable, so problems are found at startup instead of during the first cycle. The log
+/// directory is left alone when log files are disabled.
+///
+/// # Errors
+///
+/// Returns an error describing the first directory that is not writable.
+pub fn check_directories(config: &Config) -> Result<(), DirectoryError> {
+    let directory = |events_file: &'_ Path| match events_file.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
 
-        let events = match fetcher.fetch_all() {
-            Ok(events) => events,
-            Err(FetchError::Request(error)) => {
-                warn!("Failed to fetch events: {}", error);
-                continue;
-            }
-            Err(FetchError::Parse(error)) => {
-                exit(format!("Failed to fetch events: {}", error).as_str())
+    if config.log.to_file() {
+        preflight::ensure_writable(config.log.directory())?;
+    }
+    preflight::ensure_writable(&directory(config.events_file()))?;
+    for club in &config.clubs {
+        preflight::ensure_writable(&directory(club.events_file()))?;
+    }
+    Ok(())
+}
+
+/// Prints a summary of the events stored in `events_file` as "key: value" lines, e.g. the number
+/// of events by weekday and level, without fetching anything.
+///
+/// # Errors
+///
+/// Returns an error if the events cannot be read from `events_file`.
+pub fn print_stats(events_file: &Path) -> Result<(), Box<dyn Error>> {
+    let events = event::deserialize_events(events_file)?;
+    print!("{}", stats::Stats::of(&events));
+    Ok(())
+}
+
+/// Checks the configuration without fetching or sending anything: that the directories are
+/// writable, that the HTTP client can be built, that the selectors compile, and that the notifier
+/// can be created. Values given as arguments or in the config file have already been validated
+/// when `config` was parsed. Prints the result of each check and a summary.
+///
+/// # Errors
+///
+/// Returns the number of checks that failed, if any.
+pub fn validate_config(config: &Config) -> Result<(), usize> {
+    let checks = [
+        (
+            "Directories",
+            check_directories(config).map_err(|error| error.to_string()),
+        ),
+        (
+            "HTTP client",
+            create_clubs(config, EventParser::default(), EventFetcherBuilder::build)
+                .map(|_| ())
+                .map_err(|error| error.to_string()),
+        ),
+        (
+            "Selectors",
+            EventParser::new(config.fetch.selectors())
+                .map(|_| ())
+                .map_err(|error| error.to_string()),
+        ),
+        (
+            "Notifier",
+            create_router(&config.notifier, false)
+                .map(|_| ())
+                .map_err(|error| error.to_string()),
+        ),
+    ];
+
+    for (name, result) in &checks {
+        match result {
+            Ok(()) => println!("ok      {}", name),
+            Err(error) => println!("FAILED  {}: {}", name, error),
+        }
+    }
+
+    let failed = checks.iter().filter(|(_, result)| result.is_err()).count();
+    println!(
+        "{} of {} checks passed.",
+        checks.len() - failed,
+        checks.len()
+    );
+    if failed > 0 {
+        return Err(failed);
+    }
+    Ok(())
+}
+
+/// Runs the bot like [`run`], but fetches events without blocking, with the main loop driven by a
+/// tokio interval. Notifications are still sent and events still stored synchronously.
+///
+/// # Errors
+///
+/// Returns an error if the single cycle fails. Running continuously never returns.
+#[cfg(feature = "async")]
+pub fn run_async(mut config: Config) -> Result<(), CycleError> {
+    use tokio::{
+        task,
+        time::{Instant, Interval, MissedTickBehavior},
+    };
+
+    let _logger_handle = init_logger(&config.log);
+
+    if config.test_notification {
+        return send_test_notification(&config);
+    }
+
+    let parser = create_parser_or_exit(&config);
+    if let Some(path) = &config.from_file {
+        return print_events_from_file(path, &parser);
+    }
+
+    info!("Creating AsyncEventFetcher...");
+
+    let clubs =
+        create_clubs(&config, parser, EventFetcherBuilder::build_async).unwrap_or_else(|error| {
+            exit(format!("Failed to create AsyncEventFetcher: {}", error).as_str())
+        });
+    let runtime = tokio::runtime::Runtime::new()
+        .unwrap_or_else(|error| exit(format!("Failed to start runtime: {}", error).as_str()));
+
+    let router = create_router_or_exit(&config);
+
+    info!(
+        "Created AsyncEventFetcher. Loading stored events from {}...",
+        config.store
+    );
+
+    let _locks = lock_stores_or_exit(&config);
+    let mut store = open_store(&config)
+        .unwrap_or_else(|error| exit(format!("Failed to open store: {}", error).as_str()));
+    let mut stored_events = load_stored_events(store.as_mut(), &config, || {
+        runtime.block_on(clubs.fetch_all())
+    });
+    let mut notified = load_notified_ids(&config);
+    let mut digest = load_digest(&config);
+
+    runtime.block_on(async {
+        // Notifiers and stores block, so they are run outside of the async context
+        let mut run_cycle = |events: HashSet<Event>,
+                             config: &Config,
+                             stored_events: &mut HashSet<Event>,
+                             notified: &mut NotifiedIds,
+                             delivery: Delivery<'_>,
+                             started: Instant| {
+            let fetched = events.len();
+            let result = task::block_in_place(|| {
+                let store = store.as_mut();
+                process_events(
+                    events,
+                    config,
+                    store,
+                    stored_events,
+                    &router,
+                    notified,
+                    delivery,
+                )
+            });
+            if let Ok(outcome) = &result {
+                log_cycle_summary(fetched, outcome, started.elapsed());
             }
+            result
         };
 
-        info!("Fetched events. Comparing to local list of events...");
+        if config.once {
+            info!("Running once.");
 
-        let mut diff: Vec<_> = events.difference(&stored_events).collect();
+            let started = Instant::now();
+            let delivery = task::block_in_place(|| {
+                once_delivery(digest.as_mut(), &stored_events, &router, &mut notified)
+            });
+            let result = match delivery {
+                Ok(delivery) => match clubs.fetch_all().await {
+                    Ok(events) => run_cycle(
+                        events,
+                        &config,
+                        &mut stored_events,
+                        &mut notified,
+                        delivery,
+                        started,
+                    ),
+                    Err(error) => Err(CycleError::from(error)),
+                },
+                Err(error) => Err(error),
+            };
+            return finish_once(result);
+        }
 
-        if diff.is_empty() {
-            info!("There are no new events.");
-            continue;
+        if config.interval_jitter > 0 {
+            warn!("Interval jitter is not supported when running asynchronously and is ignored.");
         }
 
-        info!(
-            "There are {} new events. Sorting and sending push notification...",
-            diff.len()
-        );
+        let new_interval = |start: Instant, period: Duration| -> Interval {
+            let mut interval = tokio::time::interval_at(start, period);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            interval
+        };
 
-        diff.sort();
+        let mut state = LoopState::start(&config, &stored_events);
+        let mut interval = new_interval(Instant::now(), config.fetch_interval);
+        info!("Now running.");
+        loop {
+            // The first tick completes immediately
+            interval.tick().await;
 
-        send_push_notification(&diff, &config.pushover).unwrap_or_else(|error| {
-            exit(format!("Failed to send push notification: {}", error).as_str())
-        });
+            let period = config.fetch_interval;
+            state.reload_if_requested(&mut config);
+            if config.fetch_interval != period {
+                let period = config.fetch_interval;
+                interval = new_interval(Instant::now() + period, period);
+            }
+
+            let delivery = task::block_in_place(|| {
+                let digest = digest.as_mut();
+                state.before_cycle(&config, &stored_events, &router, &mut notified, digest)
+            });
+            let started = Instant::now();
+            info!("Fetching events...");
+            let result = match clubs.fetch_all().await {
+                Ok(events) => {
+                    info!("Fetched events.");
+                    run_cycle(
+                        events,
+                        &config,
+                        &mut stored_events,
+                        &mut notified,
+                        delivery,
+                        started,
+                    )
+                }
+                Err(error) => Err(CycleError::from(error)),
+            };
+            let strings = config.message.language.strings();
+            task::block_in_place(|| state.after_cycle(result, &stored_events, &router, strings));
 
-        info!("Sent push notification. Updating local list of events...");
+            if config.align_schedule {
+                // Ticking from the next multiple of the interval keeps the schedule on the clock
+                let period = config.fetch_interval;
+                interval = new_interval(Instant::now() + until_aligned(Utc::now(), period), period);
+            }
 
-        stored_events = events;
-        if let Err(error) = event::serialize_events(&stored_events, config.events_file()) {
-            exit(format!("Failed to serialize events: {}", error).as_str());
+            if let Some(backoff) = state.take_backoff() {
+                tokio::time::sleep(backoff).await;
+            }
         }
+    })
+}
+
+/// Sends a single test notification through the default notifier instead of fetching anything,
+/// and prints the response, to check that notifications are delivered with the configured keys.
+fn send_test_notification(config: &Config) -> Result<(), CycleError> {
+    let router = create_router_or_exit(config);
 
-        info!("Updated local list of events.");
+    let strings = config.message.language.strings();
+    match router
+        .default_notifier()
+        .send_test(strings.test, strings.test)
+    {
+        Ok(response) => {
+            println!("{}", response);
+            Ok(())
+        }
+        Err(error) => {
+            eprintln!("Failed to send test notification: {}", error);
+            Err(CycleError::Notify(error))
+        }
     }
 }
 
-fn exit(message: &str) -> ! {
-    error!("{}", message);
-    panic!("{}", message)
+/// Parses the events in the local HTML file at `path` instead of fetching them, and prints them
+/// as JSON, e.g. to debug changes to the markup of the site.
+fn print_events_from_file(path: &Path, parser: &EventParser) -> Result<(), CycleError> {
+    info!("Parsing events from {:?}...", path);
+
+    let html = fs::read_to_string(path)
+        .unwrap_or_else(|error| exit(format!("Failed to read {:?}: {}", path, error).as_str()));
+    let events = event::fetch::parse_page(parser, &path.to_string_lossy(), &html, None).map_err(
+        |error| {
+            error!("Failed to parse events from {:?}: {}", path, error);
+            CycleError::from(error)
+        },
+    )?;
+
+    let mut events: Vec<_> = events.iter().collect();
+    events.sort();
+    let json = serde_json::to_string_pretty(&events)
+        .unwrap_or_else(|error| exit(format!("Failed to serialize events: {}", error).as_str()));
+    println!("{}", json);
+
+    info!("Parsed {} events.", events.len());
+    Ok(())
+}
+
+/// Logs the result of running a single cycle with [`Config::once`] set.
+fn finish_once(result: Result<CycleOutcome, CycleError>) -> Result<(), CycleError> {
+    match result {
+        Ok(outcome) => {
+            info!("Finished: {}", outcome);
+            Ok(())
+        }
+        Err(error) => {
+            error!("Failed: {}", error);
+            Err(error)
+        }
+    }
+}
+
+fn init_logger(config: &LogConfig) -> flexi_logger::LoggerHandle {
+    log::init_logger(
+        config.level(),
+        config.directory(),
+        config.format(),
+        config.rotation(),
+        config.keep(),
+        config.to_file(),
+    )
+    .unwrap_or_else(|error| panic!("Failed to initialize logger: {}", error))
+}
+
+/// Creates a fetcher with `build` for each club, all configured the same apart from the base URL.
+fn create_clubs<F>(
+    config: &Config,
+    parser: EventParser,
+    build: impl Fn(&EventFetcherBuilder) -> Result<F, reqwest::Error>,
+) -> Result<Clubs<F>, reqwest::Error> {
+    let mut builder = EventFetcher::builder();
+    builder
+        .retry(*config.fetch.retry())
+        .timeout(config.fetch.timeout())
+        .page_delay(config.fetch.page_delay())
+        .parser(parser);
+    if let Some(proxy) = config.fetch.proxy() {
+        builder.proxy(proxy);
+    }
+    if let Some(warm_up_url) = config.fetch.warm_up_url() {
+        builder.warm_up_url(warm_up_url);
+    }
+
+    let mut clubs = Vec::new();
+    for base_url in config.base_urls() {
+        let fetcher = build(builder.base_url(base_url))?;
+        clubs.push((club::name(base_url), fetcher));
+    }
+    Ok(Clubs::new(clubs))
+}
+
+fn create_parser_or_exit(config: &Config) -> EventParser {
+    EventParser::new(config.fetch.selectors())
+        .unwrap_or_else(|error| exit(format!("Failed to create EventParser: {}", error).as_str()))
+}
+
+fn create_router_or_exit(config: &Config) -> Router {
+    if config.dry_run {
+        info!("Running in dry run mode. Notifications will be logged instead of sent.");
+    }
+
+    create_router(&config.notifier, config.dry_run)
+        .map(|router| {
+            router
+                .message_format(config.message.clone())
+                .require_all(config.notify_require_all)
+        })
+        .unwrap_or_else(|error| exit(format!("Failed to create notifier: {}", error).as_str()))
+}
+
+/// Loads the ids of events already notified about, or no ids if loading fails.
+fn load_notified_ids(config: &Config) -> NotifiedIds {
+    let path = config.notified_file();
+    NotifiedIds::load(&path).unwrap_or_else(|error| {
+        warn!(
+            "Failed to load notified event ids from {:?}: {}",
+            path, error
+        );
+        NotifiedIds::empty(path)
+    })
+}
+
+/// Loads the daily digest if one is configured, or an empty digest if loading fails.
+fn load_digest(config: &Config) -> Option<Digest> {
+    let at = config.digest_at?;
+    let path = config.digest_file();
+    let digest = Digest::load(&path, at).unwrap_or_else(|error| {
+        warn!("Failed to load daily digest from {:?}: {}", path, error);
+        Digest::empty(path, at)
+    });
+    info!(
+        "Sending new events in a daily digest at {}.",
+        at.format("%H:%M")
+    );
+    Some(digest)
+}
+
+/// Sends the daily digest if it is due, and gets how to deliver notifications when running once.
+fn once_delivery<'a>(
+    digest: Option<&'a mut Digest>,
+    stored_events: &HashSet<Event>,
+    router: &Router,
+    notified: &mut NotifiedIds,
+) -> Result<Delivery<'a>, CycleError> {
+    match digest {
+        Some(digest) => {
+            send_digest_if_due(digest, stored_events, router, notified)
+                .map_err(CycleError::Notify)?;
+            Ok(Delivery::Digest(digest))
+        }
+        None => Ok(Delivery::Immediate),
+    }
+}
+
+/// Loads known events from `store`. If there are none, then the events are fetched with `fetch`
+/// and stored. If storing fails, then the fetched events are only kept in memory.
+fn load_stored_events(
+    store: &mut dyn Store,
+    config: &Config,
+    fetch: impl FnOnce() -> Result<HashSet<Event>, FetchError>,
+) -> HashSet<Event> {
+    match store.load() {
+        Ok(Some(events)) => events,
+        // Starting without known events makes the first cycle notify about all events as new
+        Ok(None) if config.notify_on_first_run => {
+            info!("There are no stored events yet. Notifying about all events in the first cycle.");
+            HashSet::new()
+        }
+        result => {
+            match result {
+                Err(error) => warn!("Failed to load stored events: {}", error),
+                _ => info!("There are no stored events yet."),
+            }
+            warn!(
+                "Fetching events and storing them in {} instead...",
+                config.store
+            );
+
+            let events = fetch().unwrap_or_else(|error| {
+                exit(format!("Failed to fetch events: {}", error).as_str())
+            });
+
+            info!("Fetched events. Storing them in {}...", config.store);
+
+            let no_events = HashSet::new();
+            if let Err(error) = store.save_diff(&events, &Diff::between(&no_events, &events)) {
+                warn!("Failed to store fetched events: {}", error);
+                warn!("Continuing without saving events to disk, only storing them in memory.");
+            } else {
+                info!("Stored events in {}.", config.store);
+            }
+
+            events
+        }
+    }
 }
 
-fn send_push_notification(
-    events: &Vec<&Event>,
-    config: &PushoverConfig,
-) -> Result<Response, reqwest::Error> {
-    let mut message = String::from("<u>Der er blevet lagt nye tider op</u>:");
-    for event in events {
-        message.push_str(
-            format!(
-                "\n- <b>{}</b>: {}",
-                event.title,
-                event.date_time.format("%a %e %b %Y"),
-            )
-            .as_str(),
-        );
-    }
-    Notification::new(&config.api_key, &config.group_key, &message[..])
-        .title("Nye tider lagt op!")
-        .html(true)
-        .send()
+/// The state kept in between the cycles of running continuously, i.e. heartbeats, notifications
+/// held during quiet hours, and the state served over HTTP.
+struct LoopState {
+    heartbeat: Option<Heartbeat>,
+    fetch_alert: Option<FetchAlert>,
+    /// The stored events from when quiet hours started, if notifications are being held.
+    held_events: Option<HashSet<Event>>,
+    shared_events: Option<http::SharedEvents>,
+    metrics: Arc<Metrics>,
+    /// How long to wait before the next cycle instead of the fetch interval, if rate limited.
+    backoff: Option<Duration>,
+    strict: bool,
+    /// Set when SIGHUP is received, if the config file can be reloaded.
+    reload_requested: Option<Arc<AtomicBool>>,
+}
+
+impl LoopState {
+    /// Creates the state for running continuously, starting the HTTP servers if configured.
+    fn start(config: &Config, stored_events: &HashSet<Event>) -> Self {
+        let shared_events = config.http_listen.map(|address| {
+            let events = Arc::new(RwLock::new(stored_events.clone()));
+            match http::serve(address, http::events_handler(Arc::clone(&events))) {
+                Ok(address) => info!("Serving events on http://{}/events.", address),
+                Err(error) => exit(format!("Failed to listen on {}: {}", address, error).as_str()),
+            }
+            events
+        });
+
+        let metrics = Arc::new(Metrics::default());
+        metrics.set_events_tracked(stored_events.len());
+        if let Some(address) = config.metrics_listen {
+            match http::serve(address, metrics::metrics_handler(Arc::clone(&metrics))) {
+                Ok(address) => info!("Serving metrics on http://{}/metrics.", address),
+                Err(error) => exit(format!("Failed to listen on {}: {}", address, error).as_str()),
+            }
+        }
+
+        LoopState {
+            heartbeat: config.heartbeat.map(Heartbeat::new),
+            fetch_alert: Some(config.error_alert_after)
+                .filter(|&threshold| threshold > 0)
+                .map(FetchAlert::new),
+            held_events: None,
+            shared_events,
+            metrics,
+            backoff: None,
+            strict: config.strict,
+            reload_requested: config
+                .config_file
+                .as_ref()
+                .and_then(|_| watch_reload_signal()),
+        }
+    }
+
+    /// Reloads the config file if SIGHUP was received since the last cycle.
+    fn reload_if_requested(&self, config: &mut Config) {
+        let requested = match &self.reload_requested {
+            Some(requested) => requested.swap(false, Ordering::Relaxed),
+            None => false,
+        };
+        if !requested {
+            return;
+        }
+
+        match args::reload_config(config) {
+            Ok(()) => info!(
+                "Reloaded the fetch interval, interval jitter, filters, and quiet hours from the \
+                 config file. Other settings, such as keys and the events file, are only applied \
+                 on restart."
+            ),
+            Err(error) => warn!(
+                "Failed to reload config file, keeping the settings: {}",
+                error
+            ),
+        }
+    }
+
+    /// Takes the delay to wait before the next cycle if a notifier was rate limited, in which
+    /// case the notification is retried in the next cycle.
+    fn take_backoff(&mut self) -> Option<Duration> {
+        self.backoff.take()
+    }
+
+    fn back_off(&mut self, retry_after: Duration) {
+        warn!(
+            "Rate limited. Retrying in {} seconds.",
+            retry_after.as_secs()
+        );
+        self.backoff = Some(retry_after);
+    }
+
+    /// Sends the daily digest if it is due, or starts or ends holding notifications for quiet
+    /// hours, sending the held notification when they end. Returns how to deliver notifications
+    /// in the coming cycle.
+    fn before_cycle<'a>(
+        &mut self,
+        config: &Config,
+        stored_events: &HashSet<Event>,
+        router: &Router,
+        notified: &mut NotifiedIds,
+        digest: Option<&'a mut Digest>,
+    ) -> Delivery<'a> {
+        if let Some(digest) = digest {
+            // Keep the buffer if sending fails, so the digest is sent in the next cycle
+            match send_digest_if_due(digest, stored_events, router, notified) {
+                Ok(true) => self.record_notification(),
+                Ok(false) => {}
+                Err(NotifyError::RateLimited { retry_after }) => self.back_off(retry_after),
+                Err(error) => error!("Failed to send daily digest: {}", error),
+            }
+            return Delivery::Digest(digest);
+        }
+
+        let in_quiet_hours = config
+            .quiet_hours
+            .is_some_and(|quiet_hours| quiet_hours.contains(event::local_time_now()));
+        if in_quiet_hours {
+            if self.held_events.is_none() {
+                info!("Quiet hours started. Holding notifications until they end.");
+                self.held_events = Some(stored_events.clone());
+            }
+        } else if let Some(held) = &self.held_events {
+            // Keep holding if sending fails, so changes are notified about in the next batch
+            match send_held_notification(held, stored_events, config, router, notified) {
+                Ok(notified) => {
+                    if notified {
+                        self.record_notification();
+                    }
+                    self.held_events = None;
+                }
+                Err(NotifyError::RateLimited { retry_after }) => self.back_off(retry_after),
+                Err(error) => error!("Failed to send held notification: {}", error),
+            }
+        }
+
+        if self.held_events.is_some() {
+            Delivery::Hold
+        } else {
+            Delivery::Immediate
+        }
+    }
+
+    /// Records a notification sent outside of a regular cycle.
+    fn record_notification(&mut self) {
+        self.metrics.record_notification();
+        if let Some(heartbeat) = &mut self.heartbeat {
+            heartbeat.reset();
+        }
+    }
+
+    /// Handles the result of a cycle, exiting if it failed in a way that cannot be recovered from
+    /// in the next cycle.
+    fn after_cycle(
+        &mut self,
+        result: Result<CycleOutcome, CycleError>,
+        stored_events: &HashSet<Event>,
+        router: &Router,
+        strings: &Strings,
+    ) {
+        let fetch_failed = matches!(result, Err(CycleError::Fetch(_)));
+        if let Some(fetch_alert) = &mut self.fetch_alert {
+            if fetch_failed {
+                if let Some(failing_for) = fetch_alert.record_failure() {
+                    send_fetch_alert(failing_for, strings, router.default_notifier());
+                }
+            } else if let Some(failed_for) = fetch_alert.record_success() {
+                send_fetch_recovery(failed_for, strings, router.default_notifier());
+            }
+        }
+
+        match result {
+            Ok(outcome) => {
+                self.metrics.record_fetch(false);
+                self.metrics.record_new_events(outcome.added);
+                self.metrics.set_events_tracked(stored_events.len());
+                if outcome.notified {
+                    self.metrics.record_notification();
+                }
+                if let Some(shared_events) = &self.shared_events {
+                    *shared_events.write().unwrap() = stored_events.clone();
+                }
+                if let Some(heartbeat) = &mut self.heartbeat {
+                    if outcome.notified {
+                        heartbeat.reset();
+                    } else if heartbeat.is_due() {
+                        send_heartbeat(stored_events.len(), strings, router.default_notifier());
+                        heartbeat.reset();
+                    }
+                }
+            }
+            Err(CycleError::Fetch(FetchError::Request(error))) => {
+                self.metrics.record_fetch(true);
+                warn!("Failed to fetch events: {}", error);
+            }
+            // The markup of the site sometimes changes temporarily, so keep running unless strict
+            Err(CycleError::Fetch(FetchError::Parse(error))) if !self.strict => {
+                self.metrics.record_fetch(true);
+                error!("Failed to parse events: {}", error);
+            }
+            // Keep the local list of events as is if sending fails, so the same changes are
+            // notified about in the next cycle.
+            Err(CycleError::Notify(NotifyError::RateLimited { retry_after })) => {
+                self.metrics.record_fetch(false);
+                self.back_off(retry_after);
+            }
+            Err(CycleError::Notify(error)) => {
+                self.metrics.record_fetch(false);
+                error!("Failed to send notification: {}", error);
+            }
+            Err(error) => exit(error.to_string().as_str()),
+        }
+    }
+}
+
+/// Gets the delay before the next cycle, which is `interval` randomly varied by up to
+/// `jitter_percent` percent in either direction.
+fn jittered_interval(interval: Duration, jitter_percent: u8, rng: &mut impl Rng) -> Duration {
+    if jitter_percent == 0 {
+        return interval;
+    }
+    let jitter = f64::from(jitter_percent) / 100.0;
+    interval.mul_f64(rng.gen_range(1.0 - jitter..=1.0 + jitter))
+}
+
+/// Gets the delay from `now` until the next multiple of `interval` since the Unix epoch, e.g. until
+/// the next :00, :05, :10, and so on for an interval of 5 minutes. Intervals that do not divide an
+/// hour are still aligned, but not to the same minutes every hour.
+fn until_aligned(now: DateTime<Utc>, interval: Duration) -> Duration {
+    let interval = interval.as_millis().max(1);
+    let now = now.timestamp_millis().max(0) as u128;
+    Duration::from_millis((interval - now % interval) as u64)
+}
+
+/// Runs a single cycle of fetching events, comparing them to `stored_events`, notifying about any
+/// differences, and saving the fetched events to `store` and as the new `stored_events`.
+///
+/// How notifications are delivered is decided by `delivery`. The fetched events are saved as
+/// usual either way.
+///
+/// `stored_events` is left untouched if fetching or notifying fails.
+pub fn run_once(
+    clubs: &Clubs<EventFetcher>,
+    config: &Config,
+    store: &mut dyn Store,
+    stored_events: &mut HashSet<Event>,
+    router: &Router,
+    notified: &mut NotifiedIds,
+    delivery: Delivery,
+) -> Result<CycleOutcome, CycleError> {
+    let started = Instant::now();
+
+    info!("Fetching events...");
+
+    let events = clubs.fetch_all()?;
+    let fetched = events.len();
+
+    info!("Fetched events.");
+
+    let outcome = process_events(
+        events,
+        config,
+        store,
+        stored_events,
+        router,
+        notified,
+        delivery,
+    )?;
+    log_cycle_summary(fetched, &outcome, started.elapsed());
+    Ok(outcome)
+}
+
+/// Adds the stored events of each club that fetched no events, while there are stored events of
+/// it, to `events`, as if they were fetched again. Like a single club fetching no events, which
+/// skips the whole cycle, the site of the club is likely unavailable, and its stored events should
+/// not look removed.
+fn keep_events_of_empty_clubs(
+    mut events: HashSet<Event>,
+    stored_events: &HashSet<Event>,
+    config: &Config,
+) -> HashSet<Event> {
+    if config.clubs.is_empty() {
+        return events;
+    }
+
+    for name in config.base_urls().into_iter().map(club::name) {
+        let of_club = |event: &&Event| club::of(event) == Some(name.as_str());
+        if events.iter().any(|event| of_club(&event)) {
+            continue;
+        }
+        let kept: Vec<Event> = stored_events.iter().filter(of_club).cloned().collect();
+        if !kept.is_empty() {
+            warn!(
+                "Fetched no events of {}, while there are {} stored events of it. Keeping them \
+                 this cycle, since its site is likely unavailable.",
+                name,
+                kept.len()
+            );
+            events.extend(kept);
+        }
+    }
+    events
+}
+
+/// Logs a single line summarizing a successful cycle, which took `duration` and fetched
+/// `fetched` events, so activity is easy to find by grepping the logs for "cycle done".
+fn log_cycle_summary(fetched: usize, outcome: &CycleOutcome, duration: Duration) {
+    info!(
+        "cycle done: fetched={} new={} removed={} changed={} notified={} duration={}ms",
+        fetched,
+        outcome.added,
+        outcome.removed,
+        outcome.changed,
+        outcome.notified,
+        duration.as_millis()
+    );
+}
+
+/// How to deliver the notification about the changes found in a cycle of [`run_once`].
+pub enum Delivery<'a> {
+    /// Send a notification right away.
+    Immediate,
+    /// Send no notification, e.g. during quiet hours.
+    Hold,
+    /// Buffer the new events for the daily digest instead of sending a notification.
+    Digest(&'a mut Digest),
+}
+
+/// Compares fetched `events` to `stored_events` and handles the differences as in [`run_once`],
+/// for fetchers other than [`EventFetcher`].
+fn process_events(
+    events: HashSet<Event>,
+    config: &Config,
+    store: &mut dyn Store,
+    stored_events: &mut HashSet<Event>,
+    router: &Router,
+    notified: &mut NotifiedIds,
+    delivery: Delivery,
+) -> Result<CycleOutcome, CycleError> {
+    // Otherwise, a maintenance page would make all stored events look removed
+    if events.is_empty() && !stored_events.is_empty() && !config.allow_empty {
+        warn!(
+            "Fetched no events, while there are {} stored events. Skipping this cycle, since the \
+             site is likely unavailable.",
+            stored_events.len()
+        );
+        return Ok(CycleOutcome::default());
+    }
+    let events = if config.allow_empty {
+        events
+    } else {
+        keep_events_of_empty_clubs(events, stored_events, config)
+    };
+
+    let prune_before = config
+        .prune_past
+        .and_then(|keep| prune_cutoff(event::local_date_time_now(), keep));
+    let events = match prune_before {
+        Some(cutoff) => prune_past(events, cutoff),
+        None => events,
+    };
+
+    let events = mark_seen(events, stored_events, Utc::now());
+
+    export_events(&events, &config.export);
+
+    info!("Comparing to local list of events...");
+
+    let diff = Diff::between(stored_events, &events);
+
+    if diff.is_empty() {
+        info!("There are no new, removed, or changed events.");
+        store.save_diff(&events, &diff).map_err(CycleError::Save)?;
+        *stored_events = events;
+        return Ok(CycleOutcome::default());
+    }
+
+    if is_id_change(&diff, events.len(), config.id_change_threshold) {
+        error!(
+            "{} of the {} fetched events are new, so the site has likely changed how events are \
+             identified. Storing the events without notifying. Check that events are still \
+             fetched correctly.",
+            diff.added.len(),
+            events.len()
+        );
+        let outcome = CycleOutcome {
+            added: diff.added.len(),
+            removed: diff.removed.len(),
+            changed: diff.changed.len(),
+            notified: false,
+        };
+        store.save_diff(&events, &diff).map_err(CycleError::Save)?;
+        *stored_events = events;
+        return Ok(outcome);
+    }
+
+    let mut filtered_diff = notified.retain_unnotified(filter_diff(diff.clone(), &config.filter));
+    // Pruned events are only removed from the stored events, not from the club
+    if let Some(cutoff) = prune_before {
+        filtered_diff
+            .removed
+            .retain(|event| event.date_time >= cutoff);
+    }
+    if config.emit_ndjson {
+        emit_ndjson(&filtered_diff);
+    }
+    let mut outcome = CycleOutcome {
+        added: filtered_diff.added.len(),
+        removed: filtered_diff.removed.len(),
+        changed: filtered_diff.changed.len(),
+        notified: false,
+    };
+
+    // Events that are filtered out are still saved below, so they are not notified about later
+    if filtered_diff.is_empty() {
+        info!("All new, removed, and changed events were filtered out or already notified about.");
+    } else {
+        match delivery {
+            Delivery::Immediate => {
+                info!(
+                    "There are {} new, {} removed, and {} changed events. Sending notification...",
+                    outcome.added, outcome.removed, outcome.changed
+                );
+
+                send_capped(
+                    &filtered_diff,
+                    config.max_new_per_cycle,
+                    config.message.language.strings(),
+                    router,
+                )
+                .map_err(CycleError::Notify)?;
+                outcome.notified = true;
+
+                info!("Sent notification.");
+
+                if let Err(error) = notified.record(&filtered_diff, &events) {
+                    warn!("Failed to save notified event ids: {}", error);
+                }
+            }
+            Delivery::Hold => info!(
+                "There are {} new, {} removed, and {} changed events. Holding notification...",
+                outcome.added, outcome.removed, outcome.changed
+            ),
+            // Removed and changed events are not part of the digest
+            Delivery::Digest(digest) => {
+                info!(
+                    "There are {} new, {} removed, and {} changed events. Adding the new events \
+                     to the daily digest...",
+                    outcome.added, outcome.removed, outcome.changed
+                );
+                if let Err(error) = digest.add(&filtered_diff.added) {
+                    warn!("Failed to save daily digest: {}", error);
+                }
+            }
+        }
+    }
+
+    info!("Updating stored events...");
+
+    store.save_diff(&events, &diff).map_err(CycleError::Save)?;
+    *stored_events = events;
+
+    info!("Updated stored events.");
+
+    Ok(outcome)
+}
+
+/// Marks the fetched `events` as last seen `now`, and as first seen when they were first stored in
+/// `stored_events`, or `now` for new events.
+fn mark_seen(
+    events: HashSet<Event>,
+    stored_events: &HashSet<Event>,
+    now: DateTime<Utc>,
+) -> HashSet<Event> {
+    events
+        .into_iter()
+        .map(|mut event| {
+            event.first_seen = stored_events
+                .get(&event)
+                .map_or(now, |stored| stored.first_seen);
+            event.last_seen = now;
+            event
+        })
+        .collect()
+}
+
+/// Whether more than `threshold` percent of the `fetched` events are new in `diff`, while some
+/// events were known before, which more likely means that the ids of the events have changed than
+/// that that many events were added.
+fn is_id_change(diff: &Diff, fetched: usize, threshold: u8) -> bool {
+    let known_before = !diff.removed.is_empty() || diff.added.len() < fetched;
+    threshold > 0 && known_before && diff.added.len() * 100 > fetched * usize::from(threshold)
+}
+
+/// Writes `diff` to stdout as newline-delimited JSON and flushes it, so other tools get the
+/// changes as soon as they are found instead of when the buffer happens to fill up.
+fn emit_ndjson(diff: &Diff) {
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+    if let Err(error) = diff.write_ndjson(&mut stdout).and_then(|()| stdout.flush()) {
+        warn!("Failed to write changes as NDJSON: {}", error);
+    }
+}
+
+/// Gets the time before which events are pruned when past events are kept for `keep`, or `None`
+/// if `keep` is too long to subtract from `now`.
+fn prune_cutoff(now: DateTime<FixedOffset>, keep: Duration) -> Option<DateTime<FixedOffset>> {
+    chrono::Duration::from_std(keep)
+        .ok()
+        .and_then(|keep| now.checked_sub_signed(keep))
+}
+
+/// Drops the events that started before `cutoff`.
+fn prune_past(events: HashSet<Event>, cutoff: DateTime<FixedOffset>) -> HashSet<Event> {
+    let count = events.len();
+    let events: HashSet<_> = events
+        .into_iter()
+        .filter(|event| event.date_time >= cutoff)
+        .collect();
+    if events.len() < count {
+        info!("Pruned {} past events.", count - events.len());
+    }
+    events
+}
+
+/// The result of a successful cycle of [`run_once`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CycleOutcome {
+    pub added: usize,
+    pub removed: usize,
+    pub changed: usize,
+    /// Whether a notification was sent, i.e. whether any events were added, removed, or changed.
+    pub notified: bool,
+}
+
+impl Display for CycleOutcome {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} new, {} removed, and {} changed events",
+            self.added, self.removed, self.changed
+        )?;
+        if self.notified {
+            write!(f, " (notification sent)")
+        } else {
+            write!(f, " (no notification sent)")
+        }
+    }
+}
+
+/// An error representing a failed cycle of [`run_once`].
+#[derive(Debug)]
+pub enum CycleError {
+    Fetch(FetchError),
+    Notify(NotifyError),
+    Save(StoreError),
+}
+
+impl Display for CycleError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Fetch(error) => write!(f, "Failed to fetch events: {}", error),
+            Self::Notify(error) => write!(f, "Failed to send notification: {}", error),
+            Self::Save(error) => write!(f, "Failed to save events: {}", error),
+        }
+    }
+}
+
+impl From<FetchError> for CycleError {
+    fn from(error: FetchError) -> Self {
+        CycleError::Fetch(error)
+    }
+}
+
+/// Locks the files of the stored events of all clubs, if enabled, or exits if another instance
+/// holds any of the locks. The locks are held until the returned locks are dropped.
+fn lock_stores_or_exit(config: &Config) -> Vec<InstanceLock> {
+    if !config.lock {
+        return Vec::new();
+    }
+
+    let store_path = match &config.store {
+        StoreConfig::Json(path) | StoreConfig::Sqlite(path) => path.as_path(),
+    };
+    let club_paths = config.clubs.iter().map(ClubConfig::events_file);
+    std::iter::once(store_path)
+        .chain(club_paths)
+        .map(|path| InstanceLock::acquire(path).unwrap_or_else(|error| exit(&error.to_string())))
+        .collect()
+}
+
+fn open_store(config: &Config) -> Result<Box<dyn Store>, StoreError> {
+    let store: Box<dyn Store> = match &config.store {
+        StoreConfig::Json(path) => Box::new(JsonStore::new(path, config.events_backups)),
+        StoreConfig::Sqlite(path) => Box::new(SqliteStore::open(path)?),
+    };
+    if config.clubs.is_empty() {
+        return Ok(store);
+    }
+
+    let mut stores = vec![(club::name(config.fetch.base_url()), store)];
+    for other in &config.clubs {
+        let store = JsonStore::new(other.events_file(), config.events_backups);
+        stores.push((club::name(other.base_url()), Box::new(store)));
+    }
+    Ok(Box::new(ClubStore::new(stores)))
+}
+
+fn export_events(events: &HashSet<Event>, config: &ExportConfig) {
+    if let Some(path) = config.ical_file() {
+        match fs::write(path, event::export::to_ical(events)) {
+            Ok(()) => info!("Exported events as iCalendar to {:?}.", path),
+            Err(error) => warn!(
+                "Failed to export events as iCalendar to {:?}: {}",
+                path, error
+            ),
+        }
+    }
+
+    if let Some(path) = config.csv_file() {
+        match File::create(path).and_then(|file| event::export::to_csv(events, file)) {
+            Ok(()) => info!("Exported events as CSV to {:?}.", path),
+            Err(error) => warn!("Failed to export events as CSV to {:?}: {}", path, error),
+        }
+    }
+
+    if let Some(path) = config.rss_file() {
+        match fs::write(path, event::export::to_rss(events)) {
+            Ok(()) => info!("Exported events as RSS to {:?}.", path),
+            Err(error) => warn!("Failed to export events as RSS to {:?}: {}", path, error),
+        }
+    }
+
+    if let Some(path) = config.markdown_file() {
+        match fs::write(path, event::export::to_markdown(events)) {
+            Ok(()) => info!("Exported events as Markdown to {:?}.", path),
+            Err(error) => warn!(
+                "Failed to export events as Markdown to {:?}: {}",
+                path, error
+            ),
+        }
+    }
+}
+
+/// Registers a handler that requests reloading the config file when SIGHUP is received.
+#[cfg(unix)]
+fn watch_reload_signal() -> Option<Arc<AtomicBool>> {
+    let requested = Arc::new(AtomicBool::new(false));
+    match signal_hook::flag::register(signal_hook::consts::SIGHUP, Arc::clone(&requested)) {
+        Ok(_) => {
+            info!("Send SIGHUP to reload the config file.");
+            Some(requested)
+        }
+        Err(error) => {
+            warn!(
+                "Failed to handle SIGHUP, so the config file cannot be reloaded: {}",
+                error
+            );
+            None
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn watch_reload_signal() -> Option<Arc<AtomicBool>> {
+    None
+}
+
+fn exit(message: &str) -> ! {
+    error!("{}", message);
+    panic!("{}", message)
+}
+
+/// Creates a router that sends notifications through the configured notifier, and additionally
+/// through a notifier for each configured Pushover route. Sending is retried on transient errors.
+fn create_router(config: &NotifierConfig, dry_run: bool) -> Result<Router, NotifyError> {
+    let retrying = |notifier| -> Box<dyn Notifier> {
+        Box::new(RetryingNotifier::new(notifier, RetryPolicy::default()))
+    };
+
+    let mut router = Router::new(retrying(create_notifier(config, dry_run)?));
+    if let NotifierConfig::Pushover(pushover) = config {
+        for route in &pushover.routes {
+            let notifier: Box<dyn Notifier> = if dry_run {
+                Box::new(DryRunNotifier)
+            } else {
+                Box::new(create_pushover_notifier(pushover, &route.group_key))
+            };
+            router = router.route(route.includes.clone(), retrying(notifier));
+        }
+    }
+    Ok(router)
+}
+
+fn create_pushover_notifier(config: &PushoverConfig, group_key: &PushoverKey) -> PushoverNotifier {
+    let mut notifier = PushoverNotifier::new(config.api_key.clone(), group_key.clone());
+    if let Some(priority) = config.priority {
+        notifier = notifier.priority(priority);
+    }
+    if let Some(sound) = &config.sound {
+        notifier = notifier.sound(sound);
+    }
+    if let Some(device) = &config.device {
+        notifier = notifier.device(device);
+    }
+    notifier
+}
+
+fn create_notifier(
+    config: &NotifierConfig,
+    dry_run: bool,
+) -> Result<Box<dyn Notifier>, NotifyError> {
+    if dry_run {
+        return Ok(Box::new(DryRunNotifier));
+    }
+
+    let notifier: Box<dyn Notifier> = match config {
+        NotifierConfig::Pushover(pushover) => {
+            Box::new(create_pushover_notifier(pushover, &pushover.group_key))
+        }
+        NotifierConfig::Telegram(telegram) => Box::new(TelegramNotifier::new(
+            telegram.token.clone(),
+            telegram.chat_id.clone(),
+        )),
+        NotifierConfig::Discord(discord) => {
+            Box::new(DiscordNotifier::new(discord.webhook_url.clone()))
+        }
+        NotifierConfig::Email(email) => Box::new(EmailNotifier::new(
+            &email.host,
+            email.port,
+            Credentials::new(email.username.clone(), email.password.clone()),
+            email.from.clone(),
+            email.to.clone(),
+        )?),
+        NotifierConfig::Webhook(webhook) => Box::new(webhook.headers.iter().fold(
+            GenericWebhookNotifier::new(webhook.url.clone()),
+            |notifier, header| notifier.header(header.name.clone(), header.value.clone()),
+        )),
+    };
+    Ok(notifier)
+}
+
+/// Removes the events that do not match the configured keywords, date range, weekdays, minimum
+/// advance notice, or levels from `diff`. Changed events are kept if either the old or the new
+/// version matches.
+fn filter_diff<'a>(diff: Diff<'a>, config: &FilterConfig) -> Diff<'a> {
+    let (includes, excludes) = (config.includes(), config.excludes());
+    let (from, until) = (config.from(), config.until());
+    let weekdays = config.weekdays();
+    let (now, min_advance) = (event::local_date_time_now(), config.min_advance());
+    let (levels, level_unknown_ok) = (config.levels(), config.level_unknown_ok());
+    let apply = |events| {
+        let events = filter::apply_filters(events, includes, excludes);
+        let events = filter::apply_date_range(events, from, until);
+        let events = filter::apply_levels(events, levels, level_unknown_ok);
+        filter::apply_min_advance(filter::apply_weekdays(events, weekdays), now, min_advance)
+    };
+    let is_match = |event| {
+        filter::is_match(event, includes, excludes)
+            && filter::is_in_range(event, from, until)
+            && filter::is_on_weekdays(event, weekdays)
+            && filter::is_in_advance(event, now, min_advance)
+            && filter::is_at_level(event, levels, level_unknown_ok)
+    };
+
+    Diff {
+        added: apply(diff.added),
+        removed: apply(diff.removed),
+        changed: diff
+            .changed
+            .into_iter()
+            .filter(|change| is_match(change.old) || is_match(change.new))
+            .collect(),
+    }
+}
+
+fn send_heartbeat(tracked_events: usize, strings: &Strings, notifier: &dyn Notifier) {
+    info!("Sending heartbeat notification...");
+
+    let message = format!("{} {}", tracked_events, strings.events_tracked);
+    match notifier.send_low_priority(strings.heartbeat_title, &message) {
+        Ok(()) => info!("Sent heartbeat notification."),
+        Err(error) => error!("Failed to send heartbeat notification: {}", error),
+    }
+}
+
+fn send_fetch_alert(failing_for: Duration, strings: &Strings, notifier: &dyn Notifier) {
+    warn!("Sending alert about failing fetches...");
+
+    let message = format!(
+        "{} {} {}.",
+        strings.fetch_failing,
+        failing_for.as_secs() / 60,
+        strings.minutes
+    );
+    match notifier.send(strings.fetch_failing_title, &message) {
+        Ok(()) => info!("Sent alert about failing fetches."),
+        Err(error) => error!("Failed to send alert about failing fetches: {}", error),
+    }
+}
+
+fn send_fetch_recovery(failed_for: Duration, strings: &Strings, notifier: &dyn Notifier) {
+    info!("Sending notice about fetches working again...");
+
+    let message = format!(
+        "{} {} {}.",
+        strings.fetch_recovered,
+        failed_for.as_secs() / 60,
+        strings.minutes
+    );
+    match notifier.send(strings.fetch_recovered_title, &message) {
+        Ok(()) => info!("Sent notice about fetches working again."),
+        Err(error) => error!(
+            "Failed to send notice about fetches working again: {}",
+            error
+        ),
+    }
+}
+
+/// Sends a single notification about everything that changed between `held_events`, the stored
+/// events from when notifications started being held, and `stored_events`. Returns whether a
+/// notification was sent.
+fn send_held_notification(
+    held_events: &HashSet<Event>,
+    stored_events: &HashSet<Event>,
+    config: &Config,
+    router: &Router,
+    notified: &mut NotifiedIds,
+) -> Result<bool, NotifyError> {
+    let diff = Diff::between(held_events, stored_events);
+    let diff = notified.retain_unnotified(filter_diff(diff, &config.filter));
+    if diff.is_empty() {
+        info!("Quiet hours ended. There were no new, removed, or changed events.");
+        return Ok(false);
+    }
+
+    info!(
+        "Quiet hours ended. Sending held notification about {} new, {} removed, and {} changed \
+         events...",
+        diff.added.len(),
+        diff.removed.len(),
+        diff.changed.len()
+    );
+    send_capped(
+        &diff,
+        config.max_new_per_cycle,
+        config.message.language.strings(),
+        router,
+    )?;
+    info!("Sent held notification.");
+
+    if let Err(error) = notified.record(&diff, stored_events) {
+        warn!("Failed to save notified event ids: {}", error);
+    }
+
+    Ok(true)
+}
+
+/// Sends a notification about `diff` through `router`, or a single summary to the default notifier
+/// instead if there are more than `max_new` new events, since that many at once more likely comes
+/// from a change of the site, e.g. of its event ids, than from actual new events. The summary is
+/// written with `strings`.
+fn send_capped(
+    diff: &Diff,
+    max_new: Option<usize>,
+    strings: &Strings,
+    router: &Router,
+) -> Result<(), NotifyError> {
+    match max_new {
+        Some(max_new) if diff.added.len() > max_new => {
+            warn!(
+                "There are {} new events, which is more than the maximum of {} per cycle. The \
+                 site may have changed. Sending a summary instead of the events...",
+                diff.added.len(),
+                max_new
+            );
+            let message = format!("{} {}", diff.added.len(), strings.too_many_new);
+            router
+                .default_notifier()
+                .send(strings.too_many_new_title, &message)
+        }
+        _ => router.send(diff),
+    }
+}
+
+/// Sends the daily digest about the new events buffered in `digest` that are still in
+/// `stored_events` if it is due, and empties the buffer. Returns whether a notification was sent.
+fn send_digest_if_due(
+    digest: &mut Digest,
+    stored_events: &HashSet<Event>,
+    router: &Router,
+    notified: &mut NotifiedIds,
+) -> Result<bool, NotifyError> {
+    let now = event::local_now();
+    if !digest.is_due(now) {
+        return Ok(false);
+    }
+
+    let diff = Diff {
+        added: digest.events_in(stored_events),
+        removed: Vec::new(),
+        changed: Vec::new(),
+    };
+    let sent = !diff.is_empty();
+    if sent {
+        info!(
+            "Sending daily digest about {} new events...",
+            diff.added.len()
+        );
+        router.send_digest(&diff)?;
+        info!("Sent daily digest.");
+
+        if let Err(error) = notified.record(&diff, stored_events) {
+            warn!("Failed to save notified event ids: {}", error);
+        }
+    } else {
+        info!("There are no new events for the daily digest.");
+    }
+
+    if let Err(error) = digest.clear(now.date()) {
+        warn!("Failed to save daily digest: {}", error);
+    }
+    Ok(sent)
+}
+
+fn send_notification(
+    diff: &Diff,
+    notifier: &dyn Notifier,
+    format: &MessageFormat,
+) -> Result<(), NotifyError> {
+    if let Some(result) = notifier.send_diff(diff) {
+        return result;
+    }
+    send_sections(
+        message::title(diff, format),
+        &message::sections(diff, format),
+        notifier,
+    )
+}
+
+fn send_digest_notification(
+    diff: &Diff,
+    notifier: &dyn Notifier,
+    format: &MessageFormat,
+) -> Result<(), NotifyError> {
+    // Removed and changed events are not part of the digest
+    let added = Diff {
+        added: diff.added.clone(),
+        removed: Vec::new(),
+        changed: Vec::new(),
+    };
+    if let Some(result) = notifier.send_diff(&added) {
+        return result;
+    }
+    let sections = message::digest_sections(&diff.added, format);
+    send_sections(message::digest_title(format), &sections, notifier)
+}
+
+fn send_sections(
+    title: &str,
+    sections: &[message::Section],
+    notifier: &dyn Notifier,
+) -> Result<(), NotifyError> {
+    let messages = message::render(sections, notifier.max_message_length());
+
+    if messages.len() == 1 {
+        return notifier.send(title, &messages[0]);
+    }
+    for (i, message) in messages.iter().enumerate() {
+        notifier.send(
+            &format!("{} ({}/{})", title, i + 1, messages.len()),
+            message,
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::{
+        event::parse::ParseError,
+        strings::Language,
+        test_util::{self, parser, MockResponse, MockServer},
+    };
+
+    const EVENT_PAGE: &str = r#"<table><tbody>
+        <tr class="infinite-item" id="event1">
+            <td class="liste_wide min992">Tennisskole<br>tir 5. maj 2021<br>18:00 - 19:00</td>
+            <td class="liste_wide min992 holdinfo">Hold 1</td>
+        </tr>
+    </tbody></table>"#;
+
+    fn config(directory: &TempDir) -> Config {
+        Config {
+            config_file: None,
+            log: LogConfig {
+                level: flexi_logger::Level::Info,
+                directory: directory.path().join("logs"),
+                format: LogFormat::Text,
+                rotation: LogRotation::Daily,
+                keep: 7,
+                to_file: true,
+            },
+            notifier: NotifierConfig::Discord(DiscordConfig {
+                webhook_url: String::from("http://localhost/"),
+            }),
+            fetch: FetchConfig {
+                retry: RetryPolicy::new(1, Duration::from_millis(1), 2.0),
+                timeout: Duration::from_secs(5),
+                base_url: String::from("http://localhost"),
+                page_delay: Duration::from_millis(0),
+                selectors: Selectors::default(),
+                proxy: None,
+                warm_up_url: None,
+            },
+            export: ExportConfig {
+                ical_file: None,
+                csv_file: None,
+                rss_file: None,
+                markdown_file: None,
+            },
+            filter: FilterConfig::default(),
+            events_file: directory.path().join("events.json"),
+            clubs: Vec::new(),
+            events_backups: 1,
+            prune_past: None,
+            store: StoreConfig::Json(directory.path().join("events.json")),
+            lock: true,
+            fetch_interval: Duration::from_secs(120),
+            interval_jitter: 0,
+            align_schedule: false,
+            heartbeat: None,
+            error_alert_after: 0,
+            max_new_per_cycle: None,
+            id_change_threshold: 0,
+            quiet_hours: None,
+            digest_at: None,
+            message: MessageFormat::default(),
+            http_listen: None,
+            metrics_listen: None,
+            notify_require_all: false,
+            dry_run: true,
+            once: true,
+            from_file: None,
+            test_notification: false,
+            validate_config: false,
+            strict: false,
+            allow_empty: false,
+            emit_ndjson: false,
+            notify_on_first_run: false,
+        }
+    }
+
+    fn clubs(server: &MockServer) -> Clubs<EventFetcher> {
+        let fetcher = EventFetcher::builder()
+            .parser(parser())
+            .retry(RetryPolicy::new(1, Duration::from_millis(1), 2.0))
+            .base_url(server.url())
+            .page_delay(Duration::from_millis(0))
+            .build()
+            .unwrap();
+        Clubs::new(vec![(club::name(server.url()), fetcher)])
+    }
+
+    #[test]
+    fn run_once_notifies_and_saves_new_events() {
+        let directory = TempDir::new().unwrap();
+        let config = config(&directory);
+        let server = MockServer::start(vec![
+            MockResponse::new(200, EVENT_PAGE),
+            MockResponse::new(200, EVENT_PAGE),
+        ]);
+        let mut store = JsonStore::new(config.events_file(), 1);
+        let mut stored_events = HashSet::new();
+
+        let outcome = run_once(
+            &clubs(&server),
+            &config,
+            &mut store,
+            &mut stored_events,
+            &Router::new(Box::new(DryRunNotifier)),
+            &mut NotifiedIds::empty(directory.path().join("notified.json")),
+            Delivery::Immediate,
+        )
+        .unwrap();
+
+        assert_eq!(
+            outcome,
+            CycleOutcome {
+                added: 1,
+                removed: 0,
+                changed: 0,
+                notified: true,
+            }
+        );
+        assert_eq!(stored_events.len(), 1);
+        assert_eq!(
+            event::deserialize_events(config.events_file()).unwrap(),
+            stored_events
+        );
+    }
+
+    #[test]
+    fn run_once_saves_filtered_out_events_without_notifying() {
+        let directory = TempDir::new().unwrap();
+        let mut config = config(&directory);
+        config.filter.excludes = vec![String::from("tennis")];
+        let server = MockServer::start(vec![
+            MockResponse::new(200, EVENT_PAGE),
+            MockResponse::new(200, EVENT_PAGE),
+        ]);
+        let mut store = JsonStore::new(config.events_file(), 1);
+        let mut stored_events = HashSet::new();
+
+        let outcome = run_once(
+            &clubs(&server),
+            &config,
+            &mut store,
+            &mut stored_events,
+            &Router::new(Box::new(DryRunNotifier)),
+            &mut NotifiedIds::empty(directory.path().join("notified.json")),
+            Delivery::Immediate,
+        )
+        .unwrap();
+
+        assert_eq!(outcome, CycleOutcome::default());
+        assert_eq!(stored_events.len(), 1);
+        assert!(config.events_file().exists());
+    }
+
+    #[test]
+    fn process_events_prunes_past_events_without_notifying() {
+        let directory = TempDir::new().unwrap();
+        let mut config = config(&directory);
+        config.prune_past = Some(Duration::from_secs(24 * 3600));
+        let now = event::local_date_time_now();
+        let event = |id: &str, days: i64| {
+            let mut event = Event::new();
+            event.id = id.to_string();
+            event.date_time = now + chrono::Duration::days(days);
+            event
+        };
+        let fetched = || -> HashSet<_> {
+            vec![event("past", -3), event("today", 0), event("future", 3)]
+                .into_iter()
+                .collect()
+        };
+        let mut store = JsonStore::new(config.events_file(), 1);
+        let mut stored_events: HashSet<_> = vec![event("past", -3), event("future", 3)]
+            .into_iter()
+            .collect();
+        let router = Router::new(Box::new(DryRunNotifier));
+        let mut notified = NotifiedIds::empty(directory.path().join("notified.json"));
+
+        let outcome = process_events(
+            fetched(),
+            &config,
+            &mut store,
+            &mut stored_events,
+            &router,
+            &mut notified,
+            Delivery::Immediate,
+        )
+        .unwrap();
+
+        assert_eq!(outcome.removed, 0);
+        assert_eq!(outcome.added, 1);
+        let mut ids: Vec<_> = stored_events
+            .iter()
+            .map(|event| event.id.as_str())
+            .collect();
+        ids.sort_unstable();
+        assert_eq!(ids, vec!["future", "today"]);
+
+        // The pruned event is still listed, but is not new
+        let outcome = process_events(
+            fetched(),
+            &config,
+            &mut store,
+            &mut stored_events,
+            &router,
+            &mut notified,
+            Delivery::Immediate,
+        )
+        .unwrap();
+        assert_eq!(outcome, CycleOutcome::default());
+        assert_eq!(
+            event::deserialize_events(config.events_file()).unwrap(),
+            stored_events
+        );
+    }
+
+    #[test]
+    fn run_once_stores_events_without_notifying_when_ids_change() {
+        let directory = TempDir::new().unwrap();
+        let mut config = config(&directory);
+        config.id_change_threshold = 90;
+        let server = MockServer::start(vec![
+            MockResponse::new(200, EVENT_PAGE),
+            MockResponse::new(200, EVENT_PAGE),
+        ]);
+        let mut store = JsonStore::new(config.events_file(), 1);
+        let mut stored_events = test_util::events(&["old-event1"]);
+
+        let outcome = run_once(
+            &clubs(&server),
+            &config,
+            &mut store,
+            &mut stored_events,
+            &Router::new(Box::new(DryRunNotifier)),
+            &mut NotifiedIds::empty(directory.path().join("notified.json")),
+            Delivery::Immediate,
+        )
+        .unwrap();
+
+        assert!(!outcome.notified);
+        assert_eq!(stored_events.iter().next().unwrap().id, "event1");
+        assert_eq!(
+            event::deserialize_events(config.events_file()).unwrap(),
+            stored_events
+        );
+    }
+
+    #[test]
+    fn mark_seen_keeps_first_seen_of_stored_events() {
+        let stored_events = test_util::events(&["event1"]);
+        let first_seen = stored_events.iter().next().unwrap().first_seen;
+        let now = first_seen + chrono::Duration::hours(1);
+
+        let fetched = test_util::events(&["event1", "event2"]);
+        let events = mark_seen(fetched, &stored_events, now);
+
+        let known = events.get(&test_util::event("event1")).unwrap();
+        assert_eq!((known.first_seen, known.last_seen), (first_seen, now));
+        let new = events.get(&test_util::event("event2")).unwrap();
+        assert_eq!((new.first_seen, new.last_seen), (now, now));
+    }
+
+    #[test]
+    fn is_id_change_needs_known_events_and_threshold() {
+        let old = test_util::events(&["1"]);
+        let new: HashSet<Event> = (2..12).map(|i| test_util::event(&i.to_string())).collect();
+        let mostly_new: HashSet<Event> =
+            (1..11).map(|i| test_util::event(&i.to_string())).collect();
+
+        assert!(is_id_change(&Diff::between(&old, &new), new.len(), 90));
+        // 9 of 10 new events is not more than 90%
+        assert!(!is_id_change(
+            &Diff::between(&old, &mostly_new),
+            mostly_new.len(),
+            90
+        ));
+        assert!(!is_id_change(
+            &Diff::between(&HashSet::new(), &new),
+            new.len(),
+            90
+        ));
+        assert!(!is_id_change(&Diff::between(&old, &new), new.len(), 0));
+    }
+
+    #[test]
+    fn run_once_skips_empty_fetches_unless_allowed() {
+        let directory = TempDir::new().unwrap();
+        let mut config = config(&directory);
+        let server = MockServer::start(vec![
+            MockResponse::new(200, "<table><tbody></tbody></table>"),
+            MockResponse::new(200, "<table><tbody></tbody></table>"),
+        ]);
+        let mut store = JsonStore::new(config.events_file(), 1);
+        let mut stored_events = test_util::events(&["event1"]);
+        let router = Router::new(Box::new(DryRunNotifier));
+        let mut notified = NotifiedIds::empty(directory.path().join("notified.json"));
+
+        let outcome = run_once(
+            &clubs(&server),
+            &config,
+            &mut store,
+            &mut stored_events,
+            &router,
+            &mut notified,
+            Delivery::Immediate,
+        )
+        .unwrap();
+
+        assert_eq!(outcome, CycleOutcome::default());
+        assert_eq!(stored_events.len(), 1);
+        assert!(!config.events_file().exists());
+
+        config.allow_empty = true;
+        let outcome = run_once(
+            &clubs(&server),
+            &config,
+            &mut store,
+            &mut stored_events,
+            &router,
+            &mut notified,
+            Delivery::Immediate,
+        )
+        .unwrap();
+
+        assert_eq!(outcome.removed, 1);
+        assert!(stored_events.is_empty());
+    }
+
+    #[test]
+    fn process_events_keeps_stored_events_of_clubs_that_fetched_none() {
+        let directory = TempDir::new().unwrap();
+        let mut config = config(&directory);
+        config.clubs.push(ClubConfig {
+            base_url: String::from("http://other.example"),
+            events_file: directory.path().join("other.json"),
+        });
+        let first = club::namespaced("localhost", test_util::event("event1"));
+        let other = club::namespaced("other.example", test_util::event("event2"));
+        let mut stored_events: HashSet<Event> = vec![first.clone(), other].into_iter().collect();
+        let events = vec![first].into_iter().collect();
+
+        let outcome = process_events(
+            events,
+            &config,
+            &mut JsonStore::new(config.events_file(), 1),
+            &mut stored_events,
+            &Router::new(Box::new(DryRunNotifier)),
+            &mut NotifiedIds::empty(directory.path().join("notified.json")),
+            Delivery::Immediate,
+        )
+        .unwrap();
+
+        assert_eq!(outcome.removed, 0);
+        assert_eq!(stored_events.len(), 2);
+    }
+
+    #[test]
+    fn run_once_keeps_stored_events_when_fetching_fails() {
+        let directory = TempDir::new().unwrap();
+        let config = config(&directory);
+        let server = MockServer::start(vec![MockResponse::new(500, "")]);
+        let mut store = JsonStore::new(config.events_file(), 1);
+        let mut stored_events = HashSet::new();
+
+        let result = run_once(
+            &clubs(&server),
+            &config,
+            &mut store,
+            &mut stored_events,
+            &Router::new(Box::new(DryRunNotifier)),
+            &mut NotifiedIds::empty(directory.path().join("notified.json")),
+            Delivery::Immediate,
+        );
+
+        assert!(matches!(result, Err(CycleError::Fetch(_))));
+        assert!(stored_events.is_empty());
+        assert!(!config.events_file().exists());
+    }
+
+    #[test]
+    fn run_once_saves_events_without_notifying_when_holding_notifications() {
+        let directory = TempDir::new().unwrap();
+        let config = config(&directory);
+        let server = MockServer::start(vec![
+            MockResponse::new(200, EVENT_PAGE),
+            MockResponse::new(200, EVENT_PAGE),
+        ]);
+        // Any request to the notification server would fail, so nothing must be sent
+        let notification_server = MockServer::start(vec![MockResponse::new(500, "")]);
+        let notifier = create_notifier(
+            &NotifierConfig::Discord(DiscordConfig {
+                webhook_url: notification_server.url().to_string(),
+            }),
+            false,
+        )
+        .unwrap();
+        let mut store = JsonStore::new(config.events_file(), 1);
+        let mut stored_events = HashSet::new();
+
+        let outcome = run_once(
+            &clubs(&server),
+            &config,
+            &mut store,
+            &mut stored_events,
+            &Router::new(notifier),
+            &mut NotifiedIds::empty(directory.path().join("notified.json")),
+            Delivery::Hold,
+        )
+        .unwrap();
+
+        assert!(!outcome.notified);
+        assert_eq!(outcome.added, 1);
+        assert_eq!(stored_events.len(), 1);
+        assert!(config.events_file().exists());
+    }
+
+    #[test]
+    fn run_once_does_not_notify_again_after_losing_stored_events() {
+        let directory = TempDir::new().unwrap();
+        let config = config(&directory);
+        let server = MockServer::start(vec![
+            MockResponse::new(200, EVENT_PAGE),
+            MockResponse::new(200, EVENT_PAGE),
+            MockResponse::new(200, EVENT_PAGE),
+            MockResponse::new(200, EVENT_PAGE),
+        ]);
+        let router = Router::new(Box::new(DryRunNotifier));
+        let mut store = JsonStore::new(config.events_file(), 1);
+
+        let mut notified = NotifiedIds::load(config.notified_file()).unwrap();
+        let outcome = run_once(
+            &clubs(&server),
+            &config,
+            &mut store,
+            &mut HashSet::new(),
+            &router,
+            &mut notified,
+            Delivery::Immediate,
+        )
+        .unwrap();
+        assert!(outcome.notified);
+
+        // Restart without the stored events
+        fs::remove_file(config.events_file()).unwrap();
+        let mut notified = NotifiedIds::load(config.notified_file()).unwrap();
+        let outcome = run_once(
+            &clubs(&server),
+            &config,
+            &mut store,
+            &mut HashSet::new(),
+            &router,
+            &mut notified,
+            Delivery::Immediate,
+        )
+        .unwrap();
+
+        assert!(!outcome.notified);
+        assert!(config.events_file().exists());
+    }
+
+    #[test]
+    fn run_once_buffers_new_events_for_digest_without_notifying() {
+        let directory = TempDir::new().unwrap();
+        let config = config(&directory);
+        let server = MockServer::start(vec![
+            MockResponse::new(200, EVENT_PAGE),
+            MockResponse::new(200, EVENT_PAGE),
+        ]);
+        let at = NaiveTime::from_hms(18, 0, 0);
+        let mut digest = Digest::load(config.digest_file(), at).unwrap();
+        let mut store = JsonStore::new(config.events_file(), 1);
+        let mut stored_events = HashSet::new();
+
+        let outcome = run_once(
+            &clubs(&server),
+            &config,
+            &mut store,
+            &mut stored_events,
+            &Router::new(Box::new(DryRunNotifier)),
+            &mut NotifiedIds::empty(directory.path().join("notified.json")),
+            Delivery::Digest(&mut digest),
+        )
+        .unwrap();
+
+        assert!(!outcome.notified);
+        assert_eq!(stored_events.len(), 1);
+        // The buffer is persisted, so it survives a restart
+        let digest = Digest::load(config.digest_file(), at).unwrap();
+        assert_eq!(digest.events_in(&stored_events).len(), 1);
+    }
+
+    #[test]
+    fn load_stored_events_notifies_about_all_events_on_first_run() {
+        let directory = TempDir::new().unwrap();
+        let mut config = config(&directory);
+        config.notify_on_first_run = true;
+        let server = MockServer::start(vec![
+            MockResponse::new(200, EVENT_PAGE),
+            MockResponse::new(200, EVENT_PAGE),
+        ]);
+        let mut store = JsonStore::new(config.events_file(), 1);
+
+        let mut stored_events = load_stored_events(&mut store, &config, || {
+            panic!("Fetched the baseline events")
+        });
+        assert!(stored_events.is_empty());
+
+        let outcome = run_once(
+            &clubs(&server),
+            &config,
+            &mut store,
+            &mut stored_events,
+            &Router::new(Box::new(DryRunNotifier)),
+            &mut NotifiedIds::empty(directory.path().join("notified.json")),
+            Delivery::Immediate,
+        )
+        .unwrap();
+        assert_eq!(outcome.added, 1);
+        assert!(outcome.notified);
+    }
+
+    #[test]
+    fn send_capped_sends_summary_instead_of_too_many_new_events() {
+        let server = MockServer::start(vec![MockResponse::new(200, "")]);
+        let router = Router::new(Box::new(GenericWebhookNotifier::new(server.url())));
+        let events = test_util::events(&["event0", "event1", "event2"]);
+        let stored = HashSet::new();
+        let diff = Diff::between(&stored, &events);
+
+        assert!(send_capped(&diff, Some(2), Language::English.strings(), &router).is_ok());
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        let body = &requests[0][requests[0].find("\r\n\r\n").unwrap() + 4..];
+        let json: serde_json::Value = serde_json::from_str(body).unwrap();
+        assert_eq!(
+            json["message"],
+            "3 new events detected, suppressing individual alerts."
+        );
+    }
+
+    #[test]
+    fn send_capped_sends_events_within_maximum() {
+        let server = MockServer::start(vec![MockResponse::new(200, "")]);
+        let router = Router::new(Box::new(GenericWebhookNotifier::new(server.url())));
+        let events = test_util::events(&["event1"]);
+        let stored = HashSet::new();
+        let diff = Diff::between(&stored, &events);
+
+        assert!(send_capped(&diff, Some(1), Language::English.strings(), &router).is_ok());
+
+        let request = &server.requests()[0];
+        let body = &request[request.find("\r\n\r\n").unwrap() + 4..];
+        let json: serde_json::Value = serde_json::from_str(body).unwrap();
+        assert_eq!(json["added"][0]["id"], "event1");
+    }
+
+    #[test]
+    fn validate_config_counts_failed_checks() {
+        let directory = TempDir::new().unwrap();
+        let mut config = config(&directory);
+        assert_eq!(validate_config(&config), Ok(()));
+
+        config.fetch.selectors.event = String::from("tr[");
+        config.fetch.proxy = Some(String::from("not a proxy"));
+        assert_eq!(validate_config(&config), Err(2));
+    }
+
+    #[test]
+    fn check_directories_skips_log_directory_without_log_files() {
+        let directory = TempDir::new().unwrap();
+        let mut config = config(&directory);
+        let file = directory.path().join("file");
+        fs::write(&file, "").unwrap();
+        config.log.directory = file.join("logs");
+        assert!(check_directories(&config).is_err());
+
+        config.log.to_file = false;
+        assert!(check_directories(&config).is_ok());
+    }
+
+    #[test]
+    fn send_test_notification_succeeds_in_dry_run() {
+        let directory = TempDir::new().unwrap();
+
+        assert!(send_test_notification(&config(&directory)).is_ok());
+    }
+
+    #[test]
+    fn print_events_from_file_fails_when_no_events_parse() {
+        let directory = TempDir::new().unwrap();
+        let path = directory.path().join("liste.html");
+
+        fs::write(&path, EVENT_PAGE).unwrap();
+        assert!(print_events_from_file(&path, &parser()).is_ok());
+
+        fs::write(&path, EVENT_PAGE.replace("tir 5. maj 2021", "")).unwrap();
+        let result = print_events_from_file(&path, &parser());
+        assert!(matches!(
+            result,
+            Err(CycleError::Fetch(FetchError::Parse(_)))
+        ));
+    }
+
+    fn parse_error() -> CycleError {
+        CycleError::Fetch(FetchError::Parse(ParseError::from(
+            "No 'id' attribute in event HTML.",
+        )))
+    }
+
+    #[test]
+    fn after_cycle_skips_parse_errors() {
+        let directory = TempDir::new().unwrap();
+        let config = config(&directory);
+        let mut state = LoopState::start(&config, &HashSet::new());
+        let router = Router::new(Box::new(DryRunNotifier));
+
+        state.after_cycle(
+            Err(parse_error()),
+            &HashSet::new(),
+            &router,
+            Language::English.strings(),
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn after_cycle_exits_on_parse_errors_when_strict() {
+        let directory = TempDir::new().unwrap();
+        let mut config = config(&directory);
+        config.strict = true;
+        let mut state = LoopState::start(&config, &HashSet::new());
+        let router = Router::new(Box::new(DryRunNotifier));
+
+        state.after_cycle(
+            Err(parse_error()),
+            &HashSet::new(),
+            &router,
+            Language::English.strings(),
+        );
+    }
+
+    #[test]
+    fn jittered_interval_stays_within_jitter() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let interval = Duration::from_secs(120);
+
+        for _ in 0..100 {
+            let jittered = jittered_interval(interval, 10, &mut rng);
+            assert!(jittered >= Duration::from_secs(108), "{:?}", jittered);
+            assert!(jittered <= Duration::from_secs(132), "{:?}", jittered);
+        }
+    }
+
+    #[test]
+    fn until_aligned_waits_until_next_multiple_of_interval() {
+        let at = |minute, second| Utc.ymd(2021, 6, 30).and_hms(18, minute, second);
+        let five_minutes = Duration::from_secs(5 * 60);
+
+        assert_eq!(
+            until_aligned(at(3, 30), five_minutes),
+            Duration::from_secs(90)
+        );
+        assert_eq!(
+            until_aligned(at(9, 59), five_minutes),
+            Duration::from_secs(1)
+        );
+        // A fetch exactly at a boundary waits for the next one
+        assert_eq!(until_aligned(at(10, 0), five_minutes), five_minutes);
+    }
+
+    #[test]
+    fn jittered_interval_without_jitter_is_exact() {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        assert_eq!(
+            jittered_interval(Duration::from_secs(120), 0, &mut rng),
+            Duration::from_secs(120)
+        );
+    }
+
+    #[test]
+    fn quiet_hours_contains_times_within_window() {
+        let quiet_hours = QuietHours {
+            from: NaiveTime::from_hms(1, 0, 0),
+            until: NaiveTime::from_hms(6, 30, 0),
+        };
+
+        assert!(!quiet_hours.contains(NaiveTime::from_hms(0, 59, 0)));
+        assert!(quiet_hours.contains(NaiveTime::from_hms(1, 0, 0)));
+        assert!(quiet_hours.contains(NaiveTime::from_hms(6, 29, 0)));
+        assert!(!quiet_hours.contains(NaiveTime::from_hms(6, 30, 0)));
+    }
+
+    #[test]
+    fn quiet_hours_spanning_midnight_contains_times_on_both_sides() {
+        let quiet_hours = QuietHours {
+            from: NaiveTime::from_hms(22, 0, 0),
+            until: NaiveTime::from_hms(7, 0, 0),
+        };
+
+        assert!(!quiet_hours.contains(NaiveTime::from_hms(21, 59, 0)));
+        assert!(quiet_hours.contains(NaiveTime::from_hms(22, 0, 0)));
+        assert!(quiet_hours.contains(NaiveTime::from_hms(0, 0, 0)));
+        assert!(quiet_hours.contains(NaiveTime::from_hms(6, 59, 0)));
+        assert!(!quiet_hours.contains(NaiveTime::from_hms(7, 0, 0)));
+        assert!(!quiet_hours.contains(NaiveTime::from_hms(12, 0, 0)));
+    }
+
+    #[test]
+    fn create_notifier_does_not_send_in_dry_run() {
+        // Any request to the server would fail, so sending only succeeds if nothing is sent
+        let server = MockServer::start(vec![MockResponse::new(500, "")]);
+        let config = NotifierConfig::Discord(DiscordConfig {
+            webhook_url: server.url().to_string(),
+        });
+
+        let notifier = create_notifier(&config, true).unwrap();
+
+        assert!(notifier.send("title", "message").is_ok());
+    }
+
+    #[test]
+    fn create_notifier_sends_when_not_in_dry_run() {
+        let server = MockServer::start(vec![MockResponse::new(500, "")]);
+        let config = NotifierConfig::Discord(DiscordConfig {
+            webhook_url: server.url().to_string(),
+        });
+
+        let notifier = create_notifier(&config, false).unwrap();
+
+        assert!(notifier.send("title", "message").is_err());
+    }
 }
@@ -0,0 +1,114 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process,
+};
+
+use fs2::FileExt;
+
+/// An exclusive advisory lock on a file of stored events, so a second instance using the same file
+/// does not overwrite the events of the first. The lock is held on a lock file next to the stored
+/// file, since the stored file itself is replaced on every save, and is released when dropped or
+/// when the process exits, even if it crashes.
+#[derive(Debug)]
+pub struct InstanceLock {
+    // Only held for the lock
+    _file: File,
+}
+
+impl InstanceLock {
+    /// Locks `path` by locking the file next to it with ".lock" appended to its name, which is
+    /// created if missing. The id of the process is written to the lock file for reference.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`LockError::Locked`] if another instance holds the lock, and [`LockError::Io`] if
+    /// the lock file cannot be opened or written.
+    pub fn acquire(path: &Path) -> Result<Self, LockError> {
+        let lock_path = lock_path(path);
+        let io_error = |error| LockError::Io {
+            path: lock_path.clone(),
+            error,
+        };
+
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&lock_path)
+            .map_err(io_error)?;
+        if file.try_lock_exclusive().is_err() {
+            return Err(LockError::Locked { path: lock_path });
+        }
+
+        file.set_len(0).map_err(io_error)?;
+        writeln!(file, "{}", process::id()).map_err(io_error)?;
+        Ok(InstanceLock { _file: file })
+    }
+}
+
+/// An error representing a failure to lock a file of stored events.
+#[derive(Debug)]
+pub enum LockError {
+    /// Another instance holds the lock.
+    Locked {
+        path: PathBuf,
+    },
+    Io {
+        path: PathBuf,
+        error: io::Error,
+    },
+}
+
+impl Display for LockError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Locked { path } => write!(
+                f,
+                "Another instance of ktkbot is already running with the same events, since {:?} \
+                 is locked. Stop it or use another events file.",
+                path
+            ),
+            Self::Io { path, error } => write!(f, "Failed to lock {:?}: {}", path, error),
+        }
+    }
+}
+
+/// Gets the path of the lock file of `path`, e.g. "events.json.lock" for "events.json".
+fn lock_path(path: &Path) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".lock");
+    path.with_file_name(file_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn acquire_fails_while_another_lock_is_held() {
+        let directory = TempDir::new().unwrap();
+        let path = directory.path().join("events.json");
+
+        let lock = InstanceLock::acquire(&path).unwrap();
+
+        let error = InstanceLock::acquire(&path).unwrap_err();
+        assert!(matches!(error, LockError::Locked { .. }), "{}", error);
+
+        drop(lock);
+        assert!(InstanceLock::acquire(&path).is_ok());
+    }
+
+    #[test]
+    fn lock_path_is_next_to_path() {
+        assert_eq!(
+            lock_path(Path::new("data/events.json")),
+            Path::new("data/events.json.lock")
+        );
+    }
+}
@@ -1,25 +1,150 @@
-use std::path::Path;
+use std::{io, path::Path};
 
 use flexi_logger::{
-    Age, Cleanup, Criterion, Duplicate, FileSpec, FlexiLoggerError, Level, Logger, LoggerHandle,
-    Naming,
+    Age, Cleanup, Criterion, DeferredNow, Duplicate, FileSpec, FlexiLoggerError, Level, Logger,
+    LoggerHandle, Naming,
 };
+use log_extern::Record;
+use serde_json::json;
+
+use crate::{LogFormat, LogRotation};
 
 pub fn init_logger(
     level: &Level,
     directory: impl AsRef<Path>,
+    format: LogFormat,
+    rotation: LogRotation,
+    keep: usize,
+    to_file: bool,
 ) -> Result<LoggerHandle, FlexiLoggerError> {
     let directory = directory.as_ref();
 
-    Logger::try_with_env_or_str(level.as_str())?
-        .format(flexi_logger::colored_detailed_format)
+    let logger = Logger::try_with_env_or_str(level.as_str())?;
+    // Colors are only for the terminal, since escape codes clutter the files when viewed later
+    let logger = match format {
+        LogFormat::Text => logger
+            .format(flexi_logger::colored_detailed_format)
+            .format_for_files(flexi_logger::detailed_format),
+        LogFormat::Json => logger.format(json_format),
+    };
+
+    if !to_file {
+        return logger.log_to_stdout().start();
+    }
+
+    logger
         .log_to_file(FileSpec::default().directory(directory))
         .duplicate_to_stdout(Duplicate::Info)
         .print_message()
         .rotate(
-            Criterion::Age(Age::Day), // Create a new log file every day
-            Naming::Timestamps,       // Name log files using timestamps
-            Cleanup::KeepLogFiles(7), // Keep only the 7 newest log files
+            criterion(rotation),
+            Naming::Timestamps,          // Name log files using timestamps
+            Cleanup::KeepLogFiles(keep), // Keep only the `keep` newest log files
         )
         .start()
 }
+
+/// Maps `rotation` onto the criterion that `flexi_logger` starts a new log file by.
+fn criterion(rotation: LogRotation) -> Criterion {
+    match rotation {
+        LogRotation::Daily => Criterion::Age(Age::Day),
+        LogRotation::Size(megabytes) => Criterion::Size(megabytes.saturating_mul(1024 * 1024)),
+    }
+}
+
+/// Makes `level` more verbose by `verbosity` steps, or less verbose for a negative `verbosity`,
+/// e.g. from `info` to `debug` for a verbosity of 1. The level never goes beyond `trace` or
+/// `error`.
+pub fn adjust_level(level: Level, verbosity: i64) -> Level {
+    const LEVELS: [Level; 5] = [
+        Level::Error,
+        Level::Warn,
+        Level::Info,
+        Level::Debug,
+        Level::Trace,
+    ];
+
+    let index = LEVELS.iter().position(|&l| l == level).unwrap_or(2) as i64;
+    LEVELS[(index + verbosity).max(0).min(LEVELS.len() as i64 - 1) as usize]
+}
+
+/// Formats a log record as a single line JSON object with a timestamp, level, module, and
+/// message, e.g. for shipping logs to a log aggregator.
+fn json_format(w: &mut dyn io::Write, now: &mut DeferredNow, record: &Record) -> io::Result<()> {
+    let line = json!({
+        "timestamp": now.now().to_rfc3339(),
+        "level": record.level().as_str(),
+        "module": record.module_path().unwrap_or("<unnamed>"),
+        "message": record.args().to_string(),
+    });
+    write!(w, "{}", line)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use log_extern::warn;
+    use serde_json::Value;
+    use tempfile::TempDir;
+
+    use super::*;
+
+    // The logger is global, so this is the only test that may start it
+    #[test]
+    fn init_logger_writes_files_without_colors() {
+        let directory = TempDir::new().unwrap();
+        let handle = init_logger(
+            &Level::Info,
+            directory.path(),
+            LogFormat::Text,
+            LogRotation::Daily,
+            7,
+            true,
+        )
+        .unwrap();
+
+        warn!("Written to the log file");
+        handle.flush();
+
+        let contents: String = fs::read_dir(directory.path())
+            .unwrap()
+            .map(|entry| fs::read_to_string(entry.unwrap().path()).unwrap())
+            .collect();
+        assert!(contents.contains("Written to the log file"), "{}", contents);
+        assert!(!contents.contains('\u{1b}'), "{}", contents);
+    }
+
+    #[test]
+    fn adjust_level_stays_within_levels() {
+        assert_eq!(adjust_level(Level::Info, 0), Level::Info);
+        assert_eq!(adjust_level(Level::Info, 1), Level::Debug);
+        assert_eq!(adjust_level(Level::Info, 5), Level::Trace);
+        assert_eq!(adjust_level(Level::Info, -1), Level::Warn);
+        assert_eq!(adjust_level(Level::Warn, -3), Level::Error);
+    }
+
+    #[test]
+    fn json_format_writes_one_object_per_line() {
+        let mut output = Vec::new();
+
+        json_format(
+            &mut output,
+            &mut DeferredNow::new(),
+            &Record::builder()
+                .level(Level::Warn)
+                .module_path(Some("ktkbot::event"))
+                .args(format_args!("Skipping event:\n{}", "event1"))
+                .build(),
+        )
+        .unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert!(!output.contains('\n'));
+        let line: Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(line["level"], "WARN");
+        assert_eq!(line["module"], "ktkbot::event");
+        assert_eq!(line["message"], "Skipping event:\nevent1");
+        assert!(line["timestamp"].is_string());
+    }
+}
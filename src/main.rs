@@ -1,6 +1,36 @@
-use ktkbot::args;
+use std::process;
+
+#[cfg(not(feature = "async"))]
+use ktkbot::run;
+#[cfg(feature = "async")]
+use ktkbot::run_async as run;
+use ktkbot::{
+    args::{self, Command},
+    check_directories, print_stats, validate_config,
+};
 
 fn main() {
-    let config = args::parse_config();
-    ktkbot::run(&config);
+    let config = match args::parse_command() {
+        Command::Run(config) => *config,
+        Command::Stats(events_file) => {
+            if let Err(error) = print_stats(&events_file) {
+                eprintln!("Failed to read events from {:?}: {}", events_file, error);
+                process::exit(1);
+            }
+            return;
+        }
+    };
+    if config.validate_config {
+        if validate_config(&config).is_err() {
+            process::exit(1);
+        }
+        return;
+    }
+    if let Err(error) = check_directories(&config) {
+        eprintln!("{}", error);
+        process::exit(1);
+    }
+    if run(config).is_err() {
+        process::exit(1);
+    }
 }
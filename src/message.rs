@@ -0,0 +1,784 @@
+use std::{borrow::Cow, collections::BTreeMap, str::FromStr};
+
+use chrono::{Datelike, NaiveDate};
+use serde::Deserialize;
+
+use crate::{
+    club,
+    event::{
+        diff::{Change, Diff},
+        Event,
+    },
+    strings::Language,
+};
+
+/// The default template for the line about each event, see [`render_template`].
+pub const DEFAULT_TEMPLATE: &str =
+    "[{club}: ]<b>{title}</b>: {date}[ ({location})][ – {availability}][ {url}]";
+
+/// The default format of dates in notifications, e.g. "Tue  4 May 2021".
+pub const DEFAULT_DATE_FORMAT: &str = "%a %e %b %Y";
+
+/// The format of `{date}` for events listed under the date they take place on, e.g. "18:00".
+const TIME_FORMAT: &str = "%H:%M";
+
+/// The placeholders that can be used in templates, see [`render_template`].
+pub const PLACEHOLDERS: &[&str] = &[
+    "title",
+    "date",
+    "class_info",
+    "class_number",
+    "location",
+    "instructor",
+    "price",
+    "availability",
+    "club",
+    "url",
+];
+
+/// How notifications about events are worded.
+#[derive(Debug, Clone)]
+pub struct MessageFormat {
+    /// The template for the line about each event, see [`render_template`].
+    pub template: String,
+    /// The title of all notifications about changes, instead of a title describing the changes.
+    pub title: Option<String>,
+    /// The format of dates where the time of day is not enough, e.g. `{date}` for the old version
+    /// of an event moved to another date, as accepted by [`chrono::format::strftime`].
+    pub date_format: String,
+    /// The language of the names of weekdays and months in formatted dates.
+    pub date_locale: DateLocale,
+    /// The language of the titles, headings, and other fixed texts of notifications.
+    pub language: Language,
+}
+
+impl Default for MessageFormat {
+    fn default() -> Self {
+        MessageFormat {
+            template: String::from(DEFAULT_TEMPLATE),
+            title: None,
+            date_format: String::from(DEFAULT_DATE_FORMAT),
+            date_locale: DateLocale::English,
+            language: Language::default(),
+        }
+    }
+}
+
+/// The languages that the names of weekdays and months can be written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum DateLocale {
+    /// English names, e.g. "Tue 4 May".
+    #[serde(rename = "en")]
+    English,
+    /// Danish names, e.g. "tir 4 maj".
+    #[serde(rename = "da")]
+    Danish,
+}
+
+impl FromStr for DateLocale {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "en" => Ok(Self::English),
+            "da" => Ok(Self::Danish),
+            _ => Err(format!("Unknown date locale: {}", s)),
+        }
+    }
+}
+
+const DANISH_WEEKDAYS: [&str; 7] = [
+    "mandag", "tirsdag", "onsdag", "torsdag", "fredag", "lørdag", "søndag",
+];
+
+const DANISH_MONTHS: [&str; 12] = [
+    "januar",
+    "februar",
+    "marts",
+    "april",
+    "maj",
+    "juni",
+    "juli",
+    "august",
+    "september",
+    "oktober",
+    "november",
+    "december",
+];
+
+impl DateLocale {
+    /// Replaces the weekday and month names specifiers of `format`, i.e. `%a`, `%A`, `%b`, `%h`,
+    /// and `%B`, with the names for `date` in this language, since chrono only knows the English
+    /// names.
+    fn localize<'a>(self, format: &'a str, date: &impl Datelike) -> Cow<'a, str> {
+        if self == Self::English {
+            return Cow::Borrowed(format);
+        }
+
+        let weekday = DANISH_WEEKDAYS[date.weekday().num_days_from_monday() as usize];
+        let month = DANISH_MONTHS[date.month0() as usize];
+        let mut localized = String::new();
+        let mut chars = format.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                localized.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('a') => localized.extend(weekday.chars().take(3)),
+                Some('A') => localized.push_str(weekday),
+                Some('b') | Some('h') => localized.extend(month.chars().take(3)),
+                Some('B') => localized.push_str(month),
+                Some(c) => {
+                    localized.push('%');
+                    localized.push(c);
+                }
+                None => localized.push('%'),
+            }
+        }
+        Cow::Owned(localized)
+    }
+
+    /// Formats `date` as a heading for the events on that date in this language, e.g. "Wed 5 May"
+    /// or "Ons 5. maj".
+    fn date_heading(self, date: NaiveDate) -> String {
+        let format = match self {
+            Self::English => "%a %-d %B",
+            Self::Danish => "%a %-d. %B",
+        };
+        let heading = date.format(&self.localize(format, &date)).to_string();
+
+        let mut chars = heading.chars();
+        match chars.next() {
+            Some(first) => first.to_uppercase().chain(chars).collect(),
+            None => heading,
+        }
+    }
+}
+
+/// A part of a notification message consisting of a heading and groups of lines about events.
+#[derive(Debug)]
+pub struct Section {
+    heading: String,
+    groups: Vec<Group>,
+}
+
+/// Lines about events in a [`Section`], e.g. all events on one date, with an optional heading.
+#[derive(Debug)]
+struct Group {
+    heading: Option<String>,
+    lines: Vec<String>,
+}
+
+/// Gets the title of a notification about the given changes.
+pub fn title<'a>(diff: &Diff, format: &'a MessageFormat) -> &'a str {
+    let strings = format.language.strings();
+    if let Some(title) = &format.title {
+        title
+    } else if !diff.added.is_empty() {
+        strings.added_title
+    } else if !diff.changed.is_empty() {
+        strings.changed_title
+    } else {
+        strings.removed_title
+    }
+}
+
+/// Gets the title of the daily digest.
+pub fn digest_title(format: &MessageFormat) -> &'static str {
+    format.language.strings().digest_title
+}
+
+/// Builds a section for each non-empty kind of change in `diff`, with the events grouped under a
+/// heading for each date in chronological order. The events are described by the template of
+/// `format` with `{date}` being the time of day, apart from the old version of events that were
+/// moved to another date.
+pub fn sections(diff: &Diff, format: &MessageFormat) -> Vec<Section> {
+    let strings = format.language.strings();
+    let format_time = |event: &&Event| {
+        render_template(
+            &format.template,
+            event,
+            TIME_FORMAT,
+            format.date_locale,
+            format.language,
+        )
+    };
+
+    let mut sections = Vec::new();
+    if !diff.added.is_empty() {
+        sections.push(Section {
+            heading: String::from(strings.added_heading),
+            groups: group_by_date(&diff.added, |event| *event, format_time, format.date_locale),
+        });
+    }
+    if !diff.removed.is_empty() {
+        sections.push(Section {
+            heading: String::from(strings.removed_heading),
+            groups: group_by_date(
+                &diff.removed,
+                |event| *event,
+                format_time,
+                format.date_locale,
+            ),
+        });
+    }
+    if !diff.changed.is_empty() {
+        let format_change = |change: &Change| {
+            let old = if date_of(change.old) == date_of(change.new) {
+                format_time(&change.old)
+            } else {
+                format_event(change.old, format)
+            };
+            format!("{} ➜ {}", old, format_time(&change.new))
+        };
+        sections.push(Section {
+            heading: String::from(strings.changed_heading),
+            groups: group_by_date(
+                &diff.changed,
+                |change| change.new,
+                format_change,
+                format.date_locale,
+            ),
+        });
+    }
+    sections
+}
+
+/// Groups the lines about `items`, which are in chronological order by the date of the event of
+/// each item, under a heading for each date.
+fn group_by_date<'a, T>(
+    items: &'a [T],
+    event: impl Fn(&'a T) -> &'a Event,
+    line: impl Fn(&'a T) -> String,
+    locale: DateLocale,
+) -> Vec<Group> {
+    let mut groups = Vec::new();
+    let mut current_date = None;
+    for item in items {
+        let date = date_of(event(item));
+        if current_date != Some(date) {
+            current_date = Some(date);
+            groups.push(Group {
+                heading: Some(locale.date_heading(date)),
+                lines: Vec::new(),
+            });
+        }
+        if let Some(group) = groups.last_mut() {
+            group.lines.push(line(item));
+        }
+    }
+    groups
+}
+
+/// Gets the local date that `event` takes place on.
+fn date_of(event: &Event) -> NaiveDate {
+    event.date_time.naive_local().date()
+}
+
+/// Builds a section per date for the daily digest about `events`, with the dates and the events
+/// on each date in chronological order. The dates are formatted using the date format of `format`,
+/// while the events are described by its template with `{date}` being the time of day.
+pub fn digest_sections(events: &[&Event], format: &MessageFormat) -> Vec<Section> {
+    let format_time = |event: &&Event| {
+        render_template(
+            &format.template,
+            event,
+            TIME_FORMAT,
+            format.date_locale,
+            format.language,
+        )
+    };
+    let mut by_date: BTreeMap<NaiveDate, Vec<&Event>> = BTreeMap::new();
+    for event in events.iter().copied() {
+        by_date.entry(date_of(event)).or_default().push(event);
+    }
+
+    by_date
+        .into_iter()
+        .map(|(date, mut events)| {
+            events.sort_by_key(|event| event.date_time);
+            Section {
+                heading: date
+                    .format(&format.date_locale.localize(&format.date_format, &date))
+                    .to_string(),
+                groups: vec![Group {
+                    heading: None,
+                    lines: events.iter().map(format_time).collect(),
+                }],
+            }
+        })
+        .collect()
+}
+
+/// Renders sections into one or more messages that are each at most `max_length` characters
+/// long, or a single message if there is no maximum length.
+///
+/// Messages are only split in between events, and a section or group that is split across
+/// messages has its heading repeated. A single event that is longer than `max_length` gets a
+/// message of its own.
+pub fn render(sections: &[Section], max_length: Option<usize>) -> Vec<String> {
+    let max_length = max_length.unwrap_or(usize::MAX);
+
+    let mut messages = Vec::new();
+    let mut message = String::new();
+    for section in sections {
+        let heading = format!("<u>{}</u>:", section.heading);
+        let mut has_heading = false;
+
+        for group in &section.groups {
+            let group_heading = group
+                .heading
+                .as_ref()
+                .map(|heading| format!("<b>{}</b>", heading));
+            let mut has_group_heading = false;
+
+            for line in &group.lines {
+                let headings = (has_heading, group_heading.as_deref(), has_group_heading);
+                let mut addition = render_line(&message, &heading, headings, line);
+                if !message.is_empty() && length(&message) + length(&addition) > max_length {
+                    messages.push(message);
+                    message = String::new();
+                    let headings = (false, group_heading.as_deref(), false);
+                    addition = render_line(&message, &heading, headings, line);
+                }
+                message.push_str(&addition);
+                has_heading = true;
+                has_group_heading = true;
+            }
+        }
+    }
+    if !message.is_empty() {
+        messages.push(message);
+    }
+
+    messages
+}
+
+/// Renders a line to be appended to `message`, preceded by the section heading and the heading of
+/// its group, if any, unless they have been rendered in the message already. `headings` is whether
+/// the section heading has been rendered, the group heading, and whether it has been rendered.
+fn render_line(
+    message: &str,
+    heading: &str,
+    headings: (bool, Option<&str>, bool),
+    line: &str,
+) -> String {
+    let (has_heading, group_heading, has_group_heading) = headings;
+    let mut rendered = String::new();
+    if !has_heading {
+        if !message.is_empty() {
+            rendered.push_str("\n\n");
+        }
+        rendered.push_str(heading);
+    }
+    if let (Some(group_heading), false) = (group_heading, has_group_heading) {
+        rendered.push('\n');
+        rendered.push_str(group_heading);
+    }
+    rendered.push_str("\n- ");
+    rendered.push_str(line);
+    rendered
+}
+
+fn length(s: &str) -> usize {
+    s.chars().count()
+}
+
+fn format_event(event: &Event, format: &MessageFormat) -> String {
+    render_template(
+        &format.template,
+        event,
+        &format.date_format,
+        format.date_locale,
+        format.language,
+    )
+}
+
+/// Renders the line about `event` from `template`, replacing each of the [`PLACEHOLDERS`], e.g.
+/// `{title}`, with the corresponding detail of the event, and formatting `{date}` using
+/// `date_format` with the names of weekdays and months in `locale`. Other details, e.g.
+/// `{availability}`, are worded in `language`. Text in square brackets is left out if any
+/// placeholder in it is empty, e.g. `[ ({location})]` for events without a location. Unknown
+/// placeholders are kept as is.
+pub fn render_template(
+    template: &str,
+    event: &Event,
+    date_format: &str,
+    locale: DateLocale,
+    language: Language,
+) -> String {
+    let mut line = String::new();
+    // The text of the current part in square brackets, and whether any placeholder in it is empty
+    let mut optional: Option<(String, bool)> = None;
+
+    let mut chars = template.chars();
+    while let Some(c) = chars.next() {
+        let (text, is_empty) = match c {
+            '[' => {
+                optional = Some((String::new(), false));
+                continue;
+            }
+            ']' if optional.is_some() => {
+                if let Some((text, false)) = optional.take() {
+                    line.push_str(&text);
+                }
+                continue;
+            }
+            '{' => {
+                let name: String = chars.by_ref().take_while(|&c| c != '}').collect();
+                match placeholder(&name, event, date_format, locale, language) {
+                    Some(value) => {
+                        let is_empty = value.is_empty();
+                        (value, is_empty)
+                    }
+                    None => (format!("{{{}}}", name), false),
+                }
+            }
+            c => (c.to_string(), false),
+        };
+
+        match &mut optional {
+            Some((optional_text, any_empty)) => {
+                optional_text.push_str(&text);
+                *any_empty |= is_empty;
+            }
+            None => line.push_str(&text),
+        }
+    }
+    if let Some((text, false)) = optional {
+        line.push_str(&text);
+    }
+
+    line
+}
+
+/// Gets the value of the placeholder `name` for `event`, which is empty if the event lacks the
+/// detail, or `None` if there is no such placeholder.
+fn placeholder(
+    name: &str,
+    event: &Event,
+    date_format: &str,
+    locale: DateLocale,
+    language: Language,
+) -> Option<String> {
+    let strings = language.strings();
+    let value = match name {
+        "title" => event.title.clone(),
+        "date" => {
+            let date_format = locale.localize(date_format, &event.date_time);
+            event.date_time.format(&date_format).to_string()
+        }
+        "class_info" => event.class_info.join(", "),
+        "class_number" => event.class_number.clone().unwrap_or_default(),
+        "location" => event.location.clone().unwrap_or_default(),
+        "instructor" => event.instructor.clone().unwrap_or_default(),
+        "price" => event
+            .price
+            .map(|price| format!("{} kr.", price))
+            .unwrap_or_default(),
+        "availability" => match event.available_spots {
+            Some(0) => String::from(strings.fully_booked),
+            Some(1) => String::from(strings.one_open_spot),
+            Some(spots) => format!("{} {}", spots, strings.open_spots),
+            None => String::new(),
+        },
+        "club" => club::of(event).unwrap_or_default().to_string(),
+        "url" => event.url.clone().unwrap_or_default(),
+        _ => return None,
+    };
+    Some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    fn events(count: usize) -> HashSet<Event> {
+        (0..count)
+            .map(|i| {
+                let mut event = Event::new();
+                event.id = format!("event{}", i);
+                event.title = format!("Tennisskole for øvede spillere på hold nummer {:02}", i);
+                event
+            })
+            .collect()
+    }
+
+    #[test]
+    fn format_event_includes_available_spots() {
+        let mut event = Event::new();
+        event.title = String::from("Tennisskole");
+        event.available_spots = Some(3);
+        let format = MessageFormat::default();
+
+        assert_eq!(
+            format_event(&event, &format),
+            "<b>Tennisskole</b>: Wed 30 Jun 2021 – 3 ledige pladser"
+        );
+
+        event.available_spots = Some(0);
+
+        assert_eq!(
+            format_event(&event, &format),
+            "<b>Tennisskole</b>: Wed 30 Jun 2021 – fuldt booket"
+        );
+    }
+
+    #[test]
+    fn format_event_includes_location() {
+        let mut event = Event::new();
+        event.title = String::from("Tennisskole");
+        event.location = Some(String::from("Bane 3"));
+
+        assert_eq!(
+            format_event(&event, &MessageFormat::default()),
+            "<b>Tennisskole</b>: Wed 30 Jun 2021 (Bane 3)"
+        );
+    }
+
+    #[test]
+    fn format_event_includes_url() {
+        let mut event = Event::new();
+        event.title = String::from("Tennisskole");
+        event.url = Some(String::from("https://ktk-tennis.halbooking.dk/hold"));
+
+        assert_eq!(
+            format_event(&event, &MessageFormat::default()),
+            "<b>Tennisskole</b>: Wed 30 Jun 2021 https://ktk-tennis.halbooking.dk/hold"
+        );
+    }
+
+    #[test]
+    fn format_event_includes_club_of_namespaced_events() {
+        let mut event = Event::new();
+        event.id = String::from("hold10412");
+        event.title = String::from("Tennisskole");
+        let event = club::namespaced("ktk-tennis.halbooking.dk", event);
+
+        assert_eq!(
+            format_event(&event, &MessageFormat::default()),
+            "ktk-tennis.halbooking.dk: <b>Tennisskole</b>: Wed 30 Jun 2021"
+        );
+    }
+
+    #[test]
+    fn render_template_replaces_placeholders() {
+        let mut event = Event::new();
+        event.title = String::from("Tennisskole");
+        event.class_info = vec![String::from("Hold 1"), String::from("Træner: Jens")];
+        event.price = Some(120);
+
+        let render = |template| {
+            render_template(
+                template,
+                &event,
+                "%H:%M",
+                DateLocale::English,
+                Language::Danish,
+            )
+        };
+
+        assert_eq!(
+            render("{title} kl. {date} ({class_info}, {price})"),
+            "Tennisskole kl. 00:00 (Hold 1, Træner: Jens, 120 kr.)"
+        );
+        assert_eq!(render("{title} {unknown}"), "Tennisskole {unknown}");
+    }
+
+    #[test]
+    fn render_template_leaves_out_optional_parts_with_empty_placeholders() {
+        let mut event = Event::new();
+        event.title = String::from("Tennisskole");
+        event.instructor = Some(String::from("Jens"));
+
+        assert_eq!(
+            render_template(
+                "{title}[ med {instructor}][ i {location}]!",
+                &event,
+                "%H:%M",
+                DateLocale::English,
+                Language::Danish
+            ),
+            "Tennisskole med Jens!"
+        );
+    }
+
+    #[test]
+    fn render_template_localizes_dates() {
+        let mut event = Event::new();
+        event.title = String::from("Tennisskole");
+        let render = |date_format| {
+            let locale = DateLocale::Danish;
+            render_template(
+                "{title}: {date}",
+                &event,
+                date_format,
+                locale,
+                Language::Danish,
+            )
+        };
+
+        assert_eq!(render(DEFAULT_DATE_FORMAT), "Tennisskole: ons 30 jun 2021");
+        assert_eq!(
+            render("%A %-d. %B kl. %H:%M"),
+            "Tennisskole: onsdag 30. juni kl. 00:00"
+        );
+        assert_eq!(render("%%a %d/%m"), "Tennisskole: %a 30/06");
+    }
+
+    #[test]
+    fn title_can_be_overridden() {
+        let (old, new) = (HashSet::new(), events(1));
+        let diff = Diff::between(&old, &new);
+        let format = MessageFormat {
+            title: Some(String::from("KTK")),
+            ..MessageFormat::default()
+        };
+
+        assert_eq!(
+            title(&diff, &MessageFormat::default()),
+            "Nye tider lagt op!"
+        );
+        assert_eq!(title(&diff, &format), "KTK");
+    }
+
+    #[test]
+    fn texts_follow_language() {
+        let (old, new) = (HashSet::new(), events(1));
+        let diff = Diff::between(&old, &new);
+        let format = MessageFormat {
+            language: Language::English,
+            ..MessageFormat::default()
+        };
+        let mut event = Event::new();
+        event.available_spots = Some(3);
+
+        assert_eq!(title(&diff, &format), "New times posted!");
+        assert_eq!(digest_title(&format), "Today's new times");
+        assert!(render(&sections(&diff, &format), None)[0]
+            .starts_with("<u>New times have been posted</u>:"));
+        let availability = render_template(
+            "{availability}",
+            &event,
+            "%H:%M",
+            DateLocale::English,
+            format.language,
+        );
+        assert_eq!(availability, "3 open spots");
+    }
+
+    #[test]
+    fn digest_sections_group_events_by_date() {
+        let event = |title: &str, hours| {
+            let mut event = Event::new();
+            event.title = String::from(title);
+            event.date_time += chrono::Duration::hours(hours);
+            event
+        };
+        let (tomorrow, later, earlier) = (event("C", 24), event("B", 18), event("A", 9));
+
+        let format = MessageFormat::default();
+
+        let messages = render(
+            &digest_sections(&[&tomorrow, &later, &earlier], &format),
+            None,
+        );
+
+        assert_eq!(
+            messages,
+            vec![
+                "<u>Wed 30 Jun 2021</u>:\n- <b>A</b>: 09:00\n- <b>B</b>: 18:00\n\n\
+                 <u>Thu  1 Jul 2021</u>:\n- <b>C</b>: 00:00"
+            ]
+        );
+    }
+
+    #[test]
+    fn render_without_max_length_is_single_message() {
+        let old = HashSet::new();
+        let new = events(30);
+        let diff = Diff::between(&old, &new);
+
+        let messages = render(&sections(&diff, &MessageFormat::default()), None);
+
+        assert_eq!(messages.len(), 1);
+        assert!(messages[0]
+            .starts_with("<u>Der er blevet lagt nye tider op</u>:\n<b>Wed 30 June</b>\n- "));
+        assert_eq!(messages[0].lines().count(), 32);
+    }
+
+    #[test]
+    fn render_splits_in_between_events() {
+        let old = HashSet::new();
+        let new = events(30);
+        let diff = Diff::between(&old, &new);
+
+        let messages = render(&sections(&diff, &MessageFormat::default()), Some(1024));
+
+        assert_eq!(messages.len(), 3);
+        for message in &messages {
+            assert!(length(message) <= 1024);
+            assert!(message
+                .starts_with("<u>Der er blevet lagt nye tider op</u>:\n<b>Wed 30 June</b>\n- "));
+        }
+        let event_lines: usize = messages.iter().map(|m| m.lines().count() - 2).sum();
+        assert_eq!(event_lines, 30);
+    }
+
+    #[test]
+    fn render_separates_sections() {
+        let old = events(1);
+        let new = HashSet::new();
+        let mut diff = Diff::between(&old, &new);
+        diff.added = diff.removed.clone();
+
+        let messages = render(&sections(&diff, &MessageFormat::default()), Some(1024));
+
+        assert_eq!(messages.len(), 1);
+        assert!(messages[0]
+            .contains("\n\n<u>Disse tider er blevet fjernet</u>:\n<b>Wed 30 June</b>\n- "));
+    }
+
+    #[test]
+    fn sections_group_events_by_date() {
+        let event = |id: &str, days| {
+            let mut event = Event::new();
+            event.id = String::from(id);
+            event.title = String::from("Tennisskole");
+            event.date_time += chrono::Duration::days(days);
+            event
+        };
+        let (old, new) = (
+            HashSet::new(),
+            vec![event("a", 0), event("b", 5), event("c", 5)],
+        );
+        let new: HashSet<_> = new.into_iter().collect();
+        let moved = event("a", 7);
+        let mut diff = Diff::between(&old, &new);
+        diff.changed = vec![Change {
+            old: diff.added[0],
+            new: &moved,
+        }];
+        let format = MessageFormat {
+            date_locale: DateLocale::Danish,
+            ..MessageFormat::default()
+        };
+
+        let messages = render(&sections(&diff, &format), None);
+
+        assert_eq!(
+            messages,
+            vec![
+                "<u>Der er blevet lagt nye tider op</u>:\n\
+                 <b>Ons 30. juni</b>\n- <b>Tennisskole</b>: 00:00\n\
+                 <b>Man 5. juli</b>\n- <b>Tennisskole</b>: 00:00\n- <b>Tennisskole</b>: 00:00\n\n\
+                 <u>Disse tider er blevet ændret</u>:\n\
+                 <b>Ons 7. juli</b>\n\
+                 - <b>Tennisskole</b>: ons 30 jun 2021 ➜ <b>Tennisskole</b>: 00:00"
+            ]
+        );
+    }
+}
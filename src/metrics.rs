@@ -0,0 +1,130 @@
+use std::{
+    fmt::Write,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use crate::http::Response;
+
+/// Counters and gauges describing the bot, exposed in the Prometheus text format.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    fetches: AtomicU64,
+    fetch_errors: AtomicU64,
+    new_events: AtomicU64,
+    notifications_sent: AtomicU64,
+    events_tracked: AtomicU64,
+}
+
+impl Metrics {
+    /// Records an attempt to fetch events, and whether it failed.
+    pub fn record_fetch(&self, failed: bool) {
+        self.fetches.fetch_add(1, Ordering::Relaxed);
+        if failed {
+            self.fetch_errors.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn record_new_events(&self, count: usize) {
+        self.new_events.fetch_add(count as u64, Ordering::Relaxed);
+    }
+
+    pub fn record_notification(&self) {
+        self.notifications_sent.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn set_events_tracked(&self, count: usize) {
+        self.events_tracked.store(count as u64, Ordering::Relaxed);
+    }
+
+    /// Renders all metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let metrics = [
+            (
+                "ktkbot_fetch_total",
+                "counter",
+                "Number of attempts to fetch events.",
+                &self.fetches,
+            ),
+            (
+                "ktkbot_fetch_errors_total",
+                "counter",
+                "Number of attempts to fetch events that failed.",
+                &self.fetch_errors,
+            ),
+            (
+                "ktkbot_new_events_total",
+                "counter",
+                "Number of new events found, excluding filtered out events.",
+                &self.new_events,
+            ),
+            (
+                "ktkbot_notifications_sent_total",
+                "counter",
+                "Number of notifications sent about new, removed, or changed events.",
+                &self.notifications_sent,
+            ),
+            (
+                "ktkbot_events_tracked",
+                "gauge",
+                "Number of events currently stored.",
+                &self.events_tracked,
+            ),
+        ];
+
+        let mut output = String::new();
+        for (name, kind, help, value) in &metrics {
+            // Writing to a string cannot fail
+            let _ = writeln!(output, "# HELP {} {}", name, help);
+            let _ = writeln!(output, "# TYPE {} {}", name, kind);
+            let _ = writeln!(output, "{} {}", name, value.load(Ordering::Relaxed));
+        }
+        output
+    }
+}
+
+/// Creates a handler serving `metrics` on `/metrics`.
+pub fn metrics_handler(metrics: Arc<Metrics>) -> impl Fn(&str) -> Response {
+    move |path| match path {
+        "/metrics" => Response::new(200, "text/plain; version=0.0.4", metrics.render()),
+        _ => Response::not_found(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_writes_prometheus_text_format() {
+        let metrics = Metrics::default();
+        metrics.record_fetch(false);
+        metrics.record_fetch(true);
+        metrics.record_new_events(3);
+        metrics.record_notification();
+        metrics.set_events_tracked(42);
+
+        let output = metrics.render();
+
+        assert!(output.contains("# TYPE ktkbot_fetch_total counter\nktkbot_fetch_total 2\n"));
+        assert!(output.contains("\nktkbot_fetch_errors_total 1\n"));
+        assert!(output.contains("\nktkbot_new_events_total 3\n"));
+        assert!(output.contains("\nktkbot_notifications_sent_total 1\n"));
+        assert!(output.contains("# TYPE ktkbot_events_tracked gauge\nktkbot_events_tracked 42\n"));
+    }
+
+    #[test]
+    fn metrics_handler_serves_metrics_only() {
+        let metrics = Arc::new(Metrics::default());
+        metrics.set_events_tracked(1);
+        let handler = metrics_handler(Arc::clone(&metrics));
+
+        assert_eq!(
+            handler("/metrics"),
+            Response::new(200, "text/plain; version=0.0.4", metrics.render())
+        );
+        assert_eq!(handler("/events"), Response::not_found());
+    }
+}
@@ -0,0 +1,66 @@
+use reqwest::blocking::Client;
+use serde::Serialize;
+
+use super::{Notifier, NotifyError};
+
+const DISCORD_MAX_MESSAGE_LENGTH: usize = 2000;
+
+/// A [`Notifier`] that posts notifications to a Discord channel through a webhook.
+pub struct DiscordNotifier {
+    webhook_url: String,
+}
+
+#[derive(Serialize)]
+struct WebhookPayload<'a> {
+    content: &'a str,
+}
+
+impl DiscordNotifier {
+    pub fn new(webhook_url: impl Into<String>) -> Self {
+        DiscordNotifier {
+            webhook_url: webhook_url.into(),
+        }
+    }
+}
+
+impl Notifier for DiscordNotifier {
+    fn send(&self, title: &str, message: &str) -> Result<(), NotifyError> {
+        let content = to_markdown(&format!("<b>{}</b>\n{}", title, message));
+
+        Client::new()
+            .post(&self.webhook_url)
+            .json(&WebhookPayload { content: &content })
+            .send()?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    fn max_message_length(&self) -> Option<usize> {
+        // Leave room for the title, which is sent as part of the content
+        Some(DISCORD_MAX_MESSAGE_LENGTH - 256)
+    }
+}
+
+/// Translates the HTML tags used in messages into Discord markdown, since Discord does not render
+/// HTML.
+fn to_markdown(html: &str) -> String {
+    html.replace("<b>", "**")
+        .replace("</b>", "**")
+        .replace("<u>", "__")
+        .replace("</u>", "__")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_markdown_translates_tags() {
+        let html = "<u>Nye tider</u>:\n- <b>Tennisskole</b>: Tue  4 May 2021";
+
+        assert_eq!(
+            to_markdown(html),
+            "__Nye tider__:\n- **Tennisskole**: Tue  4 May 2021"
+        );
+    }
+}
@@ -0,0 +1,22 @@
+use log_extern::info;
+
+use super::{Notifier, NotifyError};
+
+/// A [`Notifier`] that only logs notifications instead of delivering them, for trying out the bot
+/// without sending anything.
+pub struct DryRunNotifier;
+
+impl Notifier for DryRunNotifier {
+    fn send(&self, title: &str, message: &str) -> Result<(), NotifyError> {
+        info!(
+            "[dry run] Would send notification \"{}\":\n{}",
+            title, message
+        );
+        Ok(())
+    }
+
+    fn send_test(&self, title: &str, message: &str) -> Result<String, NotifyError> {
+        self.send(title, message)
+            .map(|()| String::from("Logged test notification instead of sending it (dry run)."))
+    }
+}
@@ -0,0 +1,95 @@
+use lettre::{
+    message::{Mailbox, MultiPart},
+    transport::smtp::authentication::Credentials,
+    Message, SmtpTransport, Transport,
+};
+
+use super::{Notifier, NotifyError};
+
+/// A [`Notifier`] that sends notifications as emails through an SMTP server.
+///
+/// Emails are sent as multipart messages with both a plain text and an HTML version of the
+/// message.
+pub struct EmailNotifier {
+    transport: SmtpTransport,
+    from: Mailbox,
+    to: Mailbox,
+}
+
+impl EmailNotifier {
+    /// Creates an `EmailNotifier` that connects to the SMTP server at `host` using STARTTLS.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the TLS parameters for `host` cannot be created.
+    pub fn new(
+        host: &str,
+        port: u16,
+        credentials: Credentials,
+        from: Mailbox,
+        to: Mailbox,
+    ) -> Result<Self, NotifyError> {
+        let transport = SmtpTransport::starttls_relay(host)?
+            .port(port)
+            .credentials(credentials)
+            .build();
+        Ok(EmailNotifier {
+            transport,
+            from,
+            to,
+        })
+    }
+}
+
+impl Notifier for EmailNotifier {
+    fn send(&self, title: &str, message: &str) -> Result<(), NotifyError> {
+        let email = Message::builder()
+            .from(self.from.clone())
+            .to(self.to.clone())
+            .subject(title)
+            .multipart(MultiPart::alternative_plain_html(
+                to_plain_text(message),
+                to_html(message),
+            ))?;
+
+        self.transport.send(&email)?;
+        Ok(())
+    }
+}
+
+fn to_plain_text(message: &str) -> String {
+    message
+        .replace("<b>", "")
+        .replace("</b>", "")
+        .replace("<u>", "")
+        .replace("</u>", "")
+}
+
+fn to_html(message: &str) -> String {
+    message.replace('\n', "<br>\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_plain_text_strips_tags() {
+        let message = "<u>Nye tider</u>:\n- <b>Tennisskole</b>: Tue  4 May 2021";
+
+        assert_eq!(
+            to_plain_text(message),
+            "Nye tider:\n- Tennisskole: Tue  4 May 2021"
+        );
+    }
+
+    #[test]
+    fn to_html_preserves_line_breaks() {
+        let message = "<u>Nye tider</u>:\n- <b>Tennisskole</b>: Tue  4 May 2021";
+
+        assert_eq!(
+            to_html(message),
+            "<u>Nye tider</u>:<br>\n- <b>Tennisskole</b>: Tue  4 May 2021"
+        );
+    }
+}
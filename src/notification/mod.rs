@@ -1,11 +1,291 @@
+mod discord;
+mod dry_run;
+mod email;
 mod pushover_key;
+mod retrying;
+mod telegram;
+mod webhook;
 
-use reqwest::blocking::{Client, Response};
-use serde::Serialize;
+use std::{
+    fmt::{self, Display, Formatter},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
-pub use self::pushover_key::{PushoverKey, PushoverKeyError};
+use log_extern::{info, warn};
+use reqwest::{blocking::Client, header::HeaderMap, StatusCode};
+use serde::{Deserialize, Serialize};
+
+use crate::event::diff::Diff;
+
+pub use self::{
+    discord::DiscordNotifier,
+    dry_run::DryRunNotifier,
+    email::EmailNotifier,
+    pushover_key::{PushoverKey, PushoverKeyError},
+    retrying::RetryingNotifier,
+    telegram::TelegramNotifier,
+    webhook::GenericWebhookNotifier,
+};
 
 const PUSHOVER_API_URL: &str = "https://api.pushover.net/1/messages.json";
+const PUSHOVER_MAX_MESSAGE_LENGTH: usize = 1024;
+/// How long to wait before sending again when rate limited without being told for how long.
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// The Pushover priority of notifications that are delivered without sound or vibration.
+const LOW_PRIORITY: i8 = -1;
+/// The Pushover priority of emergency notifications, which are repeated until acknowledged.
+pub const EMERGENCY_PRIORITY: i8 = 2;
+const MIN_RETRY: u32 = 30;
+const MAX_EXPIRE: u32 = 10800;
+
+/// The sounds that Pushover notifications can be played with.
+pub const SOUNDS: &[&str] = &[
+    "pushover",
+    "bike",
+    "bugle",
+    "cashregister",
+    "classical",
+    "cosmic",
+    "falling",
+    "gamelan",
+    "incoming",
+    "intermission",
+    "magic",
+    "mechanical",
+    "pianobar",
+    "siren",
+    "spacealarm",
+    "tugboat",
+    "alien",
+    "climb",
+    "persistent",
+    "echo",
+    "updown",
+    "vibrate",
+    "none",
+];
+
+// Retry emergency notifications every minute for an hour
+const EMERGENCY_RETRY: u32 = 60;
+const EMERGENCY_EXPIRE: u32 = 3600;
+
+/// A backend that delivers notifications, e.g. through Pushover, Telegram, Discord, or email.
+///
+/// Messages may contain the basic HTML tags `<b>` and `<u>`, which backends either render or
+/// translate to their own formatting.
+///
+/// Notifiers must be `Send` and `Sync`, since notifications to different notifiers are sent
+/// concurrently.
+pub trait Notifier: Send + Sync {
+    fn send(&self, title: &str, message: &str) -> Result<(), NotifyError>;
+
+    /// Sends a notification that should not disturb the recipient, e.g. a status update. Backends
+    /// without a notion of priority send it like any other notification.
+    fn send_low_priority(&self, title: &str, message: &str) -> Result<(), NotifyError> {
+        self.send(title, message)
+    }
+
+    /// Gets the maximum number of characters in a message, if any. Longer messages must be split
+    /// into multiple notifications by the caller.
+    fn max_message_length(&self) -> Option<usize> {
+        None
+    }
+
+    /// Sends a notification about the changes in `diff` as data instead of a rendered message,
+    /// e.g. for backends that pass the events on to other programs. Returns `None` if the backend
+    /// only sends rendered messages, which is the default.
+    fn send_diff(&self, _diff: &Diff) -> Option<Result<(), NotifyError>> {
+        None
+    }
+
+    /// Sends a notification to check that notifications are delivered, and describes how it was
+    /// delivered, e.g. with the id of the request, for the user to confirm.
+    fn send_test(&self, title: &str, message: &str) -> Result<String, NotifyError> {
+        self.send(title, message)
+            .map(|()| String::from("Sent test notification."))
+    }
+}
+
+/// An error representing a failure to deliver a notification.
+#[derive(Debug)]
+pub enum NotifyError {
+    Request(reqwest::Error),
+    Email(lettre::error::Error),
+    Smtp(lettre::transport::smtp::Error),
+    Invalid(String),
+    /// Too many notifications have been sent, so no more can be sent until `retry_after` has
+    /// passed.
+    RateLimited {
+        retry_after: Duration,
+    },
+    /// The service responded with an error `status`, with the `errors` it gave as the reason, e.g.
+    /// that the user key is invalid.
+    Rejected {
+        status: StatusCode,
+        errors: Vec<String>,
+    },
+}
+
+impl NotifyError {
+    /// Whether the error is likely to go away by itself, i.e. it is a network error or a server
+    /// error response. Being rate limited is not transient, since it lasts for a given time.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::Request(error) => error.status().is_none_or(|status| status.is_server_error()),
+            Self::Rejected { status, .. } => status.is_server_error(),
+            _ => false,
+        }
+    }
+}
+
+impl Display for NotifyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Request(error) => write!(f, "Request error: {}", error),
+            Self::Email(error) => write!(f, "Email error: {}", error),
+            Self::Smtp(error) => write!(f, "SMTP error: {}", error),
+            Self::Invalid(message) => write!(f, "Invalid notification: {}", message),
+            Self::RateLimited { retry_after } => {
+                write!(
+                    f,
+                    "Rate limited, retry after {} seconds",
+                    retry_after.as_secs()
+                )
+            }
+            Self::Rejected { status, errors } if errors.is_empty() => {
+                write!(f, "Rejected with status {}", status)
+            }
+            Self::Rejected { status, errors } => {
+                write!(f, "Rejected with status {}: {}", status, errors.join(", "))
+            }
+        }
+    }
+}
+
+impl From<reqwest::Error> for NotifyError {
+    fn from(error: reqwest::Error) -> Self {
+        NotifyError::Request(error)
+    }
+}
+
+impl From<lettre::error::Error> for NotifyError {
+    fn from(error: lettre::error::Error) -> Self {
+        NotifyError::Email(error)
+    }
+}
+
+impl From<lettre::transport::smtp::Error> for NotifyError {
+    fn from(error: lettre::transport::smtp::Error) -> Self {
+        NotifyError::Smtp(error)
+    }
+}
+
+/// A [`Notifier`] that sends HTML formatted notifications through Pushover.
+pub struct PushoverNotifier {
+    api_key: PushoverKey,
+    group_key: PushoverKey,
+    priority: Option<i8>,
+    sound: Option<String>,
+    device: Option<String>,
+}
+
+impl PushoverNotifier {
+    pub fn new(api_key: PushoverKey, group_key: PushoverKey) -> Self {
+        PushoverNotifier {
+            api_key,
+            group_key,
+            priority: None,
+            sound: None,
+            device: None,
+        }
+    }
+
+    /// Sets the priority of sent notifications. Emergency notifications are retried every minute
+    /// for an hour until acknowledged.
+    pub fn priority(mut self, priority: i8) -> Self {
+        self.priority = Some(priority);
+        self
+    }
+
+    /// Sets the sound that sent notifications are played with instead of the account default.
+    pub fn sound(mut self, sound: impl Into<String>) -> Self {
+        self.sound = Some(sound.into());
+        self
+    }
+
+    /// Sets a comma-separated list of devices to send notifications to instead of all devices.
+    pub fn device(mut self, device: impl Into<String>) -> Self {
+        self.device = Some(device.into());
+        self
+    }
+}
+
+impl Notifier for PushoverNotifier {
+    fn send(&self, title: &str, message: &str) -> Result<(), NotifyError> {
+        self.send_with_priority(title, message, self.priority)
+            .map(|_| ())
+    }
+
+    fn send_low_priority(&self, title: &str, message: &str) -> Result<(), NotifyError> {
+        self.send_with_priority(title, message, Some(LOW_PRIORITY))
+            .map(|_| ())
+    }
+
+    fn max_message_length(&self) -> Option<usize> {
+        Some(PUSHOVER_MAX_MESSAGE_LENGTH)
+    }
+
+    fn send_test(&self, title: &str, message: &str) -> Result<String, NotifyError> {
+        let response = self.send_with_priority(title, message, self.priority)?;
+        Ok(format!(
+            "Sent test notification with Pushover request id {}.",
+            response.request
+        ))
+    }
+}
+
+impl PushoverNotifier {
+    fn send_with_priority(
+        &self,
+        title: &str,
+        message: &str,
+        priority: Option<i8>,
+    ) -> Result<PushoverResponse, NotifyError> {
+        let mut notification = Notification::new(&self.api_key, &self.group_key, message);
+        notification.title(title).html(true);
+
+        if let Some(sound) = &self.sound {
+            notification.sound(sound);
+        }
+        if let Some(device) = &self.device {
+            notification.device(device);
+        }
+
+        if let Some(priority) = priority {
+            notification.priority(priority);
+            if priority == EMERGENCY_PRIORITY {
+                notification.retry(EMERGENCY_RETRY).expire(EMERGENCY_EXPIRE);
+            }
+        }
+
+        let response = notification.send()?;
+        if let Some(receipt) = &response.receipt {
+            info!("Sent emergency notification with receipt {}.", receipt);
+        }
+        Ok(response)
+    }
+}
+
+/// The body of a successful response from the Pushover API.
+#[derive(Debug, Deserialize)]
+pub struct PushoverResponse {
+    /// The id of the request, which can be given to Pushover support when something goes wrong.
+    pub request: String,
+    /// The receipt of an emergency notification, which can be used to check whether it has been
+    /// acknowledged.
+    pub receipt: Option<String>,
+}
 
 #[derive(Serialize)]
 pub struct Notification<'a> {
@@ -15,6 +295,11 @@ pub struct Notification<'a> {
     message: &'a str,
     html: Option<u32>,      // Set to 1 to enable html in message
     monospace: Option<u32>, // Set to 1 to enable monospace font in message
+    priority: Option<i8>,
+    retry: Option<u32>,  // Seconds between retries of emergency notifications
+    expire: Option<u32>, // Seconds until emergency notifications stop being retried
+    sound: Option<&'a str>,
+    device: Option<&'a str>, // Comma-separated device names, or all devices if not set
 }
 
 impl<'a> Notification<'a> {
@@ -26,6 +311,11 @@ impl<'a> Notification<'a> {
             message,
             html: None,
             monospace: None,
+            priority: None,
+            retry: None,
+            expire: None,
+            sound: None,
+            device: None,
         }
     }
 
@@ -34,21 +324,367 @@ impl<'a> Notification<'a> {
         self
     }
 
+    /// Sets whether the message is HTML. Pushover rejects messages that are both HTML and
+    /// monospace, so enabling HTML disables [`monospace`][`Notification::monospace`].
     pub fn html(&mut self, html: bool) -> &mut Self {
         self.html = if html { Some(1) } else { None };
+        if html {
+            self.monospace = None;
+        }
         self
     }
 
+    /// Sets whether the message is shown in a monospace font. Pushover rejects messages that are
+    /// both HTML and monospace, so enabling monospace disables [`html`][`Notification::html`].
     pub fn monospace(&mut self, monospace: bool) -> &mut Self {
         self.monospace = if monospace { Some(1) } else { None };
+        if monospace {
+            self.html = None;
+        }
+        self
+    }
+
+    /// Sets the priority from -2 (lowest) to 2 (emergency). Emergency notifications also require
+    /// [`retry`][`Notification::retry`] and [`expire`][`Notification::expire`] to be set.
+    pub fn priority(&mut self, priority: i8) -> &mut Self {
+        self.priority = Some(priority);
         self
     }
 
-    pub fn send(&self) -> Result<Response, reqwest::Error> {
-        Client::new()
-            .post(PUSHOVER_API_URL)
-            .form(self)
-            .send()?
-            .error_for_status()
+    /// Sets how often in seconds an emergency notification is retried until acknowledged. Must
+    /// be at least 30.
+    pub fn retry(&mut self, retry: u32) -> &mut Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    /// Sets for how many seconds an emergency notification keeps being retried. Must be at most
+    /// 10800 (3 hours).
+    pub fn expire(&mut self, expire: u32) -> &mut Self {
+        self.expire = Some(expire);
+        self
+    }
+
+    /// Sets the sound that the notification is played with. Must be one of [`SOUNDS`].
+    pub fn sound(&mut self, sound: &'a str) -> &mut Self {
+        self.sound = Some(sound);
+        self
+    }
+
+    /// Sets a comma-separated list of the devices to send the notification to. An empty list sends
+    /// it to all devices, which is also the default.
+    pub fn device(&mut self, device: &'a str) -> &mut Self {
+        self.device = if device.is_empty() {
+            None
+        } else {
+            Some(device)
+        };
+        self
+    }
+
+    /// Sends the notification, returning the request id and, for emergency notifications, the
+    /// receipt.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`NotifyError::Invalid`] without sending anything if the priority, retry, expire,
+    /// or sound values are not accepted by Pushover, [`NotifyError::RateLimited`] if Pushover
+    /// rejects the notification because too many have been sent, and [`NotifyError::Rejected`]
+    /// with the errors given by Pushover if it rejects the notification for another reason.
+    pub fn send(&self) -> Result<PushoverResponse, NotifyError> {
+        self.send_to(PUSHOVER_API_URL)
+    }
+
+    fn send_to(&self, url: &str) -> Result<PushoverResponse, NotifyError> {
+        self.validate()?;
+
+        let response = Client::new().post(url).form(self).send()?;
+        if response.status() == StatusCode::TOO_MANY_REQUESTS {
+            if let Some(remaining) = header_number(response.headers(), "X-Limit-App-Remaining") {
+                warn!(
+                    "Pushover rate limited with {} messages remaining this month.",
+                    remaining
+                );
+            }
+            return Err(NotifyError::RateLimited {
+                retry_after: retry_after(response.headers()),
+            });
+        }
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(NotifyError::Rejected {
+                status,
+                errors: pushover_errors(&response.text().unwrap_or_default()),
+            });
+        }
+
+        let response = response.json()?;
+        Ok(response)
+    }
+
+    fn validate(&self) -> Result<(), NotifyError> {
+        if let Some(priority) = self.priority {
+            if !(-2..=EMERGENCY_PRIORITY).contains(&priority) {
+                return Err(NotifyError::Invalid(format!(
+                    "priority must be between -2 and {}, but was {}",
+                    EMERGENCY_PRIORITY, priority
+                )));
+            }
+            if priority == EMERGENCY_PRIORITY && (self.retry.is_none() || self.expire.is_none()) {
+                return Err(NotifyError::Invalid(String::from(
+                    "emergency priority requires retry and expire to be set",
+                )));
+            }
+        }
+        if let Some(retry) = self.retry {
+            if retry < MIN_RETRY {
+                return Err(NotifyError::Invalid(format!(
+                    "retry must be at least {} seconds, but was {}",
+                    MIN_RETRY, retry
+                )));
+            }
+        }
+        if let Some(expire) = self.expire {
+            if expire > MAX_EXPIRE {
+                return Err(NotifyError::Invalid(format!(
+                    "expire must be at most {} seconds, but was {}",
+                    MAX_EXPIRE, expire
+                )));
+            }
+        }
+        if let Some(sound) = self.sound {
+            if !SOUNDS.contains(&sound) {
+                return Err(NotifyError::Invalid(format!("unknown sound: {}", sound)));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The body of an error response from the Pushover API.
+#[derive(Deserialize)]
+struct PushoverErrorResponse {
+    #[serde(default)]
+    errors: Vec<String>,
+}
+
+/// Gets the errors from the body of an error response from Pushover, e.g. "user key is invalid",
+/// or the body itself if it is not the usual JSON, e.g. from a proxy in between.
+fn pushover_errors(body: &str) -> Vec<String> {
+    match serde_json::from_str::<PushoverErrorResponse>(body) {
+        Ok(response) => response.errors,
+        Err(_) if body.trim().is_empty() => Vec::new(),
+        Err(_) => vec![body.trim().to_string()],
+    }
+}
+
+/// Gets how long to wait after being rate limited, from either the `Retry-After` header in
+/// seconds or the `X-Limit-App-Reset` header with the Unix time at which the limit resets.
+fn retry_after(headers: &HeaderMap) -> Duration {
+    if let Some(seconds) = header_number(headers, "Retry-After") {
+        return Duration::from_secs(seconds);
+    }
+    if let Some(reset) = header_number(headers, "X-Limit-App-Reset") {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        return Duration::from_secs(reset.saturating_sub(now));
+    }
+    DEFAULT_RETRY_AFTER
+}
+
+fn header_number(headers: &HeaderMap, name: &str) -> Option<u64> {
+    headers.get(name)?.to_str().ok()?.trim().parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{MockResponse, MockServer};
+
+    fn key() -> PushoverKey {
+        PushoverKey::new("qwertyuiopasdfghjklzxcvbnm0123").unwrap()
+    }
+
+    #[test]
+    fn send_returns_rate_limited_on_too_many_requests() {
+        let server = MockServer::start(vec![MockResponse::new(429, r#"{"status":0}"#)
+            .header("Retry-After", "30")
+            .header("X-Limit-App-Remaining", "0")]);
+        let key = key();
+
+        let result = Notification::new(&key, &key, "message").send_to(server.url());
+
+        assert!(matches!(
+            result,
+            Err(NotifyError::RateLimited { retry_after }) if retry_after == Duration::from_secs(30)
+        ));
+    }
+
+    #[test]
+    fn send_returns_errors_of_rejected_notifications() {
+        let server = MockServer::start(vec![MockResponse::new(
+            400,
+            r#"{"user":"invalid","errors":["user identifier is invalid"],"status":0}"#,
+        )]);
+        let key = key();
+
+        let result = Notification::new(&key, &key, "message").send_to(server.url());
+
+        let error = result.unwrap_err();
+        assert!(matches!(
+            &error,
+            NotifyError::Rejected { status, errors }
+                if *status == StatusCode::BAD_REQUEST && errors.len() == 1
+        ));
+        assert_eq!(
+            error.to_string(),
+            "Rejected with status 400 Bad Request: user identifier is invalid"
+        );
+    }
+
+    #[test]
+    fn pushover_errors_falls_back_to_body() {
+        assert_eq!(pushover_errors(r#"{"status":0}"#), Vec::<String>::new());
+        assert_eq!(pushover_errors(" Bad Gateway\n"), vec!["Bad Gateway"]);
+        assert!(pushover_errors("").is_empty());
+    }
+
+    #[test]
+    fn retry_after_falls_back_to_limit_reset_and_default() {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let mut headers = HeaderMap::new();
+        assert_eq!(retry_after(&headers), DEFAULT_RETRY_AFTER);
+
+        headers.insert(
+            "X-Limit-App-Reset",
+            (now + 120).to_string().parse().unwrap(),
+        );
+        let retry = retry_after(&headers);
+        assert!(retry <= Duration::from_secs(120) && retry >= Duration::from_secs(115));
+
+        headers.insert("Retry-After", "5".parse().unwrap());
+        assert_eq!(retry_after(&headers), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn validate_accepts_emergency_with_retry_and_expire() {
+        let key = key();
+        let mut notification = Notification::new(&key, &key, "message");
+        notification.priority(2).retry(30).expire(10800);
+
+        assert!(notification.validate().is_ok());
+    }
+
+    #[test]
+    fn validate_rejects_emergency_without_retry_and_expire() {
+        let key = key();
+        let mut notification = Notification::new(&key, &key, "message");
+        notification.priority(2);
+
+        assert!(matches!(
+            notification.validate(),
+            Err(NotifyError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn validate_rejects_out_of_range_values() {
+        let key = key();
+
+        let mut notification = Notification::new(&key, &key, "message");
+        notification.priority(3);
+        assert!(notification.validate().is_err());
+
+        let mut notification = Notification::new(&key, &key, "message");
+        notification.priority(2).retry(29).expire(3600);
+        assert!(notification.validate().is_err());
+
+        let mut notification = Notification::new(&key, &key, "message");
+        notification.priority(2).retry(60).expire(10801);
+        assert!(notification.validate().is_err());
+    }
+
+    #[test]
+    fn validate_rejects_unknown_sound() {
+        let key = key();
+
+        let mut notification = Notification::new(&key, &key, "message");
+        notification.sound("cosmic");
+        assert!(notification.validate().is_ok());
+
+        let mut notification = Notification::new(&key, &key, "message");
+        notification.sound("kazoo");
+        assert!(notification.validate().is_err());
+    }
+
+    #[test]
+    fn html_and_monospace_exclude_each_other() {
+        let key = key();
+
+        let mut notification = Notification::new(&key, &key, "message");
+        notification.html(true).monospace(true);
+        let form = serde_urlencoded::to_string(&notification).unwrap();
+        assert!(
+            form.contains("monospace=1") && !form.contains("html"),
+            "{}",
+            form
+        );
+
+        notification.html(true);
+        let form = serde_urlencoded::to_string(&notification).unwrap();
+        assert!(
+            form.contains("html=1") && !form.contains("monospace"),
+            "{}",
+            form
+        );
+
+        // Disabling one leaves the other alone
+        notification.monospace(false);
+        let form = serde_urlencoded::to_string(&notification).unwrap();
+        assert!(form.contains("html=1"), "{}", form);
+    }
+
+    #[test]
+    fn sound_is_only_serialized_when_set() {
+        let key = key();
+
+        let notification = Notification::new(&key, &key, "message");
+        let form = serde_urlencoded::to_string(&notification).unwrap();
+        assert!(!form.contains("sound="));
+
+        let mut notification = Notification::new(&key, &key, "message");
+        notification.sound("cosmic");
+        let form = serde_urlencoded::to_string(&notification).unwrap();
+        assert!(form.contains("&sound=cosmic"));
+    }
+
+    #[test]
+    fn device_is_serialized_when_set() {
+        let key = key();
+
+        let notification = Notification::new(&key, &key, "message");
+        let form = serde_urlencoded::to_string(&notification).unwrap();
+        assert!(!form.contains("device="));
+
+        let mut notification = Notification::new(&key, &key, "message");
+        notification.device("");
+        let form = serde_urlencoded::to_string(&notification).unwrap();
+        assert!(!form.contains("device="));
+
+        let mut notification = Notification::new(&key, &key, "message");
+        notification.device("phone");
+        let form = serde_urlencoded::to_string(&notification).unwrap();
+        assert!(form.contains("&device=phone"));
+
+        let mut notification = Notification::new(&key, &key, "message");
+        notification.device("phone,tablet");
+        let form = serde_urlencoded::to_string(&notification).unwrap();
+        assert!(form.contains("&device=phone%2Ctablet"));
     }
 }
@@ -1,8 +1,11 @@
 use std::{
+    convert::TryFrom,
     fmt::{self, Display, Formatter},
     str::FromStr,
 };
 
+use serde::Deserialize;
+
 const KEY_LENGTH: usize = 30;
 
 /// An immutable Pushover key that is guaranteed to be formatted correctly.
@@ -58,7 +61,8 @@ const KEY_LENGTH: usize = 30;
 /// assert_ne!(valid_key.get(), valid_key_str);
 /// assert_eq!(valid_key.get(), valid_key_str.to_ascii_lowercase());
 /// ```
-#[derive(Debug)]
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "String")]
 pub struct PushoverKey {
     value: String,
 }
@@ -157,6 +161,14 @@ impl FromStr for PushoverKey {
     }
 }
 
+impl TryFrom<String> for PushoverKey {
+    type Error = PushoverKeyError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        PushoverKey::new(value)
+    }
+}
+
 /// An error representing an invalidly formatted [`PushoverKey`].
 #[derive(Debug)]
 pub enum PushoverKeyError {
@@ -0,0 +1,110 @@
+use super::{Notifier, NotifyError};
+use crate::{event::diff::Diff, retry::RetryPolicy};
+
+/// A [`Notifier`] that sends through another notifier, retrying with backoff when sending fails
+/// with a transient error, so a short outage of the service does not lose the notification.
+pub struct RetryingNotifier {
+    notifier: Box<dyn Notifier>,
+    retry: RetryPolicy,
+}
+
+impl RetryingNotifier {
+    pub fn new(notifier: Box<dyn Notifier>, retry: RetryPolicy) -> Self {
+        RetryingNotifier { notifier, retry }
+    }
+}
+
+impl Notifier for RetryingNotifier {
+    fn send(&self, title: &str, message: &str) -> Result<(), NotifyError> {
+        self.retry.retry(
+            || self.notifier.send(title, message),
+            NotifyError::is_transient,
+        )
+    }
+
+    fn send_low_priority(&self, title: &str, message: &str) -> Result<(), NotifyError> {
+        self.retry.retry(
+            || self.notifier.send_low_priority(title, message),
+            NotifyError::is_transient,
+        )
+    }
+
+    fn max_message_length(&self) -> Option<usize> {
+        self.notifier.max_message_length()
+    }
+
+    fn send_diff(&self, diff: &Diff) -> Option<Result<(), NotifyError>> {
+        // The first attempt tells whether the notifier sends diffs at all
+        let mut first = Some(self.notifier.send_diff(diff)?);
+        let result = self.retry.retry(
+            || match first.take() {
+                Some(result) => result,
+                None => self.notifier.send_diff(diff).unwrap_or(Ok(())),
+            },
+            NotifyError::is_transient,
+        );
+        Some(result)
+    }
+
+    // Not retried, since the point of a test notification is to see whether sending works
+    fn send_test(&self, title: &str, message: &str) -> Result<String, NotifyError> {
+        self.notifier.send_test(title, message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{
+        notification::{Notification, PushoverKey},
+        test_util::{MockResponse, MockServer},
+    };
+
+    /// A notifier that sends Pushover notifications to a mock server instead of Pushover.
+    struct MockPushoverNotifier(String);
+
+    impl Notifier for MockPushoverNotifier {
+        fn send(&self, title: &str, message: &str) -> Result<(), NotifyError> {
+            let key = PushoverKey::new("qwertyuiopasdfghjklzxcvbnm0123").unwrap();
+            Notification::new(&key, &key, message)
+                .title(title)
+                .send_to(&self.0)
+                .map(|_| ())
+        }
+    }
+
+    fn retrying(server: &MockServer) -> RetryingNotifier {
+        RetryingNotifier::new(
+            Box::new(MockPushoverNotifier(server.url().to_string())),
+            RetryPolicy::new(3, Duration::from_millis(1), 2.0),
+        )
+    }
+
+    #[test]
+    fn send_retries_server_errors_until_sent() {
+        let server = MockServer::start(vec![
+            MockResponse::new(500, ""),
+            MockResponse::new(503, ""),
+            MockResponse::new(200, r#"{"status":1,"request":"abc123"}"#),
+        ]);
+
+        assert!(retrying(&server).send("title", "message").is_ok());
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[test]
+    fn send_does_not_retry_client_errors() {
+        let server = MockServer::start(vec![
+            MockResponse::new(400, r#"{"status":0}"#),
+            MockResponse::new(200, r#"{"status":1,"request":"abc123"}"#),
+        ]);
+
+        assert!(matches!(
+            retrying(&server).send("title", "message"),
+            Err(NotifyError::Rejected { .. })
+        ));
+        assert_eq!(server.requests().len(), 1);
+    }
+}
@@ -0,0 +1,55 @@
+use reqwest::blocking::Client;
+use serde::Serialize;
+
+use super::{Notifier, NotifyError};
+
+const TELEGRAM_API_URL: &str = "https://api.telegram.org";
+const TELEGRAM_MAX_MESSAGE_LENGTH: usize = 4096;
+
+/// A [`Notifier`] that sends notifications to a Telegram chat through a bot.
+pub struct TelegramNotifier {
+    token: String,
+    chat_id: String,
+}
+
+#[derive(Serialize)]
+struct SendMessage<'a> {
+    chat_id: &'a str,
+    text: &'a str,
+    parse_mode: &'a str,
+}
+
+impl TelegramNotifier {
+    pub fn new(token: impl Into<String>, chat_id: impl Into<String>) -> Self {
+        TelegramNotifier {
+            token: token.into(),
+            chat_id: chat_id.into(),
+        }
+    }
+}
+
+impl Notifier for TelegramNotifier {
+    fn send(&self, title: &str, message: &str) -> Result<(), NotifyError> {
+        // Telegram has no notion of a title, so put it in bold at the top of the message
+        let text = format!("<b>{}</b>\n{}", title, message);
+
+        Client::new()
+            .post(format!(
+                "{}/bot{}/sendMessage",
+                TELEGRAM_API_URL, self.token
+            ))
+            .json(&SendMessage {
+                chat_id: &self.chat_id,
+                text: &text,
+                parse_mode: "HTML",
+            })
+            .send()?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    fn max_message_length(&self) -> Option<usize> {
+        // Leave room for the title, which is sent as part of the text
+        Some(TELEGRAM_MAX_MESSAGE_LENGTH - 256)
+    }
+}
@@ -0,0 +1,94 @@
+use reqwest::blocking::Client;
+use serde::Serialize;
+
+use super::{Notifier, NotifyError};
+use crate::event::diff::Diff;
+
+/// A [`Notifier`] that posts notifications as JSON to any webhook, e.g. for custom automation.
+///
+/// Notifications about changes are posted as the changed events, i.e.
+/// `{"added": [...], "removed": [...], "changed": [{"old": ..., "new": ...}]}` with full event
+/// objects, and other notifications, e.g. alerts, as `{"title": ..., "message": ...}`.
+pub struct GenericWebhookNotifier {
+    url: String,
+    headers: Vec<(String, String)>,
+}
+
+#[derive(Serialize)]
+struct MessagePayload<'a> {
+    title: &'a str,
+    message: &'a str,
+}
+
+impl GenericWebhookNotifier {
+    pub fn new(url: impl Into<String>) -> Self {
+        GenericWebhookNotifier {
+            url: url.into(),
+            headers: Vec::new(),
+        }
+    }
+
+    /// Adds a header to send with every request, e.g. `Authorization` with a token.
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Posts `body` as JSON with the headers of the notifier.
+    fn post(&self, body: &impl Serialize) -> Result<(), NotifyError> {
+        let request = Client::new().post(&self.url).json(body);
+        let request = self.headers.iter().fold(request, |request, (name, value)| {
+            request.header(name, value)
+        });
+        request.send()?.error_for_status()?;
+        Ok(())
+    }
+}
+
+impl Notifier for GenericWebhookNotifier {
+    fn send(&self, title: &str, message: &str) -> Result<(), NotifyError> {
+        self.post(&MessagePayload { title, message })
+    }
+
+    fn send_diff(&self, diff: &Diff) -> Option<Result<(), NotifyError>> {
+        Some(self.post(diff))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::test_util::{self, MockResponse, MockServer};
+
+    #[test]
+    fn send_diff_posts_events_with_headers() {
+        let server = MockServer::start(vec![MockResponse::new(200, "")]);
+        let notifier = GenericWebhookNotifier::new(server.url()).header("X-Token", "secret");
+        let (old, new) = (HashSet::new(), test_util::events(&["event1"]));
+
+        let result = notifier.send_diff(&Diff::between(&old, &new));
+
+        assert!(matches!(result, Some(Ok(()))));
+        let request = &server.requests()[0];
+        assert!(
+            request.to_ascii_lowercase().contains("x-token: secret"),
+            "{}",
+            request
+        );
+        let body = &request[request.find("\r\n\r\n").unwrap() + 4..];
+        let json: serde_json::Value = serde_json::from_str(body).unwrap();
+        assert_eq!(json["added"][0]["id"], "event1");
+        assert_eq!(json["removed"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn send_fails_on_error_status() {
+        let server = MockServer::start(vec![MockResponse::new(500, "")]);
+
+        let result = GenericWebhookNotifier::new(server.url()).send("title", "message");
+
+        assert!(matches!(result, Err(NotifyError::Request(_))));
+    }
+}
@@ -0,0 +1,133 @@
+use std::{
+    collections::HashSet,
+    error::Error,
+    fs::{self, File},
+    path::PathBuf,
+};
+
+use crate::event::{diff::Diff, Event};
+
+/// The ids of events that have been notified about as new, persisted to a JSON file so the same
+/// events are not notified about again after a restart, even if the events themselves were not
+/// stored.
+pub struct NotifiedIds {
+    path: PathBuf,
+    ids: HashSet<String>,
+}
+
+impl NotifiedIds {
+    /// Loads the ids stored at `path`, or no ids if the file does not exist yet.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file exists but cannot be read or parsed.
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self, Box<dyn Error>> {
+        let path = path.into();
+        let ids = if path.exists() {
+            serde_json::from_reader(File::open(&path)?)?
+        } else {
+            HashSet::new()
+        };
+        Ok(NotifiedIds { path, ids })
+    }
+
+    /// Creates an empty set of ids that is saved to `path`, e.g. when loading it failed.
+    pub fn empty(path: impl Into<PathBuf>) -> Self {
+        NotifiedIds {
+            path: path.into(),
+            ids: HashSet::new(),
+        }
+    }
+
+    /// Removes the added events that have already been notified about from `diff`.
+    pub fn retain_unnotified<'a>(&self, mut diff: Diff<'a>) -> Diff<'a> {
+        diff.added.retain(|event| !self.ids.contains(&event.id));
+        diff
+    }
+
+    /// Records that the added events of `diff` have been notified about and saves the ids. Ids of
+    /// events no longer in `events` are forgotten, so an event that is removed and later added
+    /// again is notified about again.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the ids cannot be written to the file.
+    pub fn record(&mut self, diff: &Diff, events: &HashSet<Event>) -> Result<(), Box<dyn Error>> {
+        let current: HashSet<&str> = events.iter().map(|event| event.id.as_str()).collect();
+        self.ids.retain(|id| current.contains(id.as_str()));
+        self.ids
+            .extend(diff.added.iter().map(|event| event.id.clone()));
+
+        let mut ids: Vec<_> = self.ids.iter().collect();
+        ids.sort();
+
+        // Write to a temporary file first, so an interrupted write leaves the old ids intact
+        let mut temp_path = self.path.clone().into_os_string();
+        temp_path.push(".tmp");
+        fs::write(&temp_path, serde_json::to_vec(&ids)?)?;
+        fs::rename(&temp_path, &self.path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::test_util::events;
+
+    #[test]
+    fn load_without_file_has_no_ids() {
+        let directory = TempDir::new().unwrap();
+        let notified = NotifiedIds::load(directory.path().join("notified.json")).unwrap();
+
+        let (old, new) = (events(&[]), events(&["event1"]));
+        assert_eq!(
+            notified
+                .retain_unnotified(Diff::between(&old, &new))
+                .added
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn recorded_ids_are_not_notified_again_after_restart() {
+        let directory = TempDir::new().unwrap();
+        let path = directory.path().join("notified.json");
+        let (old, new) = (events(&[]), events(&["event1", "event2"]));
+
+        let mut notified = NotifiedIds::load(&path).unwrap();
+        let diff = notified.retain_unnotified(Diff::between(&old, &new));
+        notified.record(&diff, &new).unwrap();
+
+        // Restart with the events lost, but the notified ids intact
+        let notified = NotifiedIds::load(&path).unwrap();
+        let newer = events(&["event1", "event2", "event3"]);
+        let diff = notified.retain_unnotified(Diff::between(&old, &newer));
+        assert_eq!(diff.added.len(), 1);
+        assert_eq!(diff.added[0].id, "event3");
+    }
+
+    #[test]
+    fn record_forgets_removed_events() {
+        let directory = TempDir::new().unwrap();
+        let path = directory.path().join("notified.json");
+        let (none, one) = (events(&[]), events(&["event1"]));
+
+        let mut notified = NotifiedIds::load(&path).unwrap();
+        notified.record(&Diff::between(&none, &one), &one).unwrap();
+        notified.record(&Diff::between(&one, &none), &none).unwrap();
+
+        // The event is notified about again when it reappears, also after a restart
+        let notified = NotifiedIds::load(&path).unwrap();
+        assert_eq!(
+            notified
+                .retain_unnotified(Diff::between(&none, &one))
+                .added
+                .len(),
+            1
+        );
+    }
+}
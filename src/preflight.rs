@@ -0,0 +1,89 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    fs, io,
+    path::{Path, PathBuf},
+    process,
+};
+
+/// An error representing a directory that cannot be created or written to.
+#[derive(Debug)]
+pub struct DirectoryError {
+    directory: PathBuf,
+    error: io::Error,
+}
+
+impl Display for DirectoryError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Directory {:?} is not writable: {}",
+            self.directory, self.error
+        )
+    }
+}
+
+/// Creates `directory` and its parents if missing, and checks that files can be written to it by
+/// writing and removing a probe file.
+///
+/// # Errors
+///
+/// Returns an error if the directory cannot be created or a file cannot be written to it.
+pub fn ensure_writable(directory: &Path) -> Result<(), DirectoryError> {
+    let error = |error| DirectoryError {
+        directory: directory.to_path_buf(),
+        error,
+    };
+
+    fs::create_dir_all(directory).map_err(error)?;
+    let probe = directory.join(format!(".ktkbot-probe-{}", process::id()));
+    fs::write(&probe, b"").map_err(error)?;
+    fs::remove_file(&probe).map_err(error)
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn ensure_writable_creates_missing_directories() {
+        let directory = TempDir::new().unwrap();
+        let logs = directory.path().join("data").join("logs");
+
+        ensure_writable(&logs).unwrap();
+
+        assert!(logs.is_dir());
+        assert_eq!(fs::read_dir(&logs).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn ensure_writable_rejects_directories_that_cannot_be_created() {
+        let directory = TempDir::new().unwrap();
+        let file = directory.path().join("events.json");
+        fs::write(&file, "[]").unwrap();
+
+        assert!(ensure_writable(&file.join("logs")).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn ensure_writable_rejects_read_only_directories() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let directory = TempDir::new().unwrap();
+        fs::set_permissions(directory.path(), fs::Permissions::from_mode(0o555)).unwrap();
+        // Privileged users can write to read-only directories, so there is nothing to check
+        let privileged = fs::write(directory.path().join("probe"), b"").is_ok();
+
+        let result = ensure_writable(directory.path());
+
+        fs::set_permissions(directory.path(), fs::Permissions::from_mode(0o755)).unwrap();
+        if !privileged {
+            let error = result.unwrap_err();
+            assert!(error
+                .to_string()
+                .starts_with(&format!("Directory {:?}", directory.path())));
+        }
+    }
+}
@@ -0,0 +1,134 @@
+#[cfg(feature = "async")]
+use std::future::Future;
+use std::{fmt::Display, thread, time::Duration};
+
+use log_extern::warn;
+use rand::Rng;
+
+/// The highest number of attempts that can be configured.
+pub const MAX_ATTEMPTS: u32 = 20;
+
+/// The longest backoff between two attempts, however many retries have been made.
+const MAX_BACKOFF: Duration = Duration::from_secs(10 * 60);
+
+/// A policy for retrying fallible operations with exponential backoff and jitter.
+///
+/// The delay before retry number `n` (starting at 1) is `base_delay * multiplier^(n - 1)`, capped
+/// at ten minutes, plus a random jitter of up to half of that delay.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    base_delay: Duration,
+    multiplier: f64,
+}
+
+impl RetryPolicy {
+    pub fn new(max_attempts: u32, base_delay: Duration, multiplier: f64) -> Self {
+        RetryPolicy {
+            max_attempts,
+            base_delay,
+            multiplier,
+        }
+    }
+
+    /// Gets the delay to wait before the given retry, including jitter.
+    pub(crate) fn delay(&self, retry: u32) -> Duration {
+        let exponent = retry.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let seconds = self.base_delay.as_secs_f64() * self.multiplier.powi(exponent);
+        // Capping before converting also keeps huge (or infinite) backoffs from overflowing
+        let backoff = if seconds < MAX_BACKOFF.as_secs_f64() {
+            Duration::from_secs_f64(seconds)
+        } else {
+            MAX_BACKOFF
+        };
+        let jitter = backoff.mul_f64(rand::thread_rng().gen_range(0.0..0.5));
+        backoff + jitter
+    }
+
+    /// Runs `operation` until it succeeds, fails with an error for which `should_retry` returns
+    /// `false`, or the maximum number of attempts has been made.
+    pub(crate) fn retry<T, E>(
+        &self,
+        mut operation: impl FnMut() -> Result<T, E>,
+        should_retry: impl Fn(&E) -> bool,
+    ) -> Result<T, E>
+    where
+        E: Display,
+    {
+        let mut attempt = 1;
+        loop {
+            match operation() {
+                Err(error) if attempt < self.max_attempts && should_retry(&error) => {
+                    let delay = self.delay(attempt);
+                    warn!(
+                        "Attempt {} of {} failed: {}. Retrying in {:?}...",
+                        attempt, self.max_attempts, error, delay
+                    );
+                    thread::sleep(delay);
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Like [`retry`][`RetryPolicy::retry`], but for asynchronous operations, waiting without
+    /// blocking the thread.
+    #[cfg(feature = "async")]
+    pub(crate) async fn retry_async<T, E, F, Fut>(
+        &self,
+        mut operation: F,
+        should_retry: impl Fn(&E) -> bool,
+    ) -> Result<T, E>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+        E: Display,
+    {
+        let mut attempt = 1;
+        loop {
+            match operation().await {
+                Err(error) if attempt < self.max_attempts && should_retry(&error) => {
+                    let delay = self.delay(attempt);
+                    warn!(
+                        "Attempt {} of {} failed: {}. Retrying in {:?}...",
+                        attempt, self.max_attempts, error, delay
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy::new(3, Duration::from_secs(1), 2.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delay_grows_exponentially() {
+        let policy = RetryPolicy::new(3, Duration::from_secs(1), 2.0);
+
+        let delay = policy.delay(3);
+
+        assert!(delay >= Duration::from_secs(4) && delay < Duration::from_secs(6));
+    }
+
+    #[test]
+    fn delay_is_capped_for_many_retries() {
+        let policy = RetryPolicy::new(u32::MAX, Duration::from_secs(1), 2.0);
+
+        for retry in &[64, 1100, u32::MAX] {
+            let delay = policy.delay(*retry);
+            assert!(delay >= MAX_BACKOFF && delay < MAX_BACKOFF.mul_f64(1.5));
+        }
+    }
+}
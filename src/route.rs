@@ -0,0 +1,303 @@
+use std::{panic, thread};
+
+use log_extern::{info, warn};
+
+use crate::{
+    event::{diff::Diff, filter, Event},
+    message::MessageFormat,
+    notification::{Notifier, NotifyError},
+};
+
+/// A notifier that only receives notifications about events with a title containing any of
+/// `includes`. A route without `includes` receives notifications about all events.
+struct Route {
+    includes: Vec<String>,
+    notifier: Box<dyn Notifier>,
+}
+
+impl Route {
+    /// Describes the route in logs, e.g. "route for junior, padel".
+    fn name(&self) -> String {
+        if self.includes.is_empty() {
+            String::from("route for all events")
+        } else {
+            format!("route for {}", self.includes.join(", "))
+        }
+    }
+}
+
+/// Sends notifications about each event to the notifiers of all routes matching the event, or to
+/// a default notifier if no route matches it.
+pub struct Router {
+    routes: Vec<Route>,
+    default: Box<dyn Notifier>,
+    message: MessageFormat,
+    require_all: bool,
+}
+
+impl Router {
+    /// Creates a router without routes, which sends all notifications to `default`.
+    pub fn new(default: Box<dyn Notifier>) -> Self {
+        Router {
+            routes: Vec::new(),
+            default,
+            message: MessageFormat::default(),
+            require_all: false,
+        }
+    }
+
+    /// Sets the format of the notification messages.
+    pub fn message_format(mut self, format: MessageFormat) -> Self {
+        self.message = format;
+        self
+    }
+
+    /// Sets whether sending fails when any notifier fails, instead of only when all of them do.
+    pub fn require_all(mut self, require_all: bool) -> Self {
+        self.require_all = require_all;
+        self
+    }
+
+    /// Adds a route that sends notifications about events matching `includes` to `notifier`.
+    pub fn route(mut self, includes: Vec<String>, notifier: Box<dyn Notifier>) -> Self {
+        self.routes.push(Route { includes, notifier });
+        self
+    }
+
+    /// Gets the notifier that receives notifications about events matching no route.
+    pub fn default_notifier(&self) -> &dyn Notifier {
+        self.default.as_ref()
+    }
+
+    /// Sends a notification about the part of `diff` matching each route to the notifier of the
+    /// route, and a notification about the rest to the default notifier. Notifiers with nothing to
+    /// notify about are skipped.
+    ///
+    /// The notifiers are sent to concurrently, so a slow notifier does not hold up the others, and
+    /// the outcome of each is logged. Sending only fails if all notifiers fail, or if any notifier
+    /// fails when [`require_all`][Self::require_all] is set, with the error of the first failing
+    /// notifier.
+    pub fn send(&self, diff: &Diff) -> Result<(), NotifyError> {
+        self.send_with(diff, crate::send_notification)
+    }
+
+    /// Sends the daily digest about the added events of `diff`, routed like [`send`][Self::send].
+    pub fn send_digest(&self, diff: &Diff) -> Result<(), NotifyError> {
+        self.send_with(diff, crate::send_digest_notification)
+    }
+
+    fn send_with(
+        &self,
+        diff: &Diff,
+        send: fn(&Diff, &dyn Notifier, &MessageFormat) -> Result<(), NotifyError>,
+    ) -> Result<(), NotifyError> {
+        let mut deliveries: Vec<(String, &dyn Notifier, Diff)> = Vec::new();
+        for route in &self.routes {
+            let matching = retain(diff, |event| filter::is_match(event, &route.includes, &[]));
+            if !matching.is_empty() {
+                deliveries.push((route.name(), route.notifier.as_ref(), matching));
+            }
+        }
+
+        let unrouted = retain(diff, |event| {
+            !self
+                .routes
+                .iter()
+                .any(|route| filter::is_match(event, &route.includes, &[]))
+        });
+        if !unrouted.is_empty() {
+            deliveries.push((
+                String::from("default notifier"),
+                self.default.as_ref(),
+                unrouted,
+            ));
+        }
+
+        let message = &self.message;
+        let results: Vec<_> = thread::scope(|scope| {
+            let handles: Vec<_> = deliveries
+                .iter()
+                .map(|(name, notifier, diff)| {
+                    (name, scope.spawn(move || send(diff, *notifier, message)))
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|(name, handle)| match handle.join() {
+                    Ok(result) => (name, result),
+                    // Panic as if the notifier had been sent to on this thread
+                    Err(payload) => panic::resume_unwind(payload),
+                })
+                .collect()
+        });
+
+        let mut sent = false;
+        let mut failure = None;
+        for (name, result) in results {
+            match result {
+                Ok(()) => {
+                    info!("Sent notification to {}.", name);
+                    sent = true;
+                }
+                Err(error) => {
+                    warn!("Failed to send notification to {}: {}", name, error);
+                    failure.get_or_insert(error);
+                }
+            }
+        }
+
+        match failure {
+            Some(error) if self.require_all || !sent => Err(error),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Keeps only the events of `diff` for which `predicate` is true. Changed events are kept if the
+/// predicate is true for either the old or the new version.
+fn retain<'a>(diff: &Diff<'a>, predicate: impl Fn(&Event) -> bool) -> Diff<'a> {
+    Diff {
+        added: diff
+            .added
+            .iter()
+            .copied()
+            .filter(|event| predicate(event))
+            .collect(),
+        removed: diff
+            .removed
+            .iter()
+            .copied()
+            .filter(|event| predicate(event))
+            .collect(),
+        changed: diff
+            .changed
+            .iter()
+            .filter(|change| predicate(change.old) || predicate(change.new))
+            .cloned()
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashSet,
+        sync::{Arc, Mutex},
+    };
+
+    use super::*;
+    use crate::test_util::titled_event;
+
+    /// A notifier that records the messages it is asked to send.
+    struct RecordingNotifier(Arc<Mutex<Vec<String>>>);
+
+    impl Notifier for RecordingNotifier {
+        fn send(&self, _title: &str, message: &str) -> Result<(), NotifyError> {
+            self.0.lock().unwrap().push(message.to_string());
+            Ok(())
+        }
+    }
+
+    /// A notifier that fails to send anything.
+    struct FailingNotifier;
+
+    impl Notifier for FailingNotifier {
+        fn send(&self, _title: &str, _message: &str) -> Result<(), NotifyError> {
+            Err(NotifyError::Invalid(String::from("failed")))
+        }
+    }
+
+    fn recording_notifier() -> (Box<dyn Notifier>, Arc<Mutex<Vec<String>>>) {
+        let messages = Arc::new(Mutex::new(Vec::new()));
+        (Box::new(RecordingNotifier(Arc::clone(&messages))), messages)
+    }
+
+    #[test]
+    fn send_routes_events_to_all_matching_routes_and_default() {
+        let (junior, junior_messages) = recording_notifier();
+        let (tennis, tennis_messages) = recording_notifier();
+        let (default, default_messages) = recording_notifier();
+        let router = Router::new(default)
+            .route(vec![String::from("junior")], junior)
+            .route(vec![String::from("tennis")], tennis);
+
+        let old = HashSet::new();
+        let new: HashSet<_> = vec![
+            titled_event("event1", "Tennis for junior"),
+            titled_event("event2", "Padel"),
+        ]
+        .into_iter()
+        .collect();
+        router.send(&Diff::between(&old, &new)).unwrap();
+
+        let (junior, tennis, default) = (
+            junior_messages.lock().unwrap().join("\n"),
+            tennis_messages.lock().unwrap().join("\n"),
+            default_messages.lock().unwrap().join("\n"),
+        );
+        assert!(junior.contains("Tennis for junior") && !junior.contains("Padel"));
+        assert!(tennis.contains("Tennis for junior") && !tennis.contains("Padel"));
+        assert!(default.contains("Padel") && !default.contains("Tennis for junior"));
+    }
+
+    #[test]
+    fn send_skips_routes_without_matching_events() {
+        let (junior, junior_messages) = recording_notifier();
+        let (default, default_messages) = recording_notifier();
+        let router = Router::new(default).route(vec![String::from("junior")], junior);
+
+        let old = HashSet::new();
+        let new: HashSet<_> = vec![titled_event("event1", "Padel")].into_iter().collect();
+        router.send(&Diff::between(&old, &new)).unwrap();
+
+        assert!(junior_messages.lock().unwrap().is_empty());
+        assert_eq!(default_messages.lock().unwrap().len(), 1);
+    }
+
+    fn junior_and_padel() -> HashSet<Event> {
+        vec![
+            titled_event("event1", "Junior tennis"),
+            titled_event("event2", "Padel"),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    #[test]
+    fn send_succeeds_when_another_notifier_fails() {
+        let (junior, junior_messages) = recording_notifier();
+        let router =
+            Router::new(Box::new(FailingNotifier)).route(vec![String::from("junior")], junior);
+
+        let (old, new) = (HashSet::new(), junior_and_padel());
+        let result = router.send(&Diff::between(&old, &new));
+
+        assert!(result.is_ok());
+        assert_eq!(junior_messages.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn send_fails_when_any_notifier_fails_if_all_are_required() {
+        let (junior, junior_messages) = recording_notifier();
+        let router = Router::new(Box::new(FailingNotifier))
+            .route(vec![String::from("junior")], junior)
+            .require_all(true);
+
+        let (old, new) = (HashSet::new(), junior_and_padel());
+        let result = router.send(&Diff::between(&old, &new));
+
+        assert!(matches!(result, Err(NotifyError::Invalid(_))));
+        // The other notifier is still sent to
+        assert_eq!(junior_messages.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn send_fails_when_all_notifiers_fail() {
+        let router = Router::new(Box::new(FailingNotifier))
+            .route(vec![String::from("junior")], Box::new(FailingNotifier));
+
+        let (old, new) = (HashSet::new(), junior_and_padel());
+
+        assert!(router.send(&Diff::between(&old, &new)).is_err());
+    }
+}
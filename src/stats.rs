@@ -0,0 +1,142 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt::{self, Display, Formatter},
+};
+
+use chrono::{DateTime, Datelike, FixedOffset, Weekday};
+
+use crate::event::{filter, Event};
+
+const WEEKDAYS: [Weekday; 7] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+    Weekday::Sun,
+];
+
+/// A summary of a set of events, displayed as one "key: value" line per statistic so it is easy
+/// to grep.
+#[derive(Debug, Default)]
+pub struct Stats {
+    total: usize,
+    /// The number of events on each weekday, starting from Monday.
+    by_weekday: [usize; 7],
+    earliest: Option<DateTime<FixedOffset>>,
+    latest: Option<DateTime<FixedOffset>>,
+    /// The number of events at each level, for events with a level in their class info.
+    by_level: BTreeMap<String, usize>,
+    unknown_level: usize,
+}
+
+impl Stats {
+    /// Computes the statistics of `events`.
+    pub fn of(events: &HashSet<Event>) -> Self {
+        let mut stats = Stats {
+            total: events.len(),
+            earliest: events.iter().map(|event| event.date_time).min(),
+            latest: events.iter().map(|event| event.date_time).max(),
+            ..Stats::default()
+        };
+        for event in events {
+            stats.by_weekday[event.date_time.weekday().num_days_from_monday() as usize] += 1;
+            match filter::level(event) {
+                Some(level) => *stats.by_level.entry(level.to_string()).or_insert(0) += 1,
+                None => stats.unknown_level += 1,
+            }
+        }
+        stats
+    }
+}
+
+impl Display for Stats {
+    /// Writes the statistics as lines such as "weekday Mon: 3". Levels are only listed if any
+    /// event has a level.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "total: {}", self.total)?;
+        let format_date = |date_time: Option<DateTime<FixedOffset>>| {
+            date_time.map_or_else(
+                || String::from("none"),
+                |date| date.format("%F %R").to_string(),
+            )
+        };
+        writeln!(f, "earliest: {}", format_date(self.earliest))?;
+        writeln!(f, "latest: {}", format_date(self.latest))?;
+        for (weekday, count) in WEEKDAYS.iter().zip(&self.by_weekday) {
+            writeln!(f, "weekday {}: {}", weekday, count)?;
+        }
+        if !self.by_level.is_empty() {
+            for (level, count) in &self.by_level {
+                writeln!(f, "level {}: {}", level, count)?;
+            }
+            writeln!(f, "level unknown: {}", self.unknown_level)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn event(id: &str, day: u32, level: Option<&str>) -> Event {
+        let mut event = Event::new();
+        event.id = String::from(id);
+        event.date_time = FixedOffset::east(2 * 3600)
+            .ymd(2021, 6, day)
+            .and_hms(18, 0, 0);
+        if let Some(level) = level {
+            event
+                .class_info_map
+                .insert(String::from("Niveau"), String::from(level));
+        }
+        event
+    }
+
+    #[test]
+    fn stats_summarize_events() {
+        let events = vec![
+            event("event1", 28, Some("Øvet")),
+            event("event2", 30, Some("Begynder")),
+            event("event3", 30, Some("Øvet")),
+            event("event4", 29, None),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(
+            Stats::of(&events).to_string(),
+            "total: 4\n\
+             earliest: 2021-06-28 18:00\n\
+             latest: 2021-06-30 18:00\n\
+             weekday Mon: 1\n\
+             weekday Tue: 1\n\
+             weekday Wed: 2\n\
+             weekday Thu: 0\n\
+             weekday Fri: 0\n\
+             weekday Sat: 0\n\
+             weekday Sun: 0\n\
+             level Begynder: 1\n\
+             level Øvet: 2\n\
+             level unknown: 1\n"
+        );
+    }
+
+    #[test]
+    fn stats_leave_out_levels_when_none_are_known() {
+        let events = vec![event("event1", 28, None)].into_iter().collect();
+
+        let stats = Stats::of(&events).to_string();
+
+        assert!(stats.starts_with("total: 1\n"), "{}", stats);
+        assert!(!stats.contains("level"), "{}", stats);
+        assert_eq!(
+            Stats::of(&HashSet::new()).to_string().lines().nth(1),
+            Some("earliest: none")
+        );
+    }
+}
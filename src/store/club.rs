@@ -0,0 +1,108 @@
+use std::collections::HashSet;
+
+use super::{Store, StoreError};
+use crate::{
+    club,
+    event::{
+        diff::{Change, Diff},
+        Event,
+    },
+};
+
+/// A [`Store`] for following more than one club, which keeps the events of each club in a store of
+/// its own. The ids of the events are namespaced by club like the events fetched by
+/// [`Clubs`][crate::club::Clubs], but are stored without the namespace.
+pub struct ClubStore {
+    stores: Vec<(String, Box<dyn Store>)>,
+}
+
+impl ClubStore {
+    /// Creates a store from the name of each club and the store for its events.
+    pub fn new(stores: Vec<(String, Box<dyn Store>)>) -> Self {
+        ClubStore { stores }
+    }
+}
+
+impl Store for ClubStore {
+    fn load(&self) -> Result<Option<HashSet<Event>>, StoreError> {
+        let mut events = HashSet::new();
+        for (name, store) in &self.stores {
+            match store.load()? {
+                Some(club_events) => events.extend(
+                    club_events
+                        .into_iter()
+                        .map(|event| club::namespaced(name, event)),
+                ),
+                // Fetch the events of all clubs again rather than notifying about every event of
+                // the club without stored events
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(events))
+    }
+
+    fn save_diff(&mut self, events: &HashSet<Event>, diff: &Diff) -> Result<(), StoreError> {
+        for (name, store) in self.stores.iter_mut() {
+            let name: &str = name;
+            let strip = |event: &Event| club::without_namespace(name, event);
+
+            let club_events: HashSet<Event> = events.iter().filter_map(strip).collect();
+            let added: Vec<Event> = diff.added.iter().copied().filter_map(strip).collect();
+            let removed: Vec<Event> = diff.removed.iter().copied().filter_map(strip).collect();
+            let changed: Vec<(Event, Event)> = diff
+                .changed
+                .iter()
+                .filter_map(|change| Some((strip(change.old)?, strip(change.new)?)))
+                .collect();
+
+            let club_diff = Diff {
+                added: added.iter().collect(),
+                removed: removed.iter().collect(),
+                changed: changed
+                    .iter()
+                    .map(|(old, new)| Change { old, new })
+                    .collect(),
+            };
+            store.save_diff(&club_events, &club_diff)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::{event, store::JsonStore, test_util::event};
+
+    #[test]
+    fn events_are_stored_per_club_without_namespace() {
+        let directory = TempDir::new().unwrap();
+        let tennis = directory.path().join("tennis.json");
+        let padel = directory.path().join("padel.json");
+        let stores: Vec<(String, Box<dyn Store>)> = vec![
+            (String::from("tennis"), Box::new(JsonStore::new(&tennis, 0))),
+            (String::from("padel"), Box::new(JsonStore::new(&padel, 0))),
+        ];
+        let mut store = ClubStore::new(stores);
+        assert!(store.load().unwrap().is_none());
+
+        let events: HashSet<Event> = vec![
+            club::namespaced("tennis", event("hold1")),
+            club::namespaced("padel", event("hold1")),
+            club::namespaced("padel", event("hold2")),
+        ]
+        .into_iter()
+        .collect();
+        let no_events = HashSet::new();
+        store
+            .save_diff(&events, &Diff::between(&no_events, &events))
+            .unwrap();
+
+        assert_eq!(event::deserialize_events(&tennis).unwrap().len(), 1);
+        let padel_events = event::deserialize_events(&padel).unwrap();
+        assert!(padel_events.contains(&event("hold1")) && padel_events.contains(&event("hold2")));
+        assert_eq!(store.load().unwrap(), Some(events));
+    }
+}
@@ -0,0 +1,104 @@
+use std::{collections::HashSet, path::PathBuf};
+
+use log_extern::warn;
+
+use super::{Store, StoreError};
+use crate::event::{self, diff::Diff, Event};
+
+/// A [`Store`] that keeps all events in a single JSON file, which is rewritten on every save, and
+/// backed up first whenever the events change.
+pub struct JsonStore {
+    path: PathBuf,
+    backups: usize,
+}
+
+impl JsonStore {
+    /// Creates a store that writes to `path` and keeps `backups` previous versions of it. See
+    /// [`event::back_up`].
+    pub fn new(path: impl Into<PathBuf>, backups: usize) -> Self {
+        JsonStore {
+            path: path.into(),
+            backups,
+        }
+    }
+}
+
+impl Store for JsonStore {
+    fn load(&self) -> Result<Option<HashSet<Event>>, StoreError> {
+        if !self.path.exists() {
+            return Ok(None);
+        }
+        event::deserialize_events(&self.path)
+            .map(Some)
+            .map_err(StoreError::File)
+    }
+
+    fn save_diff(&mut self, events: &HashSet<Event>, diff: &Diff) -> Result<(), StoreError> {
+        // Unchanged events are still rewritten for when they were last seen, but backing them up
+        // would only rotate away the useful backups
+        if !diff.is_empty() || !self.path.exists() {
+            if let Err(error) = event::back_up(&self.path, self.backups) {
+                warn!("Failed to back up {:?}: {}", self.path, error);
+            }
+        }
+        event::serialize_events(events, &self.path).map_err(StoreError::File)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::test_util::{event, events};
+
+    #[test]
+    fn load_returns_none_without_file() {
+        let directory = TempDir::new().unwrap();
+        let store = JsonStore::new(directory.path().join("events.json"), 1);
+
+        assert!(store.load().unwrap().is_none());
+    }
+
+    #[test]
+    fn save_diff_then_load() {
+        let directory = TempDir::new().unwrap();
+        let mut store = JsonStore::new(directory.path().join("events.json"), 1);
+        let (old, new) = (HashSet::new(), events(&["event1", "event2"]));
+
+        store.save_diff(&new, &Diff::between(&old, &new)).unwrap();
+
+        assert_eq!(store.load().unwrap(), Some(new));
+    }
+
+    #[test]
+    fn save_diff_skips_unchanged_events() {
+        let directory = TempDir::new().unwrap();
+        let mut store = JsonStore::new(directory.path().join("events.json"), 1);
+        let (old, new) = (HashSet::new(), events(&["event1"]));
+        store.save_diff(&new, &Diff::between(&old, &new)).unwrap();
+
+        store.save_diff(&new, &Diff::between(&new, &new)).unwrap();
+
+        assert!(!directory.path().join("events.json.bak").exists());
+    }
+
+    #[test]
+    fn save_diff_updates_last_seen_of_unchanged_events() {
+        let directory = TempDir::new().unwrap();
+        let mut store = JsonStore::new(directory.path().join("events.json"), 1);
+        let (old, new) = (HashSet::new(), events(&["event1"]));
+        store.save_diff(&new, &Diff::between(&old, &new)).unwrap();
+
+        let mut seen_again = event("event1");
+        seen_again.last_seen += chrono::Duration::hours(1);
+        let seen_again: HashSet<Event> = vec![seen_again].into_iter().collect();
+        store
+            .save_diff(&seen_again, &Diff::between(&new, &seen_again))
+            .unwrap();
+
+        let loaded = store.load().unwrap().unwrap();
+        let last_seen = loaded.iter().next().unwrap().last_seen;
+        assert_eq!(last_seen, seen_again.iter().next().unwrap().last_seen);
+    }
+}
@@ -0,0 +1,53 @@
+mod club;
+mod json;
+mod sqlite;
+
+use std::{
+    collections::HashSet,
+    fmt::{self, Display, Formatter},
+};
+
+use crate::event::{diff::Diff, Event};
+
+pub use self::{club::ClubStore, json::JsonStore, sqlite::SqliteStore};
+
+/// A place to persist the known events in between fetches and restarts.
+pub trait Store {
+    /// Loads the stored events, or `None` if no events have been stored yet.
+    fn load(&self) -> Result<Option<HashSet<Event>>, StoreError>;
+
+    /// Stores `events`, which differ from the previously stored events by `diff`.
+    ///
+    /// This is called after every fetch, even if `diff` is empty.
+    fn save_diff(&mut self, events: &HashSet<Event>, diff: &Diff) -> Result<(), StoreError>;
+}
+
+/// An error representing a failure to load or save events.
+#[derive(Debug)]
+pub enum StoreError {
+    File(Box<dyn std::error::Error>),
+    Serialize(serde_json::Error),
+    Sqlite(rusqlite::Error),
+}
+
+impl Display for StoreError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::File(error) => write!(f, "File error: {}", error),
+            Self::Serialize(error) => write!(f, "Serialization error: {}", error),
+            Self::Sqlite(error) => write!(f, "SQLite error: {}", error),
+        }
+    }
+}
+
+impl From<serde_json::Error> for StoreError {
+    fn from(error: serde_json::Error) -> Self {
+        StoreError::Serialize(error)
+    }
+}
+
+impl From<rusqlite::Error> for StoreError {
+    fn from(error: rusqlite::Error) -> Self {
+        StoreError::Sqlite(error)
+    }
+}
@@ -0,0 +1,191 @@
+use std::{collections::HashSet, path::Path};
+
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+
+use super::{Store, StoreError};
+use crate::event::{diff::Diff, Event};
+
+/// The schema of the events table. The title, date, and class info are stored in separate columns
+/// for querying the database directly, while the full event is stored as JSON in `event`.
+/// Removed events are kept with `removed_at` set, so the table holds the history of all events.
+const CREATE_TABLE: &str = "
+    CREATE TABLE IF NOT EXISTS events (
+        id TEXT PRIMARY KEY,
+        title TEXT NOT NULL,
+        date_time TEXT NOT NULL,
+        class_info TEXT NOT NULL,
+        event TEXT NOT NULL,
+        first_seen TEXT NOT NULL,
+        last_seen TEXT NOT NULL,
+        removed_at TEXT
+    )";
+
+/// A [`Store`] that keeps events in an SQLite database, along with when each event was first and
+/// last seen.
+pub struct SqliteStore {
+    connection: Connection,
+}
+
+impl SqliteStore {
+    /// Opens the database at `path`, creating it and the events table if needed.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, StoreError> {
+        let connection = Connection::open(path)?;
+        connection.execute_batch(CREATE_TABLE)?;
+        Ok(SqliteStore { connection })
+    }
+}
+
+impl Store for SqliteStore {
+    fn load(&self) -> Result<Option<HashSet<Event>>, StoreError> {
+        let any_stored = self
+            .connection
+            .query_row("SELECT 1 FROM events LIMIT 1", params![], |_| Ok(()))
+            .optional()?
+            .is_some();
+        if !any_stored {
+            return Ok(None);
+        }
+
+        let mut statement = self
+            .connection
+            .prepare("SELECT event FROM events WHERE removed_at IS NULL")?;
+        let rows = statement.query_map(params![], |row| row.get::<_, String>(0))?;
+
+        let mut events = HashSet::new();
+        for json in rows {
+            events.insert(serde_json::from_str(&json?)?);
+        }
+        Ok(Some(events))
+    }
+
+    fn save_diff(&mut self, events: &HashSet<Event>, diff: &Diff) -> Result<(), StoreError> {
+        let now = Utc::now().to_rfc3339();
+        let transaction = self.connection.transaction()?;
+
+        let upserted = diff
+            .added
+            .iter()
+            .chain(diff.changed.iter().map(|change| &change.new));
+        for event in upserted {
+            upsert(&transaction, event)?;
+        }
+        for event in &diff.removed {
+            transaction.execute(
+                "UPDATE events SET removed_at = ?2 WHERE id = ?1",
+                params![event.id, now],
+            )?;
+        }
+        // The stored JSON is updated too, so loaded events know when they were last seen
+        for event in events {
+            transaction.execute(
+                "UPDATE events SET last_seen = ?2, event = ?3 WHERE id = ?1",
+                params![
+                    event.id,
+                    event.last_seen.to_rfc3339(),
+                    serde_json::to_string(event)?
+                ],
+            )?;
+        }
+
+        transaction.commit()?;
+        Ok(())
+    }
+}
+
+/// Inserts `event`, or updates it if an event with the same id is already stored.
+fn upsert(transaction: &Transaction, event: &Event) -> Result<(), StoreError> {
+    transaction.execute(
+        "INSERT INTO events (id, title, date_time, class_info, event, first_seen, last_seen)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+         ON CONFLICT (id) DO UPDATE SET
+             title = excluded.title,
+             date_time = excluded.date_time,
+             class_info = excluded.class_info,
+             event = excluded.event,
+             last_seen = excluded.last_seen,
+             removed_at = NULL",
+        params![
+            event.id,
+            event.title,
+            event.date_time.to_rfc3339(),
+            serde_json::to_string(&event.class_info)?,
+            serde_json::to_string(event)?,
+            event.first_seen.to_rfc3339(),
+            event.last_seen.to_rfc3339(),
+        ],
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::test_util::{event, titled_event};
+
+    fn store(directory: &TempDir) -> SqliteStore {
+        SqliteStore::open(directory.path().join("events.db")).unwrap()
+    }
+
+    #[test]
+    fn load_returns_none_for_new_database() {
+        let directory = TempDir::new().unwrap();
+
+        assert!(store(&directory).load().unwrap().is_none());
+    }
+
+    #[test]
+    fn save_diff_then_load() {
+        let directory = TempDir::new().unwrap();
+        let mut store = store(&directory);
+        let old = HashSet::new();
+        let new: HashSet<_> = vec![titled_event("event1", "Tennisskole")]
+            .into_iter()
+            .collect();
+
+        store.save_diff(&new, &Diff::between(&old, &new)).unwrap();
+
+        let loaded = store.load().unwrap().unwrap();
+        let loaded_event = loaded.get(&event("event1")).unwrap();
+        assert_eq!(loaded.len(), 1);
+        assert!(loaded_event.content_eq(&titled_event("event1", "Tennisskole")));
+    }
+
+    #[test]
+    fn save_diff_updates_changed_and_hides_removed_events() {
+        let directory = TempDir::new().unwrap();
+        let mut store = store(&directory);
+        let empty = HashSet::new();
+        let old: HashSet<_> = vec![
+            titled_event("event1", "Tennisskole"),
+            titled_event("event2", "Padel"),
+        ]
+        .into_iter()
+        .collect();
+        let new: HashSet<_> = vec![titled_event("event1", "Tennisskole for øvede")]
+            .into_iter()
+            .collect();
+        store.save_diff(&old, &Diff::between(&empty, &old)).unwrap();
+
+        store.save_diff(&new, &Diff::between(&old, &new)).unwrap();
+
+        let loaded = store.load().unwrap().unwrap();
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(
+            loaded.get(&event("event1")).unwrap().title,
+            "Tennisskole for øvede"
+        );
+
+        let removed_at: Option<String> = store
+            .connection
+            .query_row(
+                "SELECT removed_at FROM events WHERE id = 'event2'",
+                params![],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert!(removed_at.is_some());
+    }
+}
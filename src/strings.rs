@@ -0,0 +1,117 @@
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+/// The languages that notifications about events can be written in.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum Language {
+    #[default]
+    #[serde(rename = "da")]
+    Danish,
+    #[serde(rename = "en")]
+    English,
+}
+
+impl FromStr for Language {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "da" => Ok(Self::Danish),
+            "en" => Ok(Self::English),
+            _ => Err(format!("Unknown language: {}", s)),
+        }
+    }
+}
+
+impl Language {
+    /// Gets the texts of notifications in this language.
+    pub fn strings(self) -> &'static Strings {
+        match self {
+            Self::Danish => &DANISH,
+            Self::English => &ENGLISH,
+        }
+    }
+}
+
+/// The fixed texts of notifications in one language.
+#[derive(Debug)]
+pub struct Strings {
+    /// The title of notifications about new events.
+    pub added_title: &'static str,
+    /// The title of notifications about changed, but no new, events.
+    pub changed_title: &'static str,
+    /// The title of notifications about only removed events.
+    pub removed_title: &'static str,
+    pub added_heading: &'static str,
+    pub removed_heading: &'static str,
+    pub changed_heading: &'static str,
+    pub digest_title: &'static str,
+    pub fully_booked: &'static str,
+    pub one_open_spot: &'static str,
+    /// Follows the number of open spots when there are several, e.g. "3 open spots".
+    pub open_spots: &'static str,
+    /// The title and message of test notifications.
+    pub test: &'static str,
+    pub heartbeat_title: &'static str,
+    /// Follows the number of stored events in heartbeat notifications, e.g. "12 events tracked".
+    pub events_tracked: &'static str,
+    pub fetch_failing_title: &'static str,
+    /// Precedes the number of minutes fetching has failed for, e.g. "Fetching events has failed
+    /// for 30 minutes."
+    pub fetch_failing: &'static str,
+    pub fetch_recovered_title: &'static str,
+    /// Precedes the number of minutes fetching failed for before working again.
+    pub fetch_recovered: &'static str,
+    pub minutes: &'static str,
+    /// The title of the summary sent instead of too many new events at once.
+    pub too_many_new_title: &'static str,
+    /// Follows the number of new events in the summary, e.g. "40 new events detected, ...".
+    pub too_many_new: &'static str,
+}
+
+const DANISH: Strings = Strings {
+    added_title: "Nye tider lagt op!",
+    changed_title: "Tider ændret!",
+    removed_title: "Tider fjernet!",
+    added_heading: "Der er blevet lagt nye tider op",
+    removed_heading: "Disse tider er blevet fjernet",
+    changed_heading: "Disse tider er blevet ændret",
+    digest_title: "Dagens nye tider",
+    fully_booked: "fuldt booket",
+    one_open_spot: "1 ledig plads",
+    open_spots: "ledige pladser",
+    test: "ktkbot test",
+    heartbeat_title: "ktkbot kører stadig",
+    events_tracked: "tider følges",
+    fetch_failing_title: "ktkbot: hentning fejler",
+    fetch_failing: "Hentning af tider har fejlet i",
+    fetch_recovered_title: "ktkbot: hentning virker igen",
+    fetch_recovered: "Hentning af tider virker igen efter at have fejlet i",
+    minutes: "minutter",
+    too_many_new_title: "ktkbot: for mange nye tider",
+    too_many_new: "nye tider fundet, så der sendes ikke besked om hver enkelt.",
+};
+
+const ENGLISH: Strings = Strings {
+    added_title: "New times posted!",
+    changed_title: "Times changed!",
+    removed_title: "Times removed!",
+    added_heading: "New times have been posted",
+    removed_heading: "These times have been removed",
+    changed_heading: "These times have been changed",
+    digest_title: "Today's new times",
+    fully_booked: "fully booked",
+    one_open_spot: "1 open spot",
+    open_spots: "open spots",
+    test: "ktkbot test",
+    heartbeat_title: "ktkbot still running",
+    events_tracked: "events tracked",
+    fetch_failing_title: "ktkbot: fetch failing",
+    fetch_failing: "Fetching events has failed for",
+    fetch_recovered_title: "ktkbot: fetch recovered",
+    fetch_recovered: "Fetching events works again after failing for",
+    minutes: "minutes",
+    too_many_new_title: "ktkbot: too many new events",
+    too_many_new: "new events detected, suppressing individual alerts.",
+};
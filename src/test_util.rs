@@ -0,0 +1,136 @@
+use std::{
+    collections::HashSet,
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use crate::event::{parse::EventParser, Event};
+
+/// A parser that accepts the events of the fixtures, which took place in 2021, no matter the
+/// current year.
+pub fn parser() -> EventParser {
+    EventParser::default().plausible_years(2019..=2023)
+}
+
+/// An event with the given id and otherwise default fields.
+pub fn event(id: &str) -> Event {
+    let mut event = Event::new();
+    event.id = String::from(id);
+    event
+}
+
+/// An event with the given id and title and otherwise default fields.
+pub fn titled_event(id: &str, title: &str) -> Event {
+    let mut event = event(id);
+    event.title = String::from(title);
+    event
+}
+
+/// Events with the given ids and otherwise default fields.
+pub fn events(ids: &[&str]) -> HashSet<Event> {
+    ids.iter().map(|id| event(id)).collect()
+}
+
+/// A canned HTTP response served by a [`MockServer`].
+pub struct MockResponse {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl MockResponse {
+    pub fn new(status: u16, body: impl Into<Vec<u8>>) -> Self {
+        MockResponse {
+            status,
+            headers: Vec::new(),
+            body: body.into(),
+        }
+    }
+
+    /// Adds a header to the response.
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+}
+
+/// A minimal HTTP server that answers each incoming request with the next of a fixed list of
+/// responses. Once all responses have been served, the server stops accepting connections.
+pub struct MockServer {
+    url: String,
+    requests: Arc<Mutex<Vec<String>>>,
+}
+
+impl MockServer {
+    pub fn start(responses: Vec<MockResponse>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let received = Arc::clone(&requests);
+
+        thread::spawn(move || {
+            for response in responses {
+                let mut stream = match listener.accept() {
+                    Ok((stream, _)) => stream,
+                    Err(_) => return,
+                };
+                received.lock().unwrap().push(read_request(&stream));
+                write_response(&mut stream, &response);
+            }
+        });
+
+        MockServer { url, requests }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Gets the requests received so far, each with its request line, headers, and body.
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+fn read_request(stream: &TcpStream) -> String {
+    let mut reader = BufReader::new(stream);
+    let mut request = String::new();
+    let mut content_length = 0;
+
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).unwrap() == 0 {
+            break;
+        }
+        if let Some(value) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+            content_length = value.trim().parse().unwrap();
+        }
+        request.push_str(&line);
+        if line == "\r\n" {
+            break;
+        }
+    }
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).unwrap();
+    request.push_str(&String::from_utf8_lossy(&body));
+    request
+}
+
+fn write_response(stream: &mut TcpStream, response: &MockResponse) {
+    let headers: String = response
+        .headers
+        .iter()
+        .map(|(name, value)| format!("{}: {}\r\n", name, value))
+        .collect();
+    let head = format!(
+        "HTTP/1.1 {} Mock\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n",
+        response.status,
+        headers,
+        response.body.len()
+    );
+    stream.write_all(head.as_bytes()).unwrap();
+    stream.write_all(&response.body).unwrap();
+}
@@ -0,0 +1,95 @@
+use std::collections::HashSet;
+
+use chrono::NaiveDate;
+
+use ktkbot::{
+    event::{diff::Diff, filter},
+    parse_document,
+};
+
+const LISTE: &str = include_str!("fixtures/liste.html");
+
+#[test]
+fn parses_all_well_formed_events() {
+    let (events, errors) = parse_document(LISTE);
+
+    let mut ids: Vec<_> = events.iter().map(|event| event.id.as_str()).collect();
+    ids.sort_unstable();
+    assert_eq!(
+        ids,
+        vec!["hold10412", "hold10413", "hold10420", "hold10431"]
+    );
+    // The cancelled event has no date, so it is skipped
+    assert_eq!(errors.len(), 1);
+}
+
+#[test]
+fn parses_titles_and_dates() {
+    let (events, _) = parse_document(LISTE);
+
+    let mut events: Vec<_> = events
+        .iter()
+        .map(|event| (event.title.as_str(), event.date_time.naive_local()))
+        .collect();
+    events.sort_unstable();
+    assert_eq!(
+        events,
+        vec![
+            (
+                "Juniortræning",
+                NaiveDate::from_ymd(2021, 5, 10).and_hms(16, 0, 0)
+            ),
+            (
+                "Padel intro",
+                NaiveDate::from_ymd(2021, 5, 8).and_hms(10, 0, 0)
+            ),
+            (
+                "Tennisskole for begyndere",
+                NaiveDate::from_ymd(2021, 5, 4).and_hms(18, 0, 0)
+            ),
+            (
+                "Tennisskole for øvede",
+                NaiveDate::from_ymd(2021, 5, 5).and_hms(17, 0, 0)
+            ),
+        ]
+    );
+}
+
+#[test]
+fn parses_class_info_and_location() {
+    let (events, _) = parse_document(LISTE);
+    let event = |id: &str| events.iter().find(|event| event.id == id).unwrap();
+
+    let beginners = event("hold10412");
+    assert_eq!(beginners.class_number.as_deref(), Some("1"));
+    assert_eq!(beginners.instructor.as_deref(), Some("Jens Hansen"));
+    assert_eq!(beginners.price, Some(450));
+    assert_eq!(beginners.available_spots, Some(3));
+    assert_eq!(beginners.location.as_deref(), Some("Bane 1"));
+
+    let advanced = event("hold10413");
+    assert_eq!(advanced.instructor.as_deref(), Some("Mette Larsen"));
+    assert_eq!(advanced.price, Some(1200));
+    assert_eq!(advanced.available_spots, Some(0));
+
+    let padel = event("hold10420");
+    assert_eq!(padel.location, None);
+    assert_eq!(padel.available_spots, None);
+}
+
+#[test]
+fn filters_and_diffs_parsed_events() {
+    let (events, _) = parse_document(LISTE);
+
+    let padel = filter::apply_filters(events.iter().collect(), &["padel".to_string()], &[]);
+    assert_eq!(padel.len(), 1);
+    assert_eq!(padel[0].id, "hold10420");
+
+    let old: HashSet<_> = events
+        .iter()
+        .filter(|event| event.id != "hold10420")
+        .cloned()
+        .collect();
+    let diff = Diff::between(&old, &events);
+    assert_eq!(diff.added[0].id, "hold10420");
+}